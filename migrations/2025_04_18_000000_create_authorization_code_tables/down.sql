@@ -0,0 +1,8 @@
+DROP TABLE IF EXISTS authorization_codes;
+DROP TABLE IF EXISTS oauth_grants;
+
+ALTER TABLE sessions DROP COLUMN IF EXISTS scope;
+ALTER TABLE sessions DROP COLUMN IF EXISTS client_id;
+
+ALTER TABLE clients DROP COLUMN IF EXISTS is_confidential;
+ALTER TABLE clients DROP COLUMN IF EXISTS redirect_uris;
@@ -0,0 +1,28 @@
+ALTER TABLE clients ADD COLUMN redirect_uris TEXT[] NOT NULL DEFAULT '{}';
+ALTER TABLE clients ADD COLUMN is_confidential BOOLEAN NOT NULL DEFAULT TRUE;
+
+ALTER TABLE sessions ADD COLUMN client_id UUID REFERENCES clients(id) ON DELETE CASCADE;
+ALTER TABLE sessions ADD COLUMN scope VARCHAR;
+
+CREATE TABLE oauth_grants (
+    id UUID PRIMARY KEY DEFAULT uuid_generate_v4(),
+    user_id UUID NOT NULL REFERENCES users(id) ON DELETE CASCADE,
+    client_id UUID NOT NULL REFERENCES clients(id) ON DELETE CASCADE,
+    scopes TEXT[] NOT NULL DEFAULT '{}',
+    created_at TIMESTAMPTZ NOT NULL DEFAULT NOW(),
+    updated_at TIMESTAMPTZ NOT NULL DEFAULT NOW(),
+    UNIQUE(user_id, client_id)
+);
+
+CREATE TABLE authorization_codes (
+    code_hash VARCHAR PRIMARY KEY,
+    client_id UUID NOT NULL REFERENCES clients(id) ON DELETE CASCADE,
+    user_id UUID NOT NULL REFERENCES users(id) ON DELETE CASCADE,
+    redirect_uri VARCHAR NOT NULL,
+    scope VARCHAR,
+    code_challenge VARCHAR NOT NULL,
+    code_challenge_method VARCHAR NOT NULL,
+    expires_at TIMESTAMPTZ NOT NULL,
+    used_at TIMESTAMPTZ,
+    created_at TIMESTAMPTZ NOT NULL DEFAULT NOW()
+);
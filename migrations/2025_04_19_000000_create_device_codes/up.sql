@@ -0,0 +1,13 @@
+CREATE TABLE device_codes (
+    device_code_hash VARCHAR PRIMARY KEY,
+    user_code VARCHAR NOT NULL UNIQUE,
+    client_id UUID NOT NULL REFERENCES clients(id) ON DELETE CASCADE,
+    scope VARCHAR,
+    user_id UUID REFERENCES users(id) ON DELETE CASCADE,
+    -- pending / approved / denied / consumed
+    status VARCHAR NOT NULL DEFAULT 'pending',
+    interval_secs INTEGER NOT NULL,
+    last_polled_at TIMESTAMPTZ,
+    expires_at TIMESTAMPTZ NOT NULL,
+    created_at TIMESTAMPTZ NOT NULL DEFAULT NOW()
+);
@@ -0,0 +1,8 @@
+DROP TRIGGER IF EXISTS clients_deleted_notify ON clients;
+DROP TRIGGER IF EXISTS clients_status_notify ON clients;
+DROP TRIGGER IF EXISTS sessions_deleted_notify ON sessions;
+DROP TRIGGER IF EXISTS sessions_revoked_notify ON sessions;
+DROP TRIGGER IF EXISTS revoked_tokens_notify ON revoked_tokens;
+DROP FUNCTION IF EXISTS notify_client_status();
+DROP FUNCTION IF EXISTS notify_session_revoked();
+DROP FUNCTION IF EXISTS notify_token_revoked();
@@ -0,0 +1,72 @@
+-- 吊销事件通过 NOTIFY 广播给所有实例，事务提交后才会送达
+-- 负载格式: <kind>:<id>[:<expires_at_epoch>]
+
+CREATE OR REPLACE FUNCTION notify_token_revoked() RETURNS TRIGGER AS $$
+BEGIN
+    PERFORM pg_notify(
+        'auth_revocations',
+        'jti:' || NEW.jti || ':' || EXTRACT(EPOCH FROM NEW.expires_at)::BIGINT
+    );
+    RETURN NEW;
+END;
+$$ LANGUAGE plpgsql;
+
+CREATE TRIGGER revoked_tokens_notify
+    AFTER INSERT ON revoked_tokens
+    FOR EACH ROW EXECUTE FUNCTION notify_token_revoked();
+
+CREATE OR REPLACE FUNCTION notify_session_revoked() RETURNS TRIGGER AS $$
+BEGIN
+    IF TG_OP = 'DELETE' THEN
+        PERFORM pg_notify(
+            'auth_revocations',
+            'session:' || OLD.id || ':' || EXTRACT(EPOCH FROM OLD.expires_at)::BIGINT
+        );
+        RETURN OLD;
+    END IF;
+
+    PERFORM pg_notify(
+        'auth_revocations',
+        'session:' || NEW.id || ':' || EXTRACT(EPOCH FROM NEW.expires_at)::BIGINT
+    );
+    RETURN NEW;
+END;
+$$ LANGUAGE plpgsql;
+
+CREATE TRIGGER sessions_revoked_notify
+    AFTER UPDATE OF revoked_at ON sessions
+    FOR EACH ROW
+    WHEN (OLD.revoked_at IS NULL AND NEW.revoked_at IS NOT NULL)
+    EXECUTE FUNCTION notify_session_revoked();
+
+CREATE TRIGGER sessions_deleted_notify
+    AFTER DELETE ON sessions
+    FOR EACH ROW
+    WHEN (OLD.revoked_at IS NULL)
+    EXECUTE FUNCTION notify_session_revoked();
+
+CREATE OR REPLACE FUNCTION notify_client_status() RETURNS TRIGGER AS $$
+BEGIN
+    IF TG_OP = 'DELETE' THEN
+        PERFORM pg_notify('auth_revocations', 'client:' || OLD.id);
+        RETURN OLD;
+    END IF;
+
+    IF NEW.is_active THEN
+        PERFORM pg_notify('auth_revocations', 'client_restored:' || NEW.id);
+    ELSE
+        PERFORM pg_notify('auth_revocations', 'client:' || NEW.id);
+    END IF;
+    RETURN NEW;
+END;
+$$ LANGUAGE plpgsql;
+
+CREATE TRIGGER clients_status_notify
+    AFTER UPDATE OF is_active ON clients
+    FOR EACH ROW
+    WHEN (OLD.is_active IS DISTINCT FROM NEW.is_active)
+    EXECUTE FUNCTION notify_client_status();
+
+CREATE TRIGGER clients_deleted_notify
+    AFTER DELETE ON clients
+    FOR EACH ROW EXECUTE FUNCTION notify_client_status();
@@ -0,0 +1,5 @@
+DROP TRIGGER IF EXISTS token_epochs_notify ON token_epochs;
+DROP TRIGGER IF EXISTS users_token_epoch_notify ON users;
+DROP FUNCTION IF EXISTS notify_token_epoch();
+DROP TABLE IF EXISTS token_epochs;
+ALTER TABLE users DROP COLUMN IF EXISTS tokens_valid_after;
@@ -0,0 +1,36 @@
+-- 令牌有效起点：早于该时间签发的令牌一律失效，无需更换签名密钥
+ALTER TABLE users ADD COLUMN tokens_valid_after TIMESTAMPTZ;
+
+-- 全局起点，仅有一行
+CREATE TABLE token_epochs (
+    id BOOLEAN PRIMARY KEY DEFAULT TRUE CHECK (id),
+    tokens_valid_after TIMESTAMPTZ NOT NULL,
+    updated_at TIMESTAMPTZ NOT NULL DEFAULT NOW()
+);
+
+CREATE OR REPLACE FUNCTION notify_token_epoch() RETURNS TRIGGER AS $$
+BEGIN
+    IF TG_TABLE_NAME = 'users' THEN
+        PERFORM pg_notify(
+            'auth_revocations',
+            'user:' || NEW.id || ':' || EXTRACT(EPOCH FROM NEW.tokens_valid_after)::BIGINT
+        );
+    ELSE
+        PERFORM pg_notify(
+            'auth_revocations',
+            'global:' || EXTRACT(EPOCH FROM NEW.tokens_valid_after)::BIGINT
+        );
+    END IF;
+    RETURN NEW;
+END;
+$$ LANGUAGE plpgsql;
+
+CREATE TRIGGER users_token_epoch_notify
+    AFTER UPDATE OF tokens_valid_after ON users
+    FOR EACH ROW
+    WHEN (NEW.tokens_valid_after IS NOT NULL)
+    EXECUTE FUNCTION notify_token_epoch();
+
+CREATE TRIGGER token_epochs_notify
+    AFTER INSERT OR UPDATE ON token_epochs
+    FOR EACH ROW EXECUTE FUNCTION notify_token_epoch();
@@ -0,0 +1,3 @@
+ALTER TABLE roles DROP COLUMN IF EXISTS max_password_age_days;
+ALTER TABLE users DROP COLUMN IF EXISTS must_change_password;
+ALTER TABLE users DROP COLUMN IF EXISTS password_changed_at;
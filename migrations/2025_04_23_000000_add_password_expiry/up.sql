@@ -0,0 +1,6 @@
+ALTER TABLE users ADD COLUMN password_changed_at TIMESTAMPTZ NOT NULL DEFAULT NOW();
+-- 管理员强制或密码过期后，下次登录只能修改密码
+ALTER TABLE users ADD COLUMN must_change_password BOOLEAN NOT NULL DEFAULT FALSE;
+
+-- 角色的密码最长使用天数，用户有多个角色时取最严格的
+ALTER TABLE roles ADD COLUMN max_password_age_days INTEGER CHECK (max_password_age_days > 0);
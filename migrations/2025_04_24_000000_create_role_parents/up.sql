@@ -0,0 +1,11 @@
+-- 角色继承关系：role_id 继承 parent_role_id 的全部权限
+CREATE TABLE role_parents (
+    id UUID PRIMARY KEY DEFAULT uuid_generate_v4(),
+    role_id UUID NOT NULL REFERENCES roles(id) ON DELETE CASCADE,
+    parent_role_id UUID NOT NULL REFERENCES roles(id) ON DELETE CASCADE,
+    created_at TIMESTAMPTZ NOT NULL DEFAULT NOW(),
+    UNIQUE(role_id, parent_role_id),
+    CHECK (role_id <> parent_role_id)
+);
+
+CREATE INDEX idx_role_parents_parent_role_id ON role_parents(parent_role_id);
@@ -0,0 +1,5 @@
+DROP INDEX IF EXISTS idx_permissions_action;
+
+ALTER TABLE permissions
+    DROP CONSTRAINT IF EXISTS permissions_resource_pattern,
+    DROP CONSTRAINT IF EXISTS permissions_action_pattern;
@@ -0,0 +1,7 @@
+-- 权限的资源和操作可以是通配模式：`*` 只能作为完整的段，`:` 保留给作用域分隔
+ALTER TABLE permissions
+    ADD CONSTRAINT permissions_resource_pattern CHECK (resource ~ '^(\*|[^*:/]+)(/(\*|[^*:/]+))*$'),
+    ADD CONSTRAINT permissions_action_pattern CHECK (action ~ '^(\*|[^*:/]+)$');
+
+-- 按操作筛选候选权限
+CREATE INDEX idx_permissions_action ON permissions(action);
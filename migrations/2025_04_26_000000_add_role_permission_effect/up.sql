@@ -0,0 +1,3 @@
+-- 授权效果：deny 优先于 allow，用于显式禁止其他角色授予的权限
+ALTER TABLE role_permissions
+    ADD COLUMN effect VARCHAR NOT NULL DEFAULT 'allow' CHECK (effect IN ('allow', 'deny'));
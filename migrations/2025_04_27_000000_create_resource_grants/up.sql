@@ -0,0 +1,18 @@
+-- 资源实例级授权：对某个具体对象（resource_type + resource_id）授予用户或角色操作权限
+CREATE TABLE resource_grants (
+    id UUID PRIMARY KEY DEFAULT uuid_generate_v4(),
+    resource_type VARCHAR NOT NULL,
+    resource_id VARCHAR NOT NULL,
+    action VARCHAR NOT NULL CHECK (action ~ '^(\*|[^*:/]+)$'),
+    user_id UUID REFERENCES users(id) ON DELETE CASCADE,
+    role_id UUID REFERENCES roles(id) ON DELETE CASCADE,
+    effect VARCHAR NOT NULL DEFAULT 'allow' CHECK (effect IN ('allow', 'deny')),
+    created_at TIMESTAMPTZ NOT NULL DEFAULT NOW(),
+    -- 授权对象必须且只能是用户或角色之一
+    CHECK ((user_id IS NULL) <> (role_id IS NULL))
+);
+
+CREATE UNIQUE INDEX idx_resource_grants_user
+    ON resource_grants(resource_type, resource_id, action, user_id) WHERE user_id IS NOT NULL;
+CREATE UNIQUE INDEX idx_resource_grants_role
+    ON resource_grants(resource_type, resource_id, action, role_id) WHERE role_id IS NOT NULL;
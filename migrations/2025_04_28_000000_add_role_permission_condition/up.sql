@@ -0,0 +1,2 @@
+-- 授权条件表达式，为空表示无条件生效
+ALTER TABLE role_permissions ADD COLUMN condition VARCHAR;
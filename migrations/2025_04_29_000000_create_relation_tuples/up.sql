@@ -0,0 +1,20 @@
+-- 关系元组 `namespace:object_id#relation@subject`，subject 为 `namespace:object_id`
+-- 或用户集 `namespace:object_id#relation`
+CREATE TABLE relation_tuples (
+    id UUID PRIMARY KEY DEFAULT uuid_generate_v4(),
+    namespace VARCHAR NOT NULL,
+    object_id VARCHAR NOT NULL,
+    relation VARCHAR NOT NULL,
+    subject_namespace VARCHAR NOT NULL,
+    subject_object_id VARCHAR NOT NULL,
+    subject_relation VARCHAR,
+    created_at TIMESTAMPTZ NOT NULL DEFAULT NOW()
+);
+
+-- 唯一约束同时用于按对象和关系正向查找
+CREATE UNIQUE INDEX idx_relation_tuples_unique ON relation_tuples(
+    namespace, object_id, relation, subject_namespace, subject_object_id, COALESCE(subject_relation, '')
+);
+-- 按主体反向查找，用于 list_objects
+CREATE INDEX idx_relation_tuples_subject
+    ON relation_tuples(subject_namespace, subject_object_id, subject_relation);
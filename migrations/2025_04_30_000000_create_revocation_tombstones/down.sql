@@ -0,0 +1,42 @@
+CREATE OR REPLACE FUNCTION notify_session_revoked() RETURNS TRIGGER AS $$
+BEGIN
+    IF TG_OP = 'DELETE' THEN
+        PERFORM pg_notify(
+            'auth_revocations',
+            'session:' || OLD.id || ':' || EXTRACT(EPOCH FROM OLD.expires_at)::BIGINT
+        );
+        RETURN OLD;
+    END IF;
+
+    PERFORM pg_notify(
+        'auth_revocations',
+        'session:' || NEW.id || ':' || EXTRACT(EPOCH FROM NEW.expires_at)::BIGINT
+    );
+    RETURN NEW;
+END;
+$$ LANGUAGE plpgsql;
+
+DROP TRIGGER IF EXISTS sessions_deleted_notify ON sessions;
+CREATE TRIGGER sessions_deleted_notify
+    AFTER DELETE ON sessions
+    FOR EACH ROW
+    WHEN (OLD.revoked_at IS NULL)
+    EXECUTE FUNCTION notify_session_revoked();
+
+CREATE OR REPLACE FUNCTION notify_client_status() RETURNS TRIGGER AS $$
+BEGIN
+    IF TG_OP = 'DELETE' THEN
+        PERFORM pg_notify('auth_revocations', 'client:' || OLD.id);
+        RETURN OLD;
+    END IF;
+
+    IF NEW.is_active THEN
+        PERFORM pg_notify('auth_revocations', 'client_restored:' || NEW.id);
+    ELSE
+        PERFORM pg_notify('auth_revocations', 'client:' || NEW.id);
+    END IF;
+    RETURN NEW;
+END;
+$$ LANGUAGE plpgsql;
+
+DROP TABLE IF EXISTS revocation_tombstones;
@@ -0,0 +1,61 @@
+-- 被删除的会话和服务客户端的墓碑记录
+--
+-- 删除通知只在连接期间送达，实例重连后从该表重新加载，避免已删除会话或客户端的令牌重新被接受。
+-- valid_until 之后相关令牌最多再存活一个访问令牌有效期：会话为其过期时间，客户端为删除时间。
+CREATE TABLE revocation_tombstones (
+    kind VARCHAR NOT NULL CHECK (kind IN ('session', 'client')),
+    id UUID NOT NULL,
+    valid_until TIMESTAMPTZ NOT NULL,
+    created_at TIMESTAMPTZ NOT NULL DEFAULT NOW(),
+    PRIMARY KEY (kind, id)
+);
+
+CREATE INDEX idx_revocation_tombstones_valid_until ON revocation_tombstones(valid_until);
+
+CREATE OR REPLACE FUNCTION notify_session_revoked() RETURNS TRIGGER AS $$
+BEGIN
+    IF TG_OP = 'DELETE' THEN
+        INSERT INTO revocation_tombstones (kind, id, valid_until)
+        VALUES ('session', OLD.id, OLD.expires_at)
+        ON CONFLICT DO NOTHING;
+
+        PERFORM pg_notify(
+            'auth_revocations',
+            'session:' || OLD.id || ':' || EXTRACT(EPOCH FROM OLD.expires_at)::BIGINT
+        );
+        RETURN OLD;
+    END IF;
+
+    PERFORM pg_notify(
+        'auth_revocations',
+        'session:' || NEW.id || ':' || EXTRACT(EPOCH FROM NEW.expires_at)::BIGINT
+    );
+    RETURN NEW;
+END;
+$$ LANGUAGE plpgsql;
+
+-- 已吊销的会话被删除后同样不能再出现在快照之外，删除时一律记录墓碑
+DROP TRIGGER IF EXISTS sessions_deleted_notify ON sessions;
+CREATE TRIGGER sessions_deleted_notify
+    AFTER DELETE ON sessions
+    FOR EACH ROW EXECUTE FUNCTION notify_session_revoked();
+
+CREATE OR REPLACE FUNCTION notify_client_status() RETURNS TRIGGER AS $$
+BEGIN
+    IF TG_OP = 'DELETE' THEN
+        INSERT INTO revocation_tombstones (kind, id, valid_until)
+        VALUES ('client', OLD.id, NOW())
+        ON CONFLICT DO NOTHING;
+
+        PERFORM pg_notify('auth_revocations', 'client:' || OLD.id);
+        RETURN OLD;
+    END IF;
+
+    IF NEW.is_active THEN
+        PERFORM pg_notify('auth_revocations', 'client_restored:' || NEW.id);
+    ELSE
+        PERFORM pg_notify('auth_revocations', 'client:' || NEW.id);
+    END IF;
+    RETURN NEW;
+END;
+$$ LANGUAGE plpgsql;
@@ -0,0 +1,21 @@
+CREATE OR REPLACE FUNCTION notify_session_revoked() RETURNS TRIGGER AS $$
+BEGIN
+    IF TG_OP = 'DELETE' THEN
+        INSERT INTO revocation_tombstones (kind, id, valid_until)
+        VALUES ('session', OLD.id, OLD.expires_at)
+        ON CONFLICT DO NOTHING;
+
+        PERFORM pg_notify(
+            'auth_revocations',
+            'session:' || OLD.id || ':' || EXTRACT(EPOCH FROM OLD.expires_at)::BIGINT
+        );
+        RETURN OLD;
+    END IF;
+
+    PERFORM pg_notify(
+        'auth_revocations',
+        'session:' || NEW.id || ':' || EXTRACT(EPOCH FROM NEW.expires_at)::BIGINT
+    );
+    RETURN NEW;
+END;
+$$ LANGUAGE plpgsql;
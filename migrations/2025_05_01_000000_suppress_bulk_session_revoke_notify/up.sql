@@ -0,0 +1,27 @@
+-- 紧急吊销时令牌有效起点已覆盖所有会话，批量吊销会话的事务设置
+-- auth.suppress_session_notify 以跳过逐行通知，避免每个实例为每个会话缓存一条记录
+CREATE OR REPLACE FUNCTION notify_session_revoked() RETURNS TRIGGER AS $$
+BEGIN
+    IF TG_OP = 'DELETE' THEN
+        INSERT INTO revocation_tombstones (kind, id, valid_until)
+        VALUES ('session', OLD.id, OLD.expires_at)
+        ON CONFLICT DO NOTHING;
+
+        PERFORM pg_notify(
+            'auth_revocations',
+            'session:' || OLD.id || ':' || EXTRACT(EPOCH FROM OLD.expires_at)::BIGINT
+        );
+        RETURN OLD;
+    END IF;
+
+    IF current_setting('auth.suppress_session_notify', true) = 'on' THEN
+        RETURN NEW;
+    END IF;
+
+    PERFORM pg_notify(
+        'auth_revocations',
+        'session:' || NEW.id || ':' || EXTRACT(EPOCH FROM NEW.expires_at)::BIGINT
+    );
+    RETURN NEW;
+END;
+$$ LANGUAGE plpgsql;
@@ -0,0 +1,2 @@
+-- 授权码换取的会话，授权码被重放时据此吊销已签发的令牌（RFC 6749 第4.1.2节）
+ALTER TABLE authorization_codes ADD COLUMN session_id UUID REFERENCES sessions(id) ON DELETE SET NULL;
@@ -0,0 +1,106 @@
+use actix_web::{web, HttpResponse, Responder};
+use uuid::Uuid;
+
+use crate::api_keys::models::CreateApiKeyRequest;
+use crate::api_keys::services::{create_client_api_key, create_user_api_key, list_user_api_keys, revoke_user_api_key};
+use crate::auth::models::{Principal, PrincipalKind};
+use crate::db::{run_blocking, DbPool};
+use crate::errors::ServiceError;
+
+pub async fn create_api_key_handler(
+    pool: web::Data<DbPool>,
+    principal: Principal,
+    key_data: web::Json<CreateApiKeyRequest>,
+) -> impl Responder {
+    let key_data = key_data.into_inner();
+
+    if principal.kind != PrincipalKind::User {
+        return HttpResponse::Forbidden().json("Only users can own personal API keys");
+    }
+
+    // 受作用域限制的调用方不能签发超出自身作用域的密钥，通配作用域只能由同样覆盖它的作用域签发
+    if principal.scopes.is_some() {
+        let within_caller_scopes = key_data.scopes.iter().all(|scope| {
+            scope
+                .split_once(':')
+                .is_some_and(|(resource, action)| principal.scope_allows(resource, action))
+        });
+        if !within_caller_scopes {
+            return HttpResponse::Forbidden().json("Requested scopes exceed caller scopes");
+        }
+    }
+
+    // 创建API密钥
+    match run_blocking(&pool, move |conn| create_user_api_key(conn, principal.id, key_data)).await {
+        Ok(api_key) => HttpResponse::Created().json(api_key),
+        Err(e) => {
+            eprintln!("Error creating API key: {:?}", e);
+            match e {
+                ServiceError::BadRequest(msg) => HttpResponse::BadRequest().json(msg),
+                _ => HttpResponse::InternalServerError().json("Failed to create API key")
+            }
+        }
+    }
+}
+
+pub async fn list_api_keys_handler(
+    pool: web::Data<DbPool>,
+    principal: Principal,
+) -> impl Responder {
+    // 获取API密钥列表
+    match run_blocking(&pool, move |conn| list_user_api_keys(conn, principal.id)).await {
+        Ok(api_keys) => HttpResponse::Ok().json(api_keys),
+        Err(e) => {
+            eprintln!("Error listing API keys: {:?}", e);
+            HttpResponse::InternalServerError().json("Failed to list API keys")
+        }
+    }
+}
+
+pub async fn revoke_api_key_handler(
+    pool: web::Data<DbPool>,
+    principal: Principal,
+    key_id: web::Path<Uuid>,
+) -> impl Responder {
+    let key_id = key_id.into_inner();
+
+    // 吊销API密钥
+    match run_blocking(&pool, move |conn| revoke_user_api_key(conn, principal.id, key_id)).await {
+        Ok(()) => HttpResponse::NoContent().finish(),
+        Err(e) => {
+            eprintln!("Error revoking API key: {:?}", e);
+            match e {
+                ServiceError::NotFound(msg) => HttpResponse::NotFound().json(msg),
+                _ => HttpResponse::InternalServerError().json("Failed to revoke API key")
+            }
+        }
+    }
+}
+
+pub async fn create_client_api_key_handler(
+    pool: web::Data<DbPool>,
+    principal: Principal,
+    client_id: web::Path<Uuid>,
+    key_data: web::Json<CreateApiKeyRequest>,
+) -> impl Responder {
+    let client_id = client_id.into_inner();
+    let key_data = key_data.into_inner();
+
+    // 服务客户端密钥携带该客户端的全部角色，只能由登录用户本人签发，API密钥和第三方应用令牌不可用
+    if !principal.is_interactive_user() {
+        return HttpResponse::Forbidden().json("Only signed-in users can create client API keys");
+    }
+
+    // 创建服务客户端API密钥
+    match run_blocking(&pool, move |conn| create_client_api_key(conn, client_id, key_data)).await {
+        Ok(api_key) => HttpResponse::Created().json(api_key),
+        Err(e) => {
+            eprintln!("Error creating client API key: {:?}", e);
+            match e {
+                ServiceError::BadRequest(msg) => HttpResponse::BadRequest().json(msg),
+                ServiceError::NotFound(msg) => HttpResponse::NotFound().json(msg),
+                _ => HttpResponse::InternalServerError().json("Failed to create API key")
+            }
+        }
+    }
+}
@@ -0,0 +1,187 @@
+use chrono::{Duration, Utc};
+use diesel::prelude::*;
+use uuid::Uuid;
+
+use crate::api_keys::models::*;
+use crate::auth::models::Principal;
+use crate::errors::ServiceError;
+use crate::utils::crypto::{constant_time_eq, generate_random_hex, generate_random_token, sha256_hex};
+
+// 密钥格式：sak_<12位十六进制前缀>_<随机密文>
+const KEY_MARKER: &str = "sak_";
+const PREFIX_HEX_LEN: usize = 12;
+
+// last_used_at 的最小更新间隔，避免每次请求都写库
+const LAST_USED_UPDATE_INTERVAL_SECS: i64 = 60;
+
+fn insert_api_key(
+    db: &mut PgConnection,
+    owner_user_id: Option<Uuid>,
+    owner_client_id: Option<Uuid>,
+    key_data: CreateApiKeyRequest,
+) -> Result<CreateApiKeyResponse, ServiceError> {
+    use crate::db::schema::api_keys;
+
+    if let Some(expires_at) = key_data.expires_at {
+        if expires_at <= Utc::now() {
+            return Err(ServiceError::BadRequest("expires_at must be in the future".to_string()));
+        }
+    }
+
+    let prefix = format!("{}{}", KEY_MARKER, generate_random_hex(PREFIX_HEX_LEN / 2));
+    let key = format!("{}_{}", prefix, generate_random_token(32));
+
+    let new_api_key = NewApiKey {
+        user_id: owner_user_id,
+        client_id: owner_client_id,
+        name: key_data.name,
+        prefix,
+        key_hash: sha256_hex(&key),
+        scopes: key_data.scopes,
+        expires_at: key_data.expires_at,
+    };
+
+    let api_key = diesel::insert_into(api_keys::table)
+        .values(&new_api_key)
+        .get_result::<ApiKey>(db)?;
+
+    Ok(CreateApiKeyResponse {
+        id: api_key.id,
+        name: api_key.name,
+        key,
+        prefix: api_key.prefix,
+        scopes: api_key.scopes,
+        expires_at: api_key.expires_at,
+    })
+}
+
+// 为用户创建API密钥
+pub fn create_user_api_key(
+    db: &mut PgConnection,
+    owner_id: Uuid,
+    key_data: CreateApiKeyRequest,
+) -> Result<CreateApiKeyResponse, ServiceError> {
+    insert_api_key(db, Some(owner_id), None, key_data)
+}
+
+// 为服务客户端创建API密钥
+pub fn create_client_api_key(
+    db: &mut PgConnection,
+    owner_id: Uuid,
+    key_data: CreateApiKeyRequest,
+) -> Result<CreateApiKeyResponse, ServiceError> {
+    use crate::db::schema::clients;
+
+    let client_exists: i64 = clients::table
+        .filter(clients::id.eq(owner_id))
+        .select(diesel::dsl::count_star())
+        .first(db)?;
+
+    if client_exists == 0 {
+        return Err(ServiceError::NotFound("Client not found".to_string()));
+    }
+
+    insert_api_key(db, None, Some(owner_id), key_data)
+}
+
+// 获取用户的所有API密钥
+pub fn list_user_api_keys(
+    db: &mut PgConnection,
+    owner_id: Uuid,
+) -> Result<Vec<ApiKey>, ServiceError> {
+    use crate::db::schema::api_keys;
+
+    let keys = api_keys::table
+        .filter(api_keys::user_id.eq(owner_id))
+        .order(api_keys::created_at.desc())
+        .load::<ApiKey>(db)?;
+
+    Ok(keys)
+}
+
+// 吊销用户自己的API密钥
+pub fn revoke_user_api_key(
+    db: &mut PgConnection,
+    owner_id: Uuid,
+    key_id: Uuid,
+) -> Result<(), ServiceError> {
+    use crate::db::schema::api_keys;
+
+    let updated = diesel::update(
+        api_keys::table
+            .filter(api_keys::id.eq(key_id))
+            .filter(api_keys::user_id.eq(owner_id)),
+    )
+    .set(api_keys::revoked_at.eq(diesel::dsl::now))
+    .execute(db)?;
+
+    if updated == 0 {
+        return Err(ServiceError::NotFound("API key not found".to_string()));
+    }
+
+    Ok(())
+}
+
+// 校验API密钥并返回其所属主体
+pub fn authenticate_api_key(
+    db: &mut PgConnection,
+    key: &str,
+) -> Result<Principal, ServiceError> {
+    use crate::db::schema::{api_keys, clients, users};
+
+    let prefix_len = KEY_MARKER.len() + PREFIX_HEX_LEN;
+    if !key.starts_with(KEY_MARKER) || key.len() <= prefix_len {
+        return Err(ServiceError::InvalidToken);
+    }
+
+    let api_key = api_keys::table
+        .filter(api_keys::prefix.eq(&key[..prefix_len]))
+        .first::<ApiKey>(db)
+        .optional()?
+        .ok_or(ServiceError::InvalidToken)?;
+
+    if !constant_time_eq(&sha256_hex(key), &api_key.key_hash) {
+        return Err(ServiceError::InvalidToken);
+    }
+
+    let now = Utc::now();
+    if api_key.revoked_at.is_some() || api_key.expires_at.is_some_and(|exp| exp <= now) {
+        return Err(ServiceError::InvalidToken);
+    }
+
+    // 所属用户或客户端必须仍处于启用状态
+    let principal = match (api_key.user_id, api_key.client_id) {
+        (Some(owner_id), None) => {
+            let is_active = users::table
+                .find(owner_id)
+                .select(users::is_active)
+                .first::<bool>(db)?;
+            if !is_active {
+                return Err(ServiceError::InvalidToken);
+            }
+            Principal::user(owner_id, Some(api_key.scopes))
+        }
+        (None, Some(owner_id)) => {
+            let is_active = clients::table
+                .find(owner_id)
+                .select(clients::is_active)
+                .first::<bool>(db)?;
+            if !is_active {
+                return Err(ServiceError::InvalidToken);
+            }
+            Principal::client(owner_id, Some(api_key.scopes))
+        }
+        _ => return Err(ServiceError::InvalidToken),
+    };
+
+    let stale = api_key
+        .last_used_at
+        .is_none_or(|last| now - last > Duration::seconds(LAST_USED_UPDATE_INTERVAL_SECS));
+    if stale {
+        diesel::update(api_keys::table.find(api_key.id))
+            .set(api_keys::last_used_at.eq(now))
+            .execute(db)?;
+    }
+
+    Ok(principal)
+}
@@ -1,10 +1,36 @@
-use actix_web::{web, HttpResponse, Responder};
+use actix_web::{web, HttpRequest, HttpResponse, Responder, ResponseError};
 use uuid::Uuid;
 
-use crate::auth::models::{LoginRequest, RegisterRequest};
-use crate::auth::services::{get_user_by_id, login_user, register_user};
-use crate::db::DbPool;
+use crate::auth::models::{
+    ChangePasswordRequest, CookieSessionResponse, LoginOutcome, LoginQuery, LoginRequest, LoginResponse, Principal,
+    RegisterRequest, SetPasswordRequest,
+};
+use crate::auth::services::{
+    admin_set_password, change_password, force_role_password_change, force_user_password_change, get_user_by_id,
+    login_user, register_user,
+};
+use crate::config::CONFIG;
+use crate::db::{run_blocking, DbPool};
 use crate::errors::ServiceError;
+use crate::oauth::services::{refresh_access_token, revoke_token};
+use crate::utils::password::acquire_hash_permit;
+use crate::utils::cookies::{cleared_session_cookies, session_cookies, verify_csrf};
+
+// 以浏览器会话Cookie的形式返回登录结果
+fn cookie_session_response(response: LoginResponse) -> HttpResponse {
+    let (cookies, csrf_token) = session_cookies(&response.token, &response.refresh_token);
+
+    let mut builder = HttpResponse::Ok();
+    for cookie in cookies {
+        builder.cookie(cookie);
+    }
+
+    builder.json(CookieSessionResponse {
+        user_id: response.user_id,
+        email: response.email,
+        csrf_token,
+    })
+}
 
 pub async fn register_handler(
     pool: web::Data<DbPool>,
@@ -12,43 +38,39 @@ pub async fn register_handler(
 ) -> impl Responder {
     let register_data = register_data.into_inner();
     
-    // 获取数据库连接
-    let mut conn = match pool.get() {
-        Ok(conn) => conn,
-        Err(e) => {
-            eprintln!("Database connection error: {:?}", e);
-            return HttpResponse::InternalServerError().json("Database connection error");
-        }
-    };
-    
     // 注册用户
-    match register_user(&mut conn, register_data).await {
+    match register_user(&pool, register_data).await {
         Ok(response) => HttpResponse::Created().json(response),
         Err(e) => {
             eprintln!("Error registering user: {:?}", e);
-            HttpResponse::InternalServerError().json("Failed to register user")
+            match e {
+                ServiceError::ValidationError(_) | ServiceError::ServiceUnavailable(_) => e.error_response(),
+                _ => HttpResponse::InternalServerError().json("Failed to register user")
+            }
         }
     }
 }
 
 pub async fn login_handler(
     pool: web::Data<DbPool>,
+    query: web::Query<LoginQuery>,
     login_data: web::Json<LoginRequest>,
 ) -> impl Responder {
     let login_data = login_data.into_inner();
+    let cookie_mode = query.mode.as_deref() == Some("cookie");
     
-    // 获取数据库连接
-    let mut conn = match pool.get() {
-        Ok(conn) => conn,
-        Err(e) => {
-            eprintln!("Database connection error: {:?}", e);
-            return HttpResponse::InternalServerError().json("Database connection error");
-        }
+    // 限制并发哈希，过载时快速返回503
+    let _permit = match acquire_hash_permit().await {
+        Ok(permit) => permit,
+        Err(e) => return e.error_response(),
     };
     
-    // 登录用户
-    match login_user(&mut conn, login_data).await {
-        Ok(response) => HttpResponse::Ok().json(response),
+    // 登录用户，密码验证和数据库操作在阻塞线程池中执行
+    match run_blocking(&pool, move |conn| login_user(conn, login_data)).await {
+        Ok(LoginOutcome::Session(response)) if cookie_mode => cookie_session_response(response),
+        Ok(LoginOutcome::Session(response)) => HttpResponse::Ok().json(response),
+        // 需要修改密码时两种模式都以JSON返回受限令牌，不设置会话Cookie
+        Ok(LoginOutcome::PasswordChangeRequired(response)) => HttpResponse::Ok().json(response),
         Err(e) => {
             eprintln!("Error logging in: {:?}", e);
             if let ServiceError::InvalidCredentials = e {
@@ -66,17 +88,8 @@ pub async fn get_user_handler(
 ) -> impl Responder {
     let user_id = user_id.into_inner();
     
-    // 获取数据库连接
-    let mut conn = match pool.get() {
-        Ok(conn) => conn,
-        Err(e) => {
-            eprintln!("Database connection error: {:?}", e);
-            return HttpResponse::InternalServerError().json("Database connection error");
-        }
-    };
-    
     // 获取用户信息
-    match get_user_by_id(&mut conn, user_id).await {
+    match run_blocking(&pool, move |conn| get_user_by_id(conn, user_id)).await {
         Ok(user) => HttpResponse::Ok().json(user),
         Err(e) => {
             eprintln!("Error getting user: {:?}", e);
@@ -84,3 +97,160 @@ pub async fn get_user_handler(
         }
     }
 }
+
+pub async fn refresh_session_handler(
+    req: HttpRequest,
+    pool: web::Data<DbPool>,
+) -> impl Responder {
+    if !verify_csrf(&req) {
+        return HttpResponse::Forbidden().json("CSRF token mismatch");
+    }
+
+    let refresh_token = match req.cookie(&CONFIG.refresh_cookie_name) {
+        Some(cookie) => cookie.value().to_string(),
+        None => return HttpResponse::Unauthorized().json("Missing session"),
+    };
+
+    // 轮换刷新令牌并重新设置会话Cookie
+    match run_blocking(&pool, move |conn| refresh_access_token(conn, &refresh_token, None)).await {
+        Ok(tokens) => {
+            let refresh_token = tokens.refresh_token.unwrap_or_default();
+            let (cookies, csrf_token) = session_cookies(&tokens.access_token, &refresh_token);
+
+            let mut builder = HttpResponse::Ok();
+            for cookie in cookies {
+                builder.cookie(cookie);
+            }
+            builder.json(serde_json::json!({ "csrf_token": csrf_token }))
+        }
+        Err(e) => {
+            eprintln!("Error refreshing session: {:?}", e);
+            match e {
+                ServiceError::OAuthError(_) => HttpResponse::Unauthorized().json("Invalid session"),
+                _ => HttpResponse::InternalServerError().json("Failed to refresh session")
+            }
+        }
+    }
+}
+
+pub async fn logout_handler(
+    req: HttpRequest,
+    pool: web::Data<DbPool>,
+) -> impl Responder {
+    if !verify_csrf(&req) {
+        return HttpResponse::Forbidden().json("CSRF token mismatch");
+    }
+
+    if let Some(cookie) = req.cookie(&CONFIG.refresh_cookie_name) {
+        let refresh_token = cookie.value().to_string();
+
+        // 吊销会话，其下的访问令牌随之失效
+        let result = run_blocking(&pool, move |conn| revoke_token(conn, &refresh_token, Some("refresh_token"), None)).await;
+        if let Err(e) = result {
+            eprintln!("Error revoking session: {:?}", e);
+            return HttpResponse::InternalServerError().json("Failed to logout");
+        }
+    }
+
+    let mut builder = HttpResponse::NoContent();
+    for cookie in cleared_session_cookies() {
+        builder.cookie(cookie);
+    }
+    builder.finish()
+}
+
+pub async fn change_password_handler(
+    pool: web::Data<DbPool>,
+    principal: Principal,
+    password_data: web::Json<ChangePasswordRequest>,
+) -> impl Responder {
+    let password_data = password_data.into_inner();
+
+    // 仅限用户本人登录后修改，API密钥和第三方应用令牌不可用
+    if !principal.is_interactive_user() {
+        return HttpResponse::Forbidden().json("Only signed-in users can change their password");
+    }
+
+    // 限制并发哈希，过载时快速返回503
+    let _permit = match acquire_hash_permit().await {
+        Ok(permit) => permit,
+        Err(e) => return e.error_response(),
+    };
+
+    // 修改密码
+    match run_blocking(&pool, move |conn| change_password(conn, principal.id, password_data)).await {
+        Ok(()) => HttpResponse::NoContent().finish(),
+        Err(e) => {
+            eprintln!("Error changing password: {:?}", e);
+            match e {
+                ServiceError::InvalidCredentials | ServiceError::ValidationError(_) => e.error_response(),
+                _ => HttpResponse::InternalServerError().json("Failed to change password")
+            }
+        }
+    }
+}
+
+pub async fn admin_set_password_handler(
+    pool: web::Data<DbPool>,
+    user_id: web::Path<Uuid>,
+    password_data: web::Json<SetPasswordRequest>,
+) -> impl Responder {
+    let user_id = user_id.into_inner();
+    let password_data = password_data.into_inner();
+
+    // 限制并发哈希，过载时快速返回503
+    let _permit = match acquire_hash_permit().await {
+        Ok(permit) => permit,
+        Err(e) => return e.error_response(),
+    };
+
+    // 设置密码
+    match run_blocking(&pool, move |conn| admin_set_password(conn, user_id, password_data)).await {
+        Ok(()) => HttpResponse::NoContent().finish(),
+        Err(e) => {
+            eprintln!("Error setting password: {:?}", e);
+            match e {
+                ServiceError::UserNotFound | ServiceError::ValidationError(_) => e.error_response(),
+                _ => HttpResponse::InternalServerError().json("Failed to set password")
+            }
+        }
+    }
+}
+
+pub async fn force_user_password_change_handler(
+    pool: web::Data<DbPool>,
+    user_id: web::Path<Uuid>,
+) -> impl Responder {
+    let user_id = user_id.into_inner();
+
+    // 强制用户修改密码
+    match run_blocking(&pool, move |conn| force_user_password_change(conn, user_id)).await {
+        Ok(()) => HttpResponse::NoContent().finish(),
+        Err(e) => {
+            eprintln!("Error forcing password change: {:?}", e);
+            match e {
+                ServiceError::UserNotFound => e.error_response(),
+                _ => HttpResponse::InternalServerError().json("Failed to force password change")
+            }
+        }
+    }
+}
+
+pub async fn force_role_password_change_handler(
+    pool: web::Data<DbPool>,
+    role_id: web::Path<Uuid>,
+) -> impl Responder {
+    let role_id = role_id.into_inner();
+
+    // 强制角色下所有用户修改密码
+    match run_blocking(&pool, move |conn| force_role_password_change(conn, role_id)).await {
+        Ok(affected_users) => HttpResponse::Ok().json(serde_json::json!({ "affected_users": affected_users })),
+        Err(e) => {
+            eprintln!("Error forcing password change: {:?}", e);
+            match e {
+                ServiceError::RoleNotFound => e.error_response(),
+                _ => HttpResponse::InternalServerError().json("Failed to force password change")
+            }
+        }
+    }
+}
@@ -3,7 +3,8 @@ use serde::{Deserialize, Serialize};
 use uuid::Uuid;
 use chrono::{DateTime, Utc};
 
-use crate::db::schema::users;
+use crate::db::schema::{sessions, users};
+use crate::permissions::pattern::permission_matches;
 
 #[derive(Debug, Serialize, Deserialize, Queryable, Identifiable)]
 #[diesel(table_name = users)]
@@ -16,6 +17,10 @@ pub struct User {
     pub updated_at: DateTime<Utc>,
     pub last_login: Option<DateTime<Utc>>,
     pub is_active: bool,
+    // 早于该时间签发的令牌失效
+    pub tokens_valid_after: Option<DateTime<Utc>>,
+    pub password_changed_at: DateTime<Utc>,
+    pub must_change_password: bool,
 }
 
 #[derive(Debug, Serialize, Deserialize, Insertable)]
@@ -33,22 +38,152 @@ pub struct RegisterRequest {
     pub full_name: Option<String>,
 }
 
+// 用户修改密码请求
+#[derive(Debug, Serialize, Deserialize)]
+pub struct ChangePasswordRequest {
+    pub current_password: String,
+    pub new_password: String,
+}
+
+// 管理员设置密码请求
+#[derive(Debug, Serialize, Deserialize)]
+pub struct SetPasswordRequest {
+    pub password: String,
+}
+
 #[derive(Debug, Serialize, Deserialize)]
 pub struct LoginRequest {
     pub email: String,
     pub password: String,
 }
 
+// 登录方式：默认在JSON中返回令牌，mode=cookie 时改为设置浏览器会话Cookie
+#[derive(Debug, Serialize, Deserialize)]
+pub struct LoginQuery {
+    pub mode: Option<String>,
+}
+
+// Cookie模式下的登录响应，令牌不出现在响应体中
+#[derive(Debug, Serialize, Deserialize)]
+pub struct CookieSessionResponse {
+    pub user_id: Uuid,
+    pub email: String,
+    pub csrf_token: String,
+}
+
 #[derive(Debug, Serialize, Deserialize)]
 pub struct LoginResponse {
     pub token: String,
+    pub refresh_token: String,
     pub user_id: Uuid,
     pub email: String,
 }
 
+// 需要修改密码时的登录响应，令牌仅可用于修改密码
+#[derive(Debug, Serialize, Deserialize)]
+pub struct PasswordChangeRequiredResponse {
+    pub password_change_required: bool,
+    pub token: String,
+    pub expires_in: i64,
+    pub user_id: Uuid,
+    pub email: String,
+}
+
+// 登录结果
+#[derive(Debug)]
+pub enum LoginOutcome {
+    Session(LoginResponse),
+    PasswordChangeRequired(PasswordChangeRequiredResponse),
+}
+
 #[derive(Debug, Serialize, Deserialize)]
 pub struct UserResponse {
     pub id: Uuid,
     pub email: String,
     pub full_name: Option<String>,
 }
+
+#[derive(Debug, Serialize, Deserialize, Queryable, Identifiable, Associations)]
+#[diesel(belongs_to(User))]
+#[diesel(table_name = sessions)]
+pub struct Session {
+    pub id: Uuid,
+    pub user_id: Uuid,
+    pub refresh_token_hash: String,
+    pub expires_at: DateTime<Utc>,
+    pub revoked_at: Option<DateTime<Utc>>,
+    pub created_at: DateTime<Utc>,
+    pub updated_at: DateTime<Utc>,
+    // 第三方应用授权产生的会话所属客户端及授予的作用域
+    pub client_id: Option<Uuid>,
+    pub scope: Option<String>,
+}
+
+#[derive(Debug, Insertable)]
+#[diesel(table_name = sessions)]
+pub struct NewSession {
+    pub user_id: Uuid,
+    pub refresh_token_hash: String,
+    pub expires_at: DateTime<Utc>,
+    pub client_id: Option<Uuid>,
+    pub scope: Option<String>,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum PrincipalKind {
+    User,
+    Client,
+}
+
+// 已认证的调用方（用户或服务客户端），由认证中间件放入请求扩展
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct Principal {
+    pub kind: PrincipalKind,
+    pub id: Uuid,
+    // 令牌或API密钥携带的作用域（"resource:action"），None 表示不额外限制
+    pub scopes: Option<Vec<String>>,
+    // 令牌签发给的OAuth客户端，第一方登录会话为 None
+    #[serde(default)]
+    pub client_id: Option<String>,
+}
+
+impl Principal {
+    pub fn user(id: Uuid, scopes: Option<Vec<String>>) -> Self {
+        Principal { kind: PrincipalKind::User, id, scopes, client_id: None }
+    }
+
+    pub fn client(id: Uuid, scopes: Option<Vec<String>>) -> Self {
+        Principal { kind: PrincipalKind::Client, id, scopes, client_id: None }
+    }
+
+    /// 记录令牌签发给的OAuth客户端
+    pub fn issued_to(mut self, client_id: Option<String>) -> Self {
+        self.client_id = client_id;
+        self
+    }
+
+    /// 是否为用户本人通过第一方登录获得的凭证，API密钥和签发给第三方应用的令牌均不是
+    pub fn is_interactive_user(&self) -> bool {
+        self.kind == PrincipalKind::User && self.scopes.is_none() && self.client_id.is_none()
+    }
+
+    /// 作用域是否允许对资源执行操作，作用域与权限一样支持通配模式，如 `documents:*`
+    pub fn scope_allows(&self, resource: &str, action: &str) -> bool {
+        match &self.scopes {
+            None => true,
+            Some(scopes) => scopes.iter().any(|scope| {
+                scope
+                    .split_once(':')
+                    .is_some_and(|(scope_resource, scope_action)| {
+                        permission_matches(scope_resource, scope_action, resource, action)
+                    })
+            }),
+        }
+    }
+}
+
+/// 将空格分隔的 scope 字符串拆分为列表
+pub fn parse_scope(scope: Option<&str>) -> Option<Vec<String>> {
+    scope.map(|s| s.split_whitespace().map(str::to_string).collect())
+}
@@ -1,17 +1,42 @@
+use chrono::{Duration, Utc};
 use diesel::prelude::*;
 use uuid::Uuid;
 
 use crate::auth::models::*;
+use crate::config::CONFIG;
+use crate::db::{run_blocking, DbPool};
 use crate::db::schema::users::dsl::*;
-use crate::errors::ServiceError;
-use crate::utils::password::{hash_password, verify_password};
-use crate::utils::jwt::generate_token;
+use crate::errors::{FieldError, ServiceError};
+use crate::utils::password::{acquire_hash_permit, hash_password, needs_rehash, verify_password};
+use crate::utils::password_policy::{PasswordContext, PASSWORD_POLICY};
+use crate::utils::crypto::{generate_random_token, sha256_hex};
+use crate::permissions::services::{expand_role_descendants, get_user_password_max_age_days};
+use crate::utils::jwt::{generate_password_change_token, generate_session_token, PASSWORD_CHANGE_TOKEN_TTL_SECS};
 use crate::utils::supabase::sign_up_user;
 
+/// 注册用户，数据库操作和密码哈希在阻塞线程池中执行
+///
+/// 密码哈希名额只在创建用户时持有，同步到Supabase的网络请求不占用名额。
 pub async fn register_user(
-    db: &mut PgConnection,
+    pool: &DbPool,
     register_data: RegisterRequest,
 ) -> Result<LoginResponse, ServiceError> {
+    let password = register_data.password.clone();
+
+    // 创建用户，限制并发哈希，过载时快速返回503
+    let user = {
+        let _permit = acquire_hash_permit().await?;
+        run_blocking(pool, move |db| create_user(db, register_data)).await?
+    };
+
+    // 同步用户到Supabase
+    sign_up_user(&user.email, &password).await?;
+
+    // 创建会话并生成JWT令牌
+    run_blocking(pool, move |db| start_session(db, user)).await
+}
+
+fn create_user(db: &mut PgConnection, register_data: RegisterRequest) -> Result<User, ServiceError> {
     // 检查用户是否已存在
     let existing_user = users
         .filter(email.eq(&register_data.email))
@@ -22,6 +47,16 @@ pub async fn register_user(
         return Err(ServiceError::UserAlreadyExists);
     }
 
+    // 校验密码策略
+    PASSWORD_POLICY.validate(
+        "password",
+        &register_data.password,
+        &PasswordContext {
+            email: &register_data.email,
+            full_name: register_data.full_name.as_deref(),
+        },
+    )?;
+
     // 哈希密码
     let password_hash_str = hash_password(&register_data.password)?;
 
@@ -37,23 +72,28 @@ pub async fn register_user(
         .values(&new_user)
         .get_result(db)?;
 
-    // 同步用户到Supabase
-    sign_up_user(&user.email, &register_data.password).await?;
+    record_password_history(db, user.id, &user.password_hash)?;
 
-    // 生成JWT令牌
-    let token = generate_token(user.id)?;
+    Ok(user)
+}
+
+// 为用户创建第一方会话并生成JWT令牌
+fn start_session(db: &mut PgConnection, user: User) -> Result<LoginResponse, ServiceError> {
+    let (session, refresh_token) = create_session(db, user.id, None, None)?;
+    let token = generate_session_token(user.id, session.id)?;
 
     Ok(LoginResponse {
         token,
+        refresh_token,
         user_id: user.id,
         email: user.email,
     })
 }
 
-pub async fn login_user(
+pub fn login_user(
     db: &mut PgConnection,
     login_data: LoginRequest,
-) -> Result<LoginResponse, ServiceError> {
+) -> Result<LoginOutcome, ServiceError> {
     // 查找用户
     let user = users
         .filter(email.eq(&login_data.email))
@@ -66,22 +106,97 @@ pub async fn login_user(
         return Err(ServiceError::InvalidCredentials);
     }
 
+    // 哈希参数弱于当前策略时，借助已验证的明文密码透明升级
+    if needs_rehash(&user.password_hash) {
+        match hash_password(&login_data.password) {
+            Ok(new_hash) => rehash_password(db, &user, &new_hash)?,
+            Err(e) => log::warn!("密码重新哈希失败: {}", e),
+        }
+    }
+
     // 更新最后登录时间
     let user: User = diesel::update(users.find(user.id))
         .set(last_login.eq(diesel::dsl::now))
         .get_result(db)?;
 
-    // 生成JWT令牌
-    let token = generate_token(user.id)?;
+    // 需要修改密码时不创建会话，只签发修改密码用的受限令牌
+    if is_password_change_required(db, &user)? {
+        return Ok(LoginOutcome::PasswordChangeRequired(PasswordChangeRequiredResponse {
+            password_change_required: true,
+            token: generate_password_change_token(user.id)?,
+            expires_in: PASSWORD_CHANGE_TOKEN_TTL_SECS,
+            user_id: user.id,
+            email: user.email,
+        }));
+    }
 
-    Ok(LoginResponse {
-        token,
-        user_id: user.id,
-        email: user.email,
+    // 创建会话并生成JWT令牌
+    Ok(LoginOutcome::Session(start_session(db, user)?))
+}
+
+// 替换当前密码的哈希，历史记录中的对应条目一并替换，
+// 否则 pepper 轮换后旧条目无法再验证，历史检查会漏掉当前密码
+fn rehash_password(db: &mut PgConnection, user: &User, new_hash: &str) -> Result<(), ServiceError> {
+    use crate::db::schema::password_history;
+
+    db.transaction::<_, ServiceError, _>(|conn| {
+        diesel::update(users.find(user.id))
+            .set((password_hash.eq(new_hash), updated_at.eq(diesel::dsl::now)))
+            .execute(conn)?;
+
+        diesel::update(
+            password_history::table
+                .filter(password_history::user_id.eq(user.id))
+                .filter(password_history::password_hash.eq(&user.password_hash)),
+        )
+        .set(password_history::password_hash.eq(new_hash))
+        .execute(conn)?;
+
+        Ok(())
     })
 }
 
-pub async fn get_user_by_id(
+// 管理员强制修改，或密码已超过适用的最长使用天数
+fn is_password_change_required(db: &mut PgConnection, user: &User) -> Result<bool, ServiceError> {
+    if user.must_change_password {
+        return Ok(true);
+    }
+
+    let expired = get_user_password_max_age_days(db, user.id)?
+        .is_some_and(|days| user.password_changed_at + Duration::days(days as i64) <= Utc::now());
+
+    Ok(expired)
+}
+
+/// 为用户创建会话，返回会话及明文刷新令牌（仅此一次可见）
+///
+/// 通过OAuth授权给第三方应用的会话需传入客户端ID及授予的作用域。
+pub fn create_session(
+    db: &mut PgConnection,
+    target_user_id: Uuid,
+    session_client_id: Option<Uuid>,
+    session_scope: Option<String>,
+) -> Result<(Session, String), ServiceError> {
+    use crate::db::schema::sessions;
+
+    let refresh_token = generate_random_token(32);
+
+    let new_session = NewSession {
+        user_id: target_user_id,
+        refresh_token_hash: sha256_hex(&refresh_token),
+        expires_at: Utc::now() + Duration::seconds(CONFIG.refresh_token_ttl_secs),
+        client_id: session_client_id,
+        scope: session_scope,
+    };
+
+    let session = diesel::insert_into(sessions::table)
+        .values(&new_session)
+        .get_result::<Session>(db)?;
+
+    Ok((session, refresh_token))
+}
+
+pub fn get_user_by_id(
     db: &mut PgConnection,
     user_id: Uuid,
 ) -> Result<User, ServiceError> {
@@ -93,3 +208,198 @@ pub async fn get_user_by_id(
 
     Ok(user)
 }
+
+// 记录密码哈希，并只保留最近 PASSWORD_HISTORY_SIZE 条
+fn record_password_history(
+    db: &mut PgConnection,
+    target_user_id: Uuid,
+    new_hash: &str,
+) -> Result<(), ServiceError> {
+    use crate::db::schema::password_history;
+
+    if CONFIG.password_history_size <= 0 {
+        return Ok(());
+    }
+
+    diesel::insert_into(password_history::table)
+        .values((
+            password_history::user_id.eq(target_user_id),
+            password_history::password_hash.eq(new_hash),
+        ))
+        .execute(db)?;
+
+    let kept_ids = password_history::table
+        .filter(password_history::user_id.eq(target_user_id))
+        .order(password_history::created_at.desc())
+        .limit(CONFIG.password_history_size)
+        .select(password_history::id)
+        .load::<Uuid>(db)?;
+
+    diesel::delete(
+        password_history::table
+            .filter(password_history::user_id.eq(target_user_id))
+            .filter(password_history::id.ne_all(kept_ids)),
+    )
+    .execute(db)?;
+
+    Ok(())
+}
+
+// 新密码是否与当前密码或最近使用过的密码相同
+fn is_password_reused(
+    db: &mut PgConnection,
+    user: &User,
+    new_password: &str,
+) -> Result<bool, ServiceError> {
+    use crate::db::schema::password_history;
+
+    if CONFIG.password_history_size <= 0 {
+        return Ok(false);
+    }
+
+    // 导入的用户可能还没有历史记录，当前密码单独比较
+    if verify_password(new_password, &user.password_hash)? {
+        return Ok(true);
+    }
+
+    let recent_hashes = password_history::table
+        .filter(password_history::user_id.eq(user.id))
+        .order(password_history::created_at.desc())
+        .limit(CONFIG.password_history_size)
+        .select(password_history::password_hash)
+        .load::<String>(db)?;
+
+    for recent_hash in recent_hashes {
+        // 无法验证的旧哈希（如使用已下线的 pepper）不应阻止修改密码，但需记录，
+        // 因为此时无法保证新密码与该条历史不同
+        match verify_password(new_password, &recent_hash) {
+            Ok(true) => return Ok(true),
+            Ok(false) => {}
+            Err(e) => log::warn!("用户 {} 的密码历史无法验证: {}", user.id, e),
+        }
+    }
+
+    Ok(false)
+}
+
+/// 按密码策略校验并设置新密码，用户修改密码和管理员设置密码都经由此处
+///
+/// 目前没有忘记密码的重置流程，今后添加时同样应经由此处，以统一执行密码策略和历史检查。
+///
+/// `must_change` 表示新密码是否仍需用户下次登录时自行修改（如管理员代设的临时密码）。
+fn set_user_password(
+    db: &mut PgConnection,
+    user: &User,
+    field: &str,
+    new_password: &str,
+    must_change: bool,
+) -> Result<(), ServiceError> {
+    PASSWORD_POLICY.validate(
+        field,
+        new_password,
+        &PasswordContext {
+            email: &user.email,
+            full_name: user.full_name.as_deref(),
+        },
+    )?;
+
+    if is_password_reused(db, user, new_password)? {
+        return Err(ServiceError::ValidationError(vec![FieldError {
+            field: field.to_string(),
+            code: "password_reused".to_string(),
+            message: format!("不能使用最近{}次用过的密码", CONFIG.password_history_size),
+        }]));
+    }
+
+    let new_hash = hash_password(new_password)?;
+    db.transaction::<_, ServiceError, _>(|conn| {
+        diesel::update(users.find(user.id))
+            .set((
+                password_hash.eq(&new_hash),
+                password_changed_at.eq(diesel::dsl::now),
+                must_change_password.eq(must_change),
+                updated_at.eq(diesel::dsl::now),
+            ))
+            .execute(conn)?;
+
+        record_password_history(conn, user.id, &new_hash)
+    })
+}
+
+/// 用户修改自己的密码，需验证当前密码
+pub fn change_password(
+    db: &mut PgConnection,
+    user_id: Uuid,
+    request: ChangePasswordRequest,
+) -> Result<(), ServiceError> {
+    let user = users
+        .find(user_id)
+        .first::<User>(db)
+        .optional()?
+        .ok_or(ServiceError::UserNotFound)?;
+
+    if !verify_password(&request.current_password, &user.password_hash)? {
+        return Err(ServiceError::InvalidCredentials);
+    }
+
+    set_user_password(db, &user, "new_password", &request.new_password, false)
+}
+
+/// 管理员直接设置用户密码，用户下次登录时须自行修改
+pub fn admin_set_password(
+    db: &mut PgConnection,
+    user_id: Uuid,
+    request: SetPasswordRequest,
+) -> Result<(), ServiceError> {
+    let user = users
+        .find(user_id)
+        .first::<User>(db)
+        .optional()?
+        .ok_or(ServiceError::UserNotFound)?;
+
+    set_user_password(db, &user, "password", &request.password, true)
+}
+
+/// 强制用户下次登录时修改密码
+pub fn force_user_password_change(
+    db: &mut PgConnection,
+    target_user_id: Uuid,
+) -> Result<(), ServiceError> {
+    let updated = diesel::update(users.find(target_user_id))
+        .set((must_change_password.eq(true), updated_at.eq(diesel::dsl::now)))
+        .execute(db)?;
+
+    if updated == 0 {
+        return Err(ServiceError::UserNotFound);
+    }
+
+    Ok(())
+}
+
+/// 强制拥有指定角色（含通过子角色继承）的所有用户下次登录时修改密码，返回受影响的用户数
+pub fn force_role_password_change(
+    db: &mut PgConnection,
+    target_role_id: Uuid,
+) -> Result<usize, ServiceError> {
+    use crate::db::schema::{roles, user_roles};
+
+    let role_exists: i64 = roles::table
+        .filter(roles::id.eq(target_role_id))
+        .count()
+        .get_result(db)?;
+
+    if role_exists == 0 {
+        return Err(ServiceError::RoleNotFound);
+    }
+
+    let role_ids = expand_role_descendants(db, &[target_role_id])?;
+    let role_user_ids = user_roles::table
+        .filter(user_roles::role_id.eq_any(role_ids))
+        .select(user_roles::user_id);
+
+    let updated = diesel::update(users.filter(id.eq_any(role_user_ids)))
+        .set((must_change_password.eq(true), updated_at.eq(diesel::dsl::now)))
+        .execute(db)?;
+
+    Ok(updated)
+}
@@ -0,0 +1,92 @@
+use diesel::pg::PgConnection;
+use diesel::Connection;
+use std::path::Path;
+use uuid::Uuid;
+
+use crate::config::CONFIG;
+use crate::oauth::services::{revoke_all_tokens, revoke_user_tokens};
+use crate::utils::breach_filter::BreachFilter;
+
+const USAGE: &str = "用法:
+  supabase-auth-rust                              启动HTTP服务
+  supabase-auth-rust revoke-all-tokens            吊销此前签发的全部令牌
+  supabase-auth-rust revoke-user-tokens <用户ID>  吊销指定用户此前签发的全部令牌
+  supabase-auth-rust build-breach-filter <HIBP语料> <输出文件> [--fp-rate 0.001] [--min-count 1]
+                                                  从 HIBP SHA-1 语料构建泄露密码过滤器";
+
+fn establish_connection() -> Result<PgConnection, String> {
+    PgConnection::establish(&CONFIG.database_url).map_err(|e| format!("数据库连接失败: {}", e))
+}
+
+// 读取 `--name value` 形式的可选参数
+fn option_value<T: std::str::FromStr>(args: &[String], name: &str, default: T) -> Result<T, String> {
+    match args.iter().position(|arg| arg == name) {
+        Some(index) => args
+            .get(index + 1)
+            .and_then(|value| value.parse().ok())
+            .ok_or_else(|| format!("{} 需要有效的数值", name)),
+        None => Ok(default),
+    }
+}
+
+fn build_breach_filter(args: &[String]) -> Result<(), String> {
+    let (input, output) = match (args.get(1), args.get(2)) {
+        (Some(input), Some(output)) => (input, output),
+        _ => return Err(USAGE.to_string()),
+    };
+    let false_positive_rate: f64 = option_value(args, "--fp-rate", 0.001)?;
+    let min_count: u64 = option_value(args, "--min-count", 1)?;
+
+    if !(false_positive_rate > 0.0 && false_positive_rate < 1.0) {
+        return Err("--fp-rate 必须在 0 和 1 之间".to_string());
+    }
+
+    let filter = BreachFilter::build(Path::new(input), false_positive_rate, min_count)
+        .map_err(|e| format!("读取语料失败: {}", e))?;
+    filter
+        .save(Path::new(output))
+        .map_err(|e| format!("写入过滤器失败: {}", e))?;
+
+    println!("已生成泄露密码过滤器: {}", output);
+    Ok(())
+}
+
+fn run_command(args: &[String]) -> Result<(), String> {
+    match args.first().map(String::as_str) {
+        Some("revoke-all-tokens") => {
+            let mut conn = establish_connection()?;
+            let valid_after = revoke_all_tokens(&mut conn).map_err(|e| e.to_string())?;
+            println!("已吊销全部令牌，{} 之前签发的令牌均已失效", valid_after);
+            Ok(())
+        }
+        Some("revoke-user-tokens") => {
+            let user_id = args
+                .get(1)
+                .and_then(|value| Uuid::parse_str(value).ok())
+                .ok_or_else(|| "请提供有效的用户ID".to_string())?;
+
+            let mut conn = establish_connection()?;
+            let valid_after = revoke_user_tokens(&mut conn, user_id).map_err(|e| e.to_string())?;
+            println!("已吊销用户 {} 的全部令牌，{} 之前签发的令牌均已失效", user_id, valid_after);
+            Ok(())
+        }
+        Some("build-breach-filter") => build_breach_filter(args),
+        Some("help") | Some("--help") | Some("-h") => {
+            println!("{}", USAGE);
+            Ok(())
+        }
+        Some(command) => Err(format!("未知命令: {}\n{}", command, USAGE)),
+        None => Err(USAGE.to_string()),
+    }
+}
+
+/// 执行管理命令，返回进程退出码
+pub fn run(args: &[String]) -> i32 {
+    match run_command(args) {
+        Ok(()) => 0,
+        Err(message) => {
+            eprintln!("{}", message);
+            1
+        }
+    }
+}
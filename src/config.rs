@@ -1,16 +1,68 @@
 use dotenv::dotenv;
 use lazy_static::lazy_static;
 use std::env;
+use std::str::FromStr;
 
 lazy_static! {
     pub static ref CONFIG: Config = {
         dotenv().ok();
+
+        let token_format = env::var("TOKEN_FORMAT").unwrap_or_else(|_| "jwt".to_string());
         
         Config {
             database_url: env::var("DATABASE_URL").expect("DATABASE_URL必须设置"),
             supabase_url: env::var("SUPABASE_URL").expect("SUPABASE_URL必须设置"),
             supabase_key: env::var("SUPABASE_KEY").expect("SUPABASE_KEY必须设置"),
             jwt_secret: env::var("JWT_SECRET").expect("JWT_SECRET必须设置"),
+            device_verification_uri: env::var("DEVICE_VERIFICATION_URI")
+                .unwrap_or_else(|_| "http://127.0.0.1:8080/oauth/device".to_string()),
+            jwt_issuer: env::var("JWT_ISSUER")
+                .unwrap_or_else(|_| "supabase-auth-rust".to_string()),
+            jwt_audiences: parse_list(&env::var("JWT_AUDIENCE").unwrap_or_else(|_| "authenticated".to_string())),
+            access_token_ttl_secs: env_or("ACCESS_TOKEN_TTL_SECS", 24 * 3600),
+            refresh_token_ttl_secs: env_or("REFRESH_TOKEN_TTL_SECS", 30 * 24 * 3600),
+            jwt_leeway_secs: env_or("JWT_LEEWAY_SECS", 60),
+            session_cookie_name: env::var("SESSION_COOKIE_NAME")
+                .unwrap_or_else(|_| "sa_session".to_string()),
+            refresh_cookie_name: env::var("REFRESH_COOKIE_NAME")
+                .unwrap_or_else(|_| "sa_refresh".to_string()),
+            csrf_cookie_name: env::var("CSRF_COOKIE_NAME")
+                .unwrap_or_else(|_| "sa_csrf".to_string()),
+            cookie_domain: env::var("SESSION_COOKIE_DOMAIN").ok(),
+            cookie_path: env::var("SESSION_COOKIE_PATH").unwrap_or_else(|_| "/".to_string()),
+            cookie_secure: env_or("SESSION_COOKIE_SECURE", true),
+            cookie_same_site: env::var("SESSION_COOKIE_SAMESITE").unwrap_or_else(|_| "Lax".to_string()),
+            token_format: token_format.clone(),
+            enabled_token_formats: parse_list(&env::var("ENABLED_TOKEN_FORMATS").unwrap_or(token_format)),
+            paseto_local_key: env::var("PASETO_LOCAL_KEY").ok(),
+            paseto_secret_key: env::var("PASETO_SECRET_KEY").ok(),
+            argon2_algorithm: env::var("ARGON2_ALGORITHM").unwrap_or_else(|_| "argon2id".to_string()),
+            argon2_memory_kib: env_or("ARGON2_MEMORY_KIB", 19 * 1024),
+            argon2_iterations: env_or("ARGON2_ITERATIONS", 2),
+            argon2_parallelism: env_or("ARGON2_PARALLELISM", 1),
+            password_min_length: env_or("PASSWORD_MIN_LENGTH", 8),
+            password_max_length: env_or("PASSWORD_MAX_LENGTH", 128),
+            password_require_lowercase: env_or("PASSWORD_REQUIRE_LOWERCASE", false),
+            password_require_uppercase: env_or("PASSWORD_REQUIRE_UPPERCASE", false),
+            password_require_digit: env_or("PASSWORD_REQUIRE_DIGIT", false),
+            password_require_symbol: env_or("PASSWORD_REQUIRE_SYMBOL", false),
+            password_forbid_personal_info: env_or("PASSWORD_FORBID_PERSONAL_INFO", true),
+            password_min_strength: env_or("PASSWORD_MIN_STRENGTH", 2),
+            breached_password_filter: env::var("BREACHED_PASSWORD_FILTER").ok(),
+            password_history_size: env_or("PASSWORD_HISTORY_SIZE", 4),
+            password_max_age_days: env_or("PASSWORD_MAX_AGE_DAYS", 0),
+            password_hash_concurrency: env_or(
+                "PASSWORD_HASH_CONCURRENCY",
+                std::thread::available_parallelism().map(|n| n.get()).unwrap_or(4),
+            ),
+            password_hash_queue_timeout_ms: env_or("PASSWORD_HASH_QUEUE_TIMEOUT_MS", 2000),
+            password_peppers: env::var("PASSWORD_PEPPERS")
+                .map(|value| parse_list(&value))
+                .unwrap_or_default(),
+            relation_namespaces: env::var("RELATION_NAMESPACES").ok(),
+            trusted_proxies: env::var("TRUSTED_PROXIES")
+                .map(|value| parse_list(&value))
+                .unwrap_or_default(),
         }
     };
 }
@@ -20,4 +72,88 @@ pub struct Config {
     pub supabase_url: String,
     pub supabase_key: String,
     pub jwt_secret: String,
+    // 设备授权流程中展示给用户的验证地址
+    pub device_verification_uri: String,
+    // 签发令牌的 iss，校验时必须一致
+    pub jwt_issuer: String,
+    // 本服务接受的 aud 列表（JWT_AUDIENCE 逗号分隔），签发时使用第一个
+    pub jwt_audiences: Vec<String>,
+    pub access_token_ttl_secs: i64,
+    pub refresh_token_ttl_secs: i64,
+    // 校验 exp 时允许的时钟偏差
+    pub jwt_leeway_secs: u64,
+    // 浏览器会话Cookie设置
+    pub session_cookie_name: String,
+    pub refresh_cookie_name: String,
+    pub csrf_cookie_name: String,
+    pub cookie_domain: Option<String>,
+    pub cookie_path: String,
+    pub cookie_secure: bool,
+    // Strict / Lax / None
+    pub cookie_same_site: String,
+    // 签发访问令牌的格式：jwt / paseto-v4-local / paseto-v4-public
+    pub token_format: String,
+    // 校验时接受的格式（ENABLED_TOKEN_FORMATS 逗号分隔），缺省只接受签发格式，切换格式时可临时并存
+    pub enabled_token_formats: Vec<String>,
+    // v4.local 的对称密钥，base64 编码的32字节
+    pub paseto_local_key: Option<String>,
+    // v4.public 的 Ed25519 私钥种子，base64 编码的32字节
+    pub paseto_secret_key: Option<String>,
+    // 密码哈希策略：argon2id / argon2i / argon2d，默认值与 argon2 库默认参数一致
+    pub argon2_algorithm: String,
+    pub argon2_memory_kib: u32,
+    pub argon2_iterations: u32,
+    pub argon2_parallelism: u32,
+    // 密码策略
+    pub password_min_length: usize,
+    pub password_max_length: usize,
+    pub password_require_lowercase: bool,
+    pub password_require_uppercase: bool,
+    pub password_require_digit: bool,
+    pub password_require_symbol: bool,
+    // 禁止密码包含邮箱或姓名
+    pub password_forbid_personal_info: bool,
+    // 最低强度评分 0-4，0 表示不检查
+    pub password_min_strength: u8,
+    // 泄露密码布隆过滤器文件路径（由 build-breach-filter 命令生成），未设置时不筛查
+    pub breached_password_filter: Option<String>,
+    // 禁止重复使用最近几次的密码（含当前密码），0 表示不限制
+    pub password_history_size: i64,
+    // 未通过角色设置时的密码最长使用天数，0 表示不过期
+    pub password_max_age_days: i32,
+    // 同时进行的密码哈希数上限，默认为CPU核数
+    pub password_hash_concurrency: usize,
+    // 等待哈希名额的最长时间，超时返回503
+    pub password_hash_queue_timeout_ms: u64,
+    // 密码 pepper 列表（PASSWORD_PEPPERS 逗号分隔的 `<key id>:<base64密钥>`），
+    // 第一个用于新哈希，其余仅用于验证轮换前的哈希；不存入数据库
+    pub password_peppers: Vec<String>,
+    // 关系授权的命名空间配置文件路径（JSON），未设置时不允许写入任何关系元组
+    pub relation_namespaces: Option<String>,
+    // 受信任的反向代理地址或网段（TRUSTED_PROXIES 逗号分隔，如 10.0.0.0/8），
+    // 直连对端属于其中时才从 X-Forwarded-For 中取客户端地址
+    pub trusted_proxies: Vec<String>,
+}
+
+// 读取可选的数值配置，未设置时使用默认值
+fn env_or<T: FromStr>(key: &str, default: T) -> T {
+    match env::var(key) {
+        Ok(value) => value
+            .parse()
+            .unwrap_or_else(|_| panic!("{}必须是有效的数值", key)),
+        Err(_) => default,
+    }
+}
+
+// 解析逗号分隔的配置列表
+fn parse_list(value: &str) -> Vec<String> {
+    let items: Vec<String> = value
+        .split(',')
+        .map(str::trim)
+        .filter(|item| !item.is_empty())
+        .map(str::to_string)
+        .collect();
+
+    assert!(!items.is_empty(), "列表配置不能为空");
+    items
 }
@@ -1,8 +1,10 @@
+use actix_web::web;
 use diesel::pg::PgConnection;
 use diesel::r2d2::{self, ConnectionManager};
 use std::time::Duration;
 
 use crate::config::CONFIG;
+use crate::errors::ServiceError;
 
 pub mod schema;
 
@@ -15,3 +17,20 @@ pub fn init_pool() -> DbPool {
         .build(manager)
         .expect("数据库连接池创建失败")
 }
+
+/// 在有界的阻塞线程池中获取连接并执行数据库操作（及密码哈希等CPU密集操作），
+/// 避免阻塞异步工作线程
+pub async fn run_blocking<F, T>(pool: &DbPool, operation: F) -> Result<T, ServiceError>
+where
+    F: FnOnce(&mut PgConnection) -> Result<T, ServiceError> + Send + 'static,
+    T: Send + 'static,
+{
+    let pool = pool.clone();
+
+    web::block(move || {
+        let mut conn = pool.get()?;
+        operation(&mut conn)
+    })
+    .await
+    .map_err(|_| ServiceError::InternalServerError)?
+}
@@ -12,6 +12,9 @@ table! {
         updated_at -> Timestamptz,
         last_login -> Nullable<Timestamptz>,
         is_active -> Bool,
+        tokens_valid_after -> Nullable<Timestamptz>,
+        password_changed_at -> Timestamptz,
+        must_change_password -> Bool,
     }
 }
 
@@ -22,6 +25,7 @@ table! {
         description -> Nullable<Text>,
         created_at -> Timestamptz,
         updated_at -> Timestamptz,
+        max_password_age_days -> Nullable<Int4>,
     }
 }
 
@@ -46,12 +50,178 @@ table! {
     }
 }
 
+table! {
+    relation_tuples (id) {
+        id -> Uuid,
+        namespace -> Varchar,
+        object_id -> Varchar,
+        relation -> Varchar,
+        subject_namespace -> Varchar,
+        subject_object_id -> Varchar,
+        subject_relation -> Nullable<Varchar>,
+        created_at -> Timestamptz,
+    }
+}
+
+table! {
+    resource_grants (id) {
+        id -> Uuid,
+        resource_type -> Varchar,
+        resource_id -> Varchar,
+        action -> Varchar,
+        user_id -> Nullable<Uuid>,
+        role_id -> Nullable<Uuid>,
+        effect -> Varchar,
+        created_at -> Timestamptz,
+    }
+}
+
+table! {
+    role_parents (id) {
+        id -> Uuid,
+        role_id -> Uuid,
+        parent_role_id -> Uuid,
+        created_at -> Timestamptz,
+    }
+}
+
 table! {
     role_permissions (id) {
         id -> Uuid,
         role_id -> Uuid,
         permission_id -> Uuid,
         created_at -> Timestamptz,
+        effect -> Varchar,
+        condition -> Nullable<Varchar>,
+    }
+}
+
+table! {
+    clients (id) {
+        id -> Uuid,
+        client_id -> Varchar,
+        client_secret_hash -> Varchar,
+        name -> Varchar,
+        is_active -> Bool,
+        created_at -> Timestamptz,
+        updated_at -> Timestamptz,
+        redirect_uris -> Array<Text>,
+        is_confidential -> Bool,
+    }
+}
+
+table! {
+    sessions (id) {
+        id -> Uuid,
+        user_id -> Uuid,
+        refresh_token_hash -> Varchar,
+        expires_at -> Timestamptz,
+        revoked_at -> Nullable<Timestamptz>,
+        created_at -> Timestamptz,
+        updated_at -> Timestamptz,
+        client_id -> Nullable<Uuid>,
+        scope -> Nullable<Varchar>,
+    }
+}
+
+table! {
+    revoked_tokens (jti) {
+        jti -> Uuid,
+        expires_at -> Timestamptz,
+        revoked_at -> Timestamptz,
+    }
+}
+
+table! {
+    api_keys (id) {
+        id -> Uuid,
+        user_id -> Nullable<Uuid>,
+        client_id -> Nullable<Uuid>,
+        name -> Varchar,
+        prefix -> Varchar,
+        key_hash -> Varchar,
+        scopes -> Array<Text>,
+        expires_at -> Nullable<Timestamptz>,
+        last_used_at -> Nullable<Timestamptz>,
+        revoked_at -> Nullable<Timestamptz>,
+        created_at -> Timestamptz,
+    }
+}
+
+table! {
+    client_roles (id) {
+        id -> Uuid,
+        client_id -> Uuid,
+        role_id -> Uuid,
+        created_at -> Timestamptz,
+    }
+}
+
+table! {
+    oauth_grants (id) {
+        id -> Uuid,
+        user_id -> Uuid,
+        client_id -> Uuid,
+        scopes -> Array<Text>,
+        created_at -> Timestamptz,
+        updated_at -> Timestamptz,
+    }
+}
+
+table! {
+    authorization_codes (code_hash) {
+        code_hash -> Varchar,
+        client_id -> Uuid,
+        user_id -> Uuid,
+        redirect_uri -> Varchar,
+        scope -> Nullable<Varchar>,
+        code_challenge -> Varchar,
+        code_challenge_method -> Varchar,
+        expires_at -> Timestamptz,
+        used_at -> Nullable<Timestamptz>,
+        created_at -> Timestamptz,
+        session_id -> Nullable<Uuid>,
+    }
+}
+
+table! {
+    device_codes (device_code_hash) {
+        device_code_hash -> Varchar,
+        user_code -> Varchar,
+        client_id -> Uuid,
+        scope -> Nullable<Varchar>,
+        user_id -> Nullable<Uuid>,
+        status -> Varchar,
+        interval_secs -> Int4,
+        last_polled_at -> Nullable<Timestamptz>,
+        expires_at -> Timestamptz,
+        created_at -> Timestamptz,
+    }
+}
+
+table! {
+    token_epochs (id) {
+        id -> Bool,
+        tokens_valid_after -> Timestamptz,
+        updated_at -> Timestamptz,
+    }
+}
+
+table! {
+    revocation_tombstones (kind, id) {
+        kind -> Varchar,
+        id -> Uuid,
+        valid_until -> Timestamptz,
+        created_at -> Timestamptz,
+    }
+}
+
+table! {
+    password_history (id) {
+        id -> Uuid,
+        user_id -> Uuid,
+        password_hash -> Varchar,
+        created_at -> Timestamptz,
     }
 }
 
@@ -59,6 +229,22 @@ joinable!(user_roles -> users (user_id));
 joinable!(user_roles -> roles (role_id));
 joinable!(role_permissions -> roles (role_id));
 joinable!(role_permissions -> permissions (permission_id));
+joinable!(sessions -> users (user_id));
+joinable!(sessions -> clients (client_id));
+joinable!(api_keys -> users (user_id));
+joinable!(api_keys -> clients (client_id));
+joinable!(client_roles -> clients (client_id));
+joinable!(client_roles -> roles (role_id));
+joinable!(oauth_grants -> users (user_id));
+joinable!(oauth_grants -> clients (client_id));
+joinable!(authorization_codes -> users (user_id));
+joinable!(authorization_codes -> clients (client_id));
+joinable!(authorization_codes -> sessions (session_id));
+joinable!(device_codes -> users (user_id));
+joinable!(device_codes -> clients (client_id));
+joinable!(password_history -> users (user_id));
+joinable!(resource_grants -> users (user_id));
+joinable!(resource_grants -> roles (role_id));
 
 allow_tables_to_appear_in_same_query!(
     users,
@@ -66,4 +252,18 @@ allow_tables_to_appear_in_same_query!(
     permissions,
     user_roles,
     role_permissions,
+    role_parents,
+    resource_grants,
+    relation_tuples,
+    clients,
+    sessions,
+    revoked_tokens,
+    api_keys,
+    client_roles,
+    oauth_grants,
+    authorization_codes,
+    device_codes,
+    token_epochs,
+    revocation_tombstones,
+    password_history,
 );
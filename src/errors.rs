@@ -4,6 +4,14 @@ use r2d2::Error as R2D2Error;
 use serde::Serialize;
 use std::fmt;
 
+// 字段级校验错误
+#[derive(Debug, Clone, Serialize)]
+pub struct FieldError {
+    pub field: String,
+    pub code: String,
+    pub message: String,
+}
+
 #[derive(Debug, Serialize)]
 pub enum ServiceError {
     InternalServerError,
@@ -27,6 +35,9 @@ pub enum ServiceError {
     MissingToken,
     InvalidToken,
     InsufficientPermissions,
+    OAuthError(String),
+    ValidationError(Vec<FieldError>),
+    ServiceUnavailable(String),
 }
 
 impl fmt::Display for ServiceError {
@@ -53,6 +64,12 @@ impl fmt::Display for ServiceError {
             ServiceError::MissingToken => write!(f, "缺少令牌"),
             ServiceError::InvalidToken => write!(f, "无效的令牌"),
             ServiceError::InsufficientPermissions => write!(f, "权限不足"),
+            ServiceError::OAuthError(code) => write!(f, "OAuth错误: {}", code),
+            ServiceError::ValidationError(errors) => {
+                let fields: Vec<&str> = errors.iter().map(|e| e.field.as_str()).collect();
+                write!(f, "校验失败: {}", fields.join(", "))
+            }
+            ServiceError::ServiceUnavailable(msg) => write!(f, "服务繁忙: {}", msg),
         }
     }
 }
@@ -81,6 +98,21 @@ impl ResponseError for ServiceError {
             ServiceError::MissingToken => HttpResponse::Unauthorized().json("缺少令牌"),
             ServiceError::InvalidToken => HttpResponse::Unauthorized().json("无效的令牌"),
             ServiceError::InsufficientPermissions => HttpResponse::Forbidden().json("权限不足"),
+            // OAuth错误按RFC 6749第5.2节返回 {"error": code}
+            ServiceError::OAuthError(code) => {
+                let body = serde_json::json!({ "error": code });
+                if code == "invalid_client" {
+                    HttpResponse::Unauthorized().json(body)
+                } else {
+                    HttpResponse::BadRequest().json(body)
+                }
+            }
+            ServiceError::ValidationError(errors) => {
+                HttpResponse::UnprocessableEntity().json(serde_json::json!({ "errors": errors }))
+            }
+            ServiceError::ServiceUnavailable(msg) => HttpResponse::ServiceUnavailable()
+                .insert_header(("Retry-After", "1"))
+                .json(msg),
         }
     }
 }
@@ -2,11 +2,15 @@ use actix_web::{web, App, HttpServer};
 use dotenv::dotenv;
 use env_logger::Env;
 
+mod api_keys;
 mod auth;
+mod cli;
 mod config;
 mod db;
 mod errors;
+mod oauth;
 mod permissions;
+mod relations;
 mod routes;
 mod utils;
 
@@ -18,9 +22,26 @@ async fn main() -> std::io::Result<()> {
     // 初始化日志
     env_logger::init_from_env(Env::default().default_filter_or("info"));
     
+    // 带参数运行时执行管理命令而不启动服务
+    let args: Vec<String> = std::env::args().skip(1).collect();
+    if !args.is_empty() {
+        std::process::exit(cli::run(&args));
+    }
+    
+    // 提前加载令牌格式、受信任代理、关系命名空间、密码策略和密码哈希配置，配置无效时启动即失败，
+    // 而不是在第一个请求上 panic
+    lazy_static::initialize(&utils::token_format::TOKEN_FORMATS);
+    lazy_static::initialize(&utils::middleware::TRUSTED_PROXIES);
+    lazy_static::initialize(&relations::namespace::NAMESPACES);
+    lazy_static::initialize(&utils::password_policy::PASSWORD_POLICY);
+    utils::password::init();
+    
     // 创建数据库连接池
     let pool = db::init_pool();
     
+    // 监听其他实例的吊销事件，维护内存中的吊销集合
+    utils::revocation::start_listener();
+    
     log::info!("启动服务器在 http://127.0.0.1:8080");
     
     // 启动HTTP服务器
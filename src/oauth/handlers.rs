@@ -0,0 +1,451 @@
+use actix_web::{web, HttpRequest, HttpResponse, Responder, ResponseError};
+use base64::{engine::general_purpose::STANDARD, Engine};
+
+use uuid::Uuid;
+
+use crate::auth::models::Principal;
+use crate::db::{run_blocking, DbPool};
+use crate::errors::ServiceError;
+use crate::oauth::models::{
+    AuthorizeRequest, Client, ConsentRequest, CreateClientRequest, DeviceApprovalRequest, DeviceAuthorizationRequest,
+    DeviceLookupQuery, IntrospectRequest, RevokeRequest, TokenRequest, TokenRevocationResponse,
+};
+use crate::oauth::services::{
+    approve_device_code, authenticate_client, authenticate_public_client, authorize, create_client,
+    exchange_authorization_code, exchange_token, introspect_token, issue_client_credentials_token, list_authorized_apps,
+    lookup_device_code, poll_device_token, refresh_access_token, revoke_all_tokens, revoke_authorized_app, revoke_token,
+    revoke_user_tokens, start_device_authorization, submit_consent,
+};
+use crate::utils::password::acquire_hash_permit;
+
+// 从 Authorization: Basic 头或表单字段中提取客户端凭证
+fn extract_client_credentials(
+    req: &HttpRequest,
+    form_client_id: Option<&str>,
+    form_client_secret: Option<&str>,
+) -> Option<(String, String)> {
+    if let Some(header) = req.headers().get("Authorization") {
+        let header_str = header.to_str().ok()?;
+        let encoded = header_str.strip_prefix("Basic ")?;
+        let decoded = STANDARD.decode(encoded.trim()).ok()?;
+        let decoded = String::from_utf8(decoded).ok()?;
+        let (client_id, client_secret) = decoded.split_once(':')?;
+
+        return Some((client_id.to_string(), client_secret.to_string()));
+    }
+
+    match (form_client_id, form_client_secret) {
+        (Some(client_id), Some(client_secret)) => {
+            Some((client_id.to_string(), client_secret.to_string()))
+        }
+        _ => None,
+    }
+}
+
+// 校验机密客户端的密钥，与用户密码共用哈希名额并在阻塞线程池中执行，名额在校验完成后即释放
+async fn verify_client_secret(pool: &DbPool, client_id: String, client_secret: String) -> Result<Client, ServiceError> {
+    let _permit = acquire_hash_permit().await?;
+    run_blocking(pool, move |conn| authenticate_client(conn, &client_id, &client_secret)).await
+}
+
+// 识别令牌端点的调用方客户端：机密客户端校验密钥，公开客户端仅凭client_id
+async fn resolve_client(
+    pool: &DbPool,
+    req: &HttpRequest,
+    form_client_id: Option<&str>,
+    form_client_secret: Option<&str>,
+) -> Result<Option<Client>, ServiceError> {
+    if let Some((client_id, client_secret)) =
+        extract_client_credentials(req, form_client_id, form_client_secret)
+    {
+        return verify_client_secret(pool, client_id, client_secret).await.map(Some);
+    }
+
+    match form_client_id {
+        Some(client_id) => {
+            let client_id = client_id.to_string();
+            run_blocking(pool, move |conn| authenticate_public_client(conn, &client_id)).await.map(Some)
+        }
+        None => Ok(None),
+    }
+}
+
+// 仅允许用户本人通过第一方登录进行授权操作，签发给第三方应用的令牌即使未限制作用域也不可用
+fn require_interactive_user(principal: &Principal) -> Option<HttpResponse> {
+    if !principal.is_interactive_user() {
+        return Some(HttpResponse::Forbidden().json("Only signed-in users can authorize applications"));
+    }
+    None
+}
+
+pub async fn create_client_handler(
+    pool: web::Data<DbPool>,
+    principal: Principal,
+    client_data: web::Json<CreateClientRequest>,
+) -> impl Responder {
+    let client_data = client_data.into_inner();
+
+    // 仅限登录用户本人注册客户端，API密钥和第三方应用令牌不可用
+    if !principal.is_interactive_user() {
+        return HttpResponse::Forbidden().json("Only signed-in users can register clients");
+    }
+
+    // 客户端密钥同样以 Argon2 哈希保存，限制并发哈希，过载时快速返回503
+    let _permit = match acquire_hash_permit().await {
+        Ok(permit) => permit,
+        Err(e) => return e.error_response(),
+    };
+
+    // 注册客户端
+    match run_blocking(&pool, move |conn| create_client(conn, client_data)).await {
+        Ok(client) => HttpResponse::Created().json(client),
+        Err(e) => {
+            eprintln!("Error creating client: {:?}", e);
+            match e {
+                ServiceError::BadRequest(msg) => HttpResponse::BadRequest().json(msg),
+                ServiceError::ServiceUnavailable(_) => e.error_response(),
+                _ => HttpResponse::InternalServerError().json("Failed to create client")
+            }
+        }
+    }
+}
+
+pub async fn introspect_handler(
+    req: HttpRequest,
+    pool: web::Data<DbPool>,
+    form: web::Form<IntrospectRequest>,
+) -> impl Responder {
+    let form = form.into_inner();
+
+    let (client_id, client_secret) = match extract_client_credentials(
+        &req,
+        form.client_id.as_deref(),
+        form.client_secret.as_deref(),
+    ) {
+        Some(credentials) => credentials,
+        None => return ServiceError::OAuthError("invalid_client".to_string()).error_response(),
+    };
+
+    // 验证调用方客户端
+    if let Err(e) = verify_client_secret(&pool, client_id, client_secret).await {
+        eprintln!("Error authenticating client: {:?}", e);
+        return e.error_response();
+    }
+
+    // 内省令牌
+    match run_blocking(&pool, move |conn| introspect_token(conn, &form.token, form.token_type_hint.as_deref())).await {
+        Ok(response) => HttpResponse::Ok().json(response),
+        Err(e) => {
+            eprintln!("Error introspecting token: {:?}", e);
+            HttpResponse::InternalServerError().json("Failed to introspect token")
+        }
+    }
+}
+
+pub async fn revoke_handler(
+    req: HttpRequest,
+    pool: web::Data<DbPool>,
+    form: web::Form<RevokeRequest>,
+) -> impl Responder {
+    let form = form.into_inner();
+
+    let (client_id, client_secret) = match extract_client_credentials(
+        &req,
+        form.client_id.as_deref(),
+        form.client_secret.as_deref(),
+    ) {
+        Some(credentials) => credentials,
+        None => return ServiceError::OAuthError("invalid_client".to_string()).error_response(),
+    };
+
+    // 验证调用方客户端
+    let client = match verify_client_secret(&pool, client_id, client_secret).await {
+        Ok(client) => client,
+        Err(e) => {
+            eprintln!("Error authenticating client: {:?}", e);
+            return e.error_response();
+        }
+    };
+
+    // 吊销令牌
+    match run_blocking(&pool, move |conn| {
+        revoke_token(conn, &form.token, form.token_type_hint.as_deref(), Some(&client))
+    })
+    .await
+    {
+        Ok(()) => HttpResponse::Ok().finish(),
+        Err(e) => {
+            eprintln!("Error revoking token: {:?}", e);
+            HttpResponse::InternalServerError().json("Failed to revoke token")
+        }
+    }
+}
+
+pub async fn token_handler(
+    req: HttpRequest,
+    pool: web::Data<DbPool>,
+    form: web::Form<TokenRequest>,
+) -> impl Responder {
+    let form = form.into_inner();
+
+    let client = match resolve_client(&pool, &req, form.client_id.as_deref(), form.client_secret.as_deref()).await {
+        Ok(client) => client,
+        Err(e) => {
+            eprintln!("Error authenticating client: {:?}", e);
+            return e.error_response();
+        }
+    };
+
+    let result = run_blocking(&pool, move |conn| match form.grant_type.as_str() {
+        "refresh_token" => match form.refresh_token.as_deref() {
+            Some(refresh_token) => refresh_access_token(conn, refresh_token, client.as_ref()),
+            None => Err(ServiceError::OAuthError("invalid_request".to_string())),
+        },
+        "client_credentials" => match client {
+            // 客户端凭证授权仅限机密客户端
+            Some(client) if client.is_confidential => {
+                issue_client_credentials_token(conn, &client, form.scope.as_deref())
+            }
+            Some(_) => Err(ServiceError::OAuthError("unauthorized_client".to_string())),
+            None => Err(ServiceError::OAuthError("invalid_client".to_string())),
+        },
+        "authorization_code" => match (
+            client,
+            form.code.as_deref(),
+            form.redirect_uri.as_deref(),
+            form.code_verifier.as_deref(),
+        ) {
+            (None, _, _, _) => Err(ServiceError::OAuthError("invalid_client".to_string())),
+            (Some(client), Some(code), Some(redirect_uri), Some(code_verifier)) => {
+                exchange_authorization_code(conn, &client, code, redirect_uri, code_verifier)
+            }
+            _ => Err(ServiceError::OAuthError("invalid_request".to_string())),
+        },
+        "urn:ietf:params:oauth:grant-type:device_code" => match (client, form.device_code.as_deref()) {
+            (None, _) => Err(ServiceError::OAuthError("invalid_client".to_string())),
+            (Some(client), Some(device_code)) => poll_device_token(conn, &client, device_code),
+            _ => Err(ServiceError::OAuthError("invalid_request".to_string())),
+        },
+        "urn:ietf:params:oauth:grant-type:token-exchange" => match client {
+            // 令牌交换仅限机密客户端
+            Some(client) if client.is_confidential => exchange_token(conn, &client, &form),
+            Some(_) => Err(ServiceError::OAuthError("unauthorized_client".to_string())),
+            None => Err(ServiceError::OAuthError("invalid_client".to_string())),
+        },
+        _ => Err(ServiceError::OAuthError("unsupported_grant_type".to_string())),
+    }).await;
+
+    match result {
+        Ok(response) => HttpResponse::Ok()
+            .insert_header(("Cache-Control", "no-store"))
+            .json(response),
+        Err(e) => {
+            eprintln!("Error issuing token: {:?}", e);
+            match e {
+                ServiceError::OAuthError(_) => e.error_response(),
+                _ => HttpResponse::InternalServerError().json("Failed to issue token"),
+            }
+        }
+    }
+}
+
+pub async fn authorize_handler(
+    pool: web::Data<DbPool>,
+    principal: Principal,
+    query: web::Query<AuthorizeRequest>,
+) -> impl Responder {
+    let request = query.into_inner();
+
+    if let Some(response) = require_interactive_user(&principal) {
+        return response;
+    }
+
+    // 处理授权请求
+    match run_blocking(&pool, move |conn| authorize(conn, principal.id, request)).await {
+        Ok(response) => HttpResponse::Ok().json(response),
+        Err(e) => {
+            eprintln!("Error authorizing client: {:?}", e);
+            match e {
+                ServiceError::OAuthError(_) => e.error_response(),
+                _ => HttpResponse::InternalServerError().json("Failed to authorize")
+            }
+        }
+    }
+}
+
+pub async fn consent_handler(
+    pool: web::Data<DbPool>,
+    principal: Principal,
+    consent: web::Json<ConsentRequest>,
+) -> impl Responder {
+    let consent = consent.into_inner();
+
+    if let Some(response) = require_interactive_user(&principal) {
+        return response;
+    }
+
+    // 记录用户同意决定
+    match run_blocking(&pool, move |conn| submit_consent(conn, principal.id, consent)).await {
+        Ok(response) => HttpResponse::Ok().json(response),
+        Err(e) => {
+            eprintln!("Error submitting consent: {:?}", e);
+            match e {
+                ServiceError::OAuthError(_) => e.error_response(),
+                _ => HttpResponse::InternalServerError().json("Failed to submit consent")
+            }
+        }
+    }
+}
+
+pub async fn list_authorized_apps_handler(
+    pool: web::Data<DbPool>,
+    principal: Principal,
+) -> impl Responder {
+    if let Some(response) = require_interactive_user(&principal) {
+        return response;
+    }
+
+    // 获取已授权应用
+    match run_blocking(&pool, move |conn| list_authorized_apps(conn, principal.id)).await {
+        Ok(apps) => HttpResponse::Ok().json(apps),
+        Err(e) => {
+            eprintln!("Error listing authorized apps: {:?}", e);
+            HttpResponse::InternalServerError().json("Failed to list authorized apps")
+        }
+    }
+}
+
+pub async fn revoke_authorized_app_handler(
+    pool: web::Data<DbPool>,
+    principal: Principal,
+    client_id: web::Path<String>,
+) -> impl Responder {
+    let client_id = client_id.into_inner();
+
+    if let Some(response) = require_interactive_user(&principal) {
+        return response;
+    }
+
+    // 撤销应用授权
+    match run_blocking(&pool, move |conn| revoke_authorized_app(conn, principal.id, &client_id)).await {
+        Ok(()) => HttpResponse::NoContent().finish(),
+        Err(e) => {
+            eprintln!("Error revoking authorized app: {:?}", e);
+            match e {
+                ServiceError::NotFound(msg) => HttpResponse::NotFound().json(msg),
+                _ => HttpResponse::InternalServerError().json("Failed to revoke authorized app")
+            }
+        }
+    }
+}
+
+pub async fn device_authorization_handler(
+    req: HttpRequest,
+    pool: web::Data<DbPool>,
+    form: web::Form<DeviceAuthorizationRequest>,
+) -> impl Responder {
+    let form = form.into_inner();
+
+    let client = match resolve_client(&pool, &req, form.client_id.as_deref(), form.client_secret.as_deref()).await {
+        Ok(Some(client)) => client,
+        Ok(None) => return ServiceError::OAuthError("invalid_client".to_string()).error_response(),
+        Err(e) => {
+            eprintln!("Error authenticating client: {:?}", e);
+            return e.error_response();
+        }
+    };
+
+    // 发起设备授权
+    match run_blocking(&pool, move |conn| start_device_authorization(conn, &client, form.scope.as_deref())).await {
+        Ok(response) => HttpResponse::Ok()
+            .insert_header(("Cache-Control", "no-store"))
+            .json(response),
+        Err(e) => {
+            eprintln!("Error starting device authorization: {:?}", e);
+            HttpResponse::InternalServerError().json("Failed to start device authorization")
+        }
+    }
+}
+
+pub async fn device_lookup_handler(
+    pool: web::Data<DbPool>,
+    principal: Principal,
+    query: web::Query<DeviceLookupQuery>,
+) -> impl Responder {
+    let user_code = query.into_inner().user_code;
+
+    if let Some(response) = require_interactive_user(&principal) {
+        return response;
+    }
+
+    // 查询设备请求
+    match run_blocking(&pool, move |conn| lookup_device_code(conn, &user_code)).await {
+        Ok(response) => HttpResponse::Ok().json(response),
+        Err(e) => {
+            eprintln!("Error looking up device code: {:?}", e);
+            match e {
+                ServiceError::NotFound(msg) => HttpResponse::NotFound().json(msg),
+                _ => HttpResponse::InternalServerError().json("Failed to look up device code")
+            }
+        }
+    }
+}
+
+pub async fn device_approval_handler(
+    pool: web::Data<DbPool>,
+    principal: Principal,
+    approval: web::Json<DeviceApprovalRequest>,
+) -> impl Responder {
+    let approval = approval.into_inner();
+
+    if let Some(response) = require_interactive_user(&principal) {
+        return response;
+    }
+
+    // 批准或拒绝设备请求
+    match run_blocking(&pool, move |conn| approve_device_code(conn, principal.id, approval)).await {
+        Ok(response) => HttpResponse::Ok().json(response),
+        Err(e) => {
+            eprintln!("Error approving device code: {:?}", e);
+            match e {
+                ServiceError::NotFound(msg) => HttpResponse::NotFound().json(msg),
+                _ => HttpResponse::InternalServerError().json("Failed to approve device code")
+            }
+        }
+    }
+}
+
+pub async fn revoke_all_tokens_handler(
+    pool: web::Data<DbPool>,
+) -> impl Responder {
+    // 使此前签发的全部令牌失效
+    match run_blocking(&pool, revoke_all_tokens).await {
+        Ok(tokens_valid_after) => {
+            log::warn!("已紧急吊销全部令牌，有效起点: {}", tokens_valid_after);
+            HttpResponse::Ok().json(TokenRevocationResponse { tokens_valid_after })
+        }
+        Err(e) => {
+            eprintln!("Error revoking all tokens: {:?}", e);
+            HttpResponse::InternalServerError().json("Failed to revoke tokens")
+        }
+    }
+}
+
+pub async fn revoke_user_tokens_handler(
+    pool: web::Data<DbPool>,
+    user_id: web::Path<Uuid>,
+) -> impl Responder {
+    let user_id = user_id.into_inner();
+
+    // 使该用户此前签发的全部令牌失效
+    match run_blocking(&pool, move |conn| revoke_user_tokens(conn, user_id)).await {
+        Ok(tokens_valid_after) => HttpResponse::Ok().json(TokenRevocationResponse { tokens_valid_after }),
+        Err(e) => {
+            eprintln!("Error revoking user tokens: {:?}", e);
+            match e {
+                ServiceError::NotFound(msg) => HttpResponse::NotFound().json(msg),
+                _ => HttpResponse::InternalServerError().json("Failed to revoke tokens")
+            }
+        }
+    }
+}
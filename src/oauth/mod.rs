@@ -0,0 +1,3 @@
+pub mod models;
+pub mod services;
+pub mod handlers;
@@ -0,0 +1,301 @@
+use diesel::prelude::*;
+use serde::{Deserialize, Serialize};
+use uuid::Uuid;
+use chrono::{DateTime, Utc};
+
+use crate::utils::jwt::Actor;
+use crate::db::schema::{authorization_codes, clients, device_codes, oauth_grants, revoked_tokens};
+
+#[derive(Debug, Serialize, Deserialize, Queryable, Identifiable)]
+#[diesel(table_name = clients)]
+pub struct Client {
+    pub id: Uuid,
+    pub client_id: String,
+    #[serde(skip_serializing)]
+    pub client_secret_hash: String,
+    pub name: String,
+    pub is_active: bool,
+    pub created_at: DateTime<Utc>,
+    pub updated_at: DateTime<Utc>,
+    pub redirect_uris: Vec<String>,
+    // 公开客户端（如SPA、移动端）无法保管密钥，只能依赖PKCE
+    pub is_confidential: bool,
+}
+
+#[derive(Debug, Insertable)]
+#[diesel(table_name = clients)]
+pub struct NewClient {
+    pub client_id: String,
+    pub client_secret_hash: String,
+    pub name: String,
+    pub redirect_uris: Vec<String>,
+    pub is_confidential: bool,
+}
+
+#[derive(Debug, Serialize, Deserialize, Queryable, Identifiable)]
+#[diesel(primary_key(jti))]
+#[diesel(table_name = revoked_tokens)]
+pub struct RevokedToken {
+    pub jti: Uuid,
+    pub expires_at: DateTime<Utc>,
+    pub revoked_at: DateTime<Utc>,
+}
+
+// 用户对第三方应用的授权记录
+#[derive(Debug, Serialize, Deserialize, Queryable, Identifiable)]
+#[diesel(table_name = oauth_grants)]
+pub struct OAuthGrant {
+    pub id: Uuid,
+    pub user_id: Uuid,
+    pub client_id: Uuid,
+    pub scopes: Vec<String>,
+    pub created_at: DateTime<Utc>,
+    pub updated_at: DateTime<Utc>,
+}
+
+#[derive(Debug, Queryable, Identifiable)]
+#[diesel(primary_key(code_hash))]
+#[diesel(table_name = authorization_codes)]
+pub struct AuthorizationCode {
+    pub code_hash: String,
+    pub client_id: Uuid,
+    pub user_id: Uuid,
+    pub redirect_uri: String,
+    pub scope: Option<String>,
+    pub code_challenge: String,
+    pub code_challenge_method: String,
+    pub expires_at: DateTime<Utc>,
+    pub used_at: Option<DateTime<Utc>>,
+    pub created_at: DateTime<Utc>,
+    // 换取的会话，授权码被重放时吊销
+    pub session_id: Option<Uuid>,
+}
+
+#[derive(Debug, Insertable)]
+#[diesel(table_name = authorization_codes)]
+pub struct NewAuthorizationCode {
+    pub code_hash: String,
+    pub client_id: Uuid,
+    pub user_id: Uuid,
+    pub redirect_uri: String,
+    pub scope: Option<String>,
+    pub code_challenge: String,
+    pub code_challenge_method: String,
+    pub expires_at: DateTime<Utc>,
+}
+
+#[derive(Debug, Queryable, Identifiable)]
+#[diesel(primary_key(device_code_hash))]
+#[diesel(table_name = device_codes)]
+pub struct DeviceCode {
+    pub device_code_hash: String,
+    pub user_code: String,
+    pub client_id: Uuid,
+    pub scope: Option<String>,
+    pub user_id: Option<Uuid>,
+    pub status: String,
+    pub interval_secs: i32,
+    pub last_polled_at: Option<DateTime<Utc>>,
+    pub expires_at: DateTime<Utc>,
+    pub created_at: DateTime<Utc>,
+}
+
+#[derive(Debug, Insertable)]
+#[diesel(table_name = device_codes)]
+pub struct NewDeviceCode {
+    pub device_code_hash: String,
+    pub user_code: String,
+    pub client_id: Uuid,
+    pub scope: Option<String>,
+    pub interval_secs: i32,
+    pub expires_at: DateTime<Utc>,
+}
+
+#[derive(Debug, Serialize, Deserialize)]
+pub struct CreateClientRequest {
+    pub name: String,
+    #[serde(default)]
+    pub redirect_uris: Vec<String>,
+    pub is_confidential: Option<bool>,
+}
+
+// 客户端创建响应，client_secret仅在此返回一次
+#[derive(Debug, Serialize, Deserialize)]
+pub struct CreateClientResponse {
+    pub id: Uuid,
+    pub client_id: String,
+    pub client_secret: String,
+    pub name: String,
+    pub redirect_uris: Vec<String>,
+    pub is_confidential: bool,
+}
+
+// RFC 7662 内省请求
+#[derive(Debug, Serialize, Deserialize)]
+pub struct IntrospectRequest {
+    pub token: String,
+    pub token_type_hint: Option<String>,
+    pub client_id: Option<String>,
+    pub client_secret: Option<String>,
+}
+
+// RFC 7662 内省响应，令牌无效时仅返回 active=false
+#[derive(Debug, Default, Serialize, Deserialize)]
+pub struct IntrospectionResponse {
+    pub active: bool,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub sub: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub username: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub scope: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub client_id: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub token_type: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub exp: Option<i64>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub iat: Option<i64>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub jti: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub sid: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub aud: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub act: Option<Actor>,
+}
+
+// RFC 7009 吊销请求
+#[derive(Debug, Serialize, Deserialize)]
+pub struct RevokeRequest {
+    pub token: String,
+    pub token_type_hint: Option<String>,
+    pub client_id: Option<String>,
+    pub client_secret: Option<String>,
+}
+
+// 令牌端点请求
+#[derive(Debug, Serialize, Deserialize)]
+pub struct TokenRequest {
+    pub grant_type: String,
+    pub device_code: Option<String>,
+    pub refresh_token: Option<String>,
+    pub code: Option<String>,
+    pub redirect_uri: Option<String>,
+    pub code_verifier: Option<String>,
+    // 令牌交换参数（RFC 8693 第2.1节）
+    pub subject_token: Option<String>,
+    pub subject_token_type: Option<String>,
+    pub actor_token: Option<String>,
+    pub actor_token_type: Option<String>,
+    pub audience: Option<String>,
+    pub requested_token_type: Option<String>,
+    pub scope: Option<String>,
+    pub client_id: Option<String>,
+    pub client_secret: Option<String>,
+}
+
+#[derive(Debug, Serialize, Deserialize)]
+pub struct TokenResponse {
+    pub access_token: String,
+    pub token_type: String,
+    pub expires_in: i64,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub refresh_token: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub scope: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub issued_token_type: Option<String>,
+}
+
+// 授权请求参数（RFC 6749 第4.1.1节 + RFC 7636）
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct AuthorizeRequest {
+    pub response_type: String,
+    pub client_id: String,
+    pub redirect_uri: String,
+    pub scope: Option<String>,
+    pub state: Option<String>,
+    pub code_challenge: Option<String>,
+    pub code_challenge_method: Option<String>,
+}
+
+// 用户对授权请求的同意或拒绝
+#[derive(Debug, Serialize, Deserialize)]
+pub struct ConsentRequest {
+    #[serde(flatten)]
+    pub authorization: AuthorizeRequest,
+    pub approve: bool,
+}
+
+#[derive(Debug, Serialize, Deserialize)]
+pub struct ConsentClientInfo {
+    pub client_id: String,
+    pub name: String,
+}
+
+// 授权端点响应：需要用户同意时返回同意信息，否则返回回调地址
+#[derive(Debug, Serialize, Deserialize)]
+pub struct AuthorizeResponse {
+    pub consent_required: bool,
+    pub client: ConsentClientInfo,
+    pub scopes: Vec<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub redirect_to: Option<String>,
+}
+
+// 用户已授权应用列表项
+#[derive(Debug, Serialize, Deserialize)]
+pub struct AuthorizedAppResponse {
+    pub client_id: String,
+    pub name: String,
+    pub scopes: Vec<String>,
+    pub created_at: DateTime<Utc>,
+    pub updated_at: DateTime<Utc>,
+}
+
+// 设备授权请求（RFC 8628 第3.1节）
+#[derive(Debug, Serialize, Deserialize)]
+pub struct DeviceAuthorizationRequest {
+    pub client_id: Option<String>,
+    pub client_secret: Option<String>,
+    pub scope: Option<String>,
+}
+
+#[derive(Debug, Serialize, Deserialize)]
+pub struct DeviceAuthorizationResponse {
+    pub device_code: String,
+    pub user_code: String,
+    pub verification_uri: String,
+    pub verification_uri_complete: String,
+    pub expires_in: i64,
+    pub interval: i32,
+}
+
+#[derive(Debug, Serialize, Deserialize)]
+pub struct DeviceLookupQuery {
+    pub user_code: String,
+}
+
+// 已登录用户对设备用户码的批准或拒绝
+#[derive(Debug, Serialize, Deserialize)]
+pub struct DeviceApprovalRequest {
+    pub user_code: String,
+    pub approve: bool,
+}
+
+#[derive(Debug, Serialize, Deserialize)]
+pub struct DeviceVerificationResponse {
+    pub user_code: String,
+    pub client: ConsentClientInfo,
+    pub scopes: Vec<String>,
+    pub status: String,
+}
+
+// 紧急吊销结果，早于该时间签发的令牌均已失效
+#[derive(Debug, Serialize, Deserialize)]
+pub struct TokenRevocationResponse {
+    pub tokens_valid_after: DateTime<Utc>,
+}
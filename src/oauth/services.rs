@@ -0,0 +1,1328 @@
+use chrono::{DateTime, Duration, TimeZone, Utc};
+use diesel::prelude::*;
+use uuid::Uuid;
+
+use crate::auth::models::{parse_scope, Principal, PrincipalKind, Session, User};
+use crate::auth::services::create_session;
+use crate::errors::ServiceError;
+use crate::oauth::models::*;
+use rand_core::{OsRng, RngCore};
+
+use crate::config::CONFIG;
+use crate::utils::crypto::{constant_time_eq, generate_random_token, sha256_base64url, sha256_hex};
+use crate::permissions::services::{check_client_permission, check_principal_permission};
+use crate::utils::condition::AccessContext;
+use crate::utils::jwt::{decode_token_any_audience, encode_claims, Actor, Claims};
+use crate::utils::password::{hash_password, verify_password};
+use crate::utils::revocation::issued_before;
+
+/// 授权码有效期（分钟）
+pub const AUTHORIZATION_CODE_TTL_MINUTES: i64 = 10;
+
+/// 设备码有效期（分钟）
+pub const DEVICE_CODE_TTL_MINUTES: i64 = 10;
+
+/// 设备轮询的初始最小间隔（秒），收到 slow_down 后每次增加5秒
+pub const DEVICE_POLL_INTERVAL_SECS: i32 = 5;
+const DEVICE_SLOW_DOWN_INCREMENT_SECS: i32 = 5;
+
+// 令牌交换支持的令牌类型（RFC 8693 第3节）
+const TOKEN_TYPE_ACCESS_TOKEN: &str = "urn:ietf:params:oauth:token-type:access_token";
+const TOKEN_TYPE_JWT: &str = "urn:ietf:params:oauth:token-type:jwt";
+
+// 用户码字符集：去掉元音和易混淆字符（RFC 8628 第6.1节）
+const USER_CODE_ALPHABET: &[u8] = b"BCDFGHJKLMNPQRSTVWXZ";
+
+// 注册客户端，返回客户端及明文密钥
+pub fn create_client(
+    db: &mut PgConnection,
+    client_data: CreateClientRequest,
+) -> Result<CreateClientResponse, ServiceError> {
+    use crate::db::schema::clients;
+
+    // 回调地址必须是不含片段的绝对URL
+    for redirect_uri in &client_data.redirect_uris {
+        let parsed = url::Url::parse(redirect_uri)
+            .map_err(|_| ServiceError::BadRequest(format!("Invalid redirect URI: {}", redirect_uri)))?;
+        if parsed.fragment().is_some() {
+            return Err(ServiceError::BadRequest(format!("Redirect URI must not contain a fragment: {}", redirect_uri)));
+        }
+    }
+
+    let client_secret = generate_random_token(32);
+
+    let new_client = NewClient {
+        client_id: generate_random_token(16),
+        client_secret_hash: hash_password(&client_secret)?,
+        name: client_data.name,
+        redirect_uris: client_data.redirect_uris,
+        is_confidential: client_data.is_confidential.unwrap_or(true),
+    };
+
+    let client = diesel::insert_into(clients::table)
+        .values(&new_client)
+        .get_result::<Client>(db)?;
+
+    Ok(CreateClientResponse {
+        id: client.id,
+        client_id: client.client_id,
+        client_secret,
+        name: client.name,
+        redirect_uris: client.redirect_uris,
+        is_confidential: client.is_confidential,
+    })
+}
+
+// 验证客户端凭证
+pub fn authenticate_client(
+    db: &mut PgConnection,
+    client_id: &str,
+    client_secret: &str,
+) -> Result<Client, ServiceError> {
+    use crate::db::schema::clients;
+
+    let client = clients::table
+        .filter(clients::client_id.eq(client_id))
+        .first::<Client>(db)
+        .optional()?
+        .ok_or_else(|| ServiceError::OAuthError("invalid_client".to_string()))?;
+
+    if !client.is_active || !verify_password(client_secret, &client.client_secret_hash)? {
+        return Err(ServiceError::OAuthError("invalid_client".to_string()));
+    }
+
+    Ok(client)
+}
+
+// 识别不持有密钥的公开客户端，仅凭client_id
+pub fn authenticate_public_client(
+    db: &mut PgConnection,
+    client_id: &str,
+) -> Result<Client, ServiceError> {
+    use crate::db::schema::clients;
+
+    let client = clients::table
+        .filter(clients::client_id.eq(client_id))
+        .first::<Client>(db)
+        .optional()?
+        .ok_or_else(|| ServiceError::OAuthError("invalid_client".to_string()))?;
+
+    // 机密客户端必须提供密钥
+    if !client.is_active || client.is_confidential {
+        return Err(ServiceError::OAuthError("invalid_client".to_string()));
+    }
+
+    Ok(client)
+}
+
+// 检查已通过签名校验的访问令牌是否被吊销
+pub fn is_token_active(
+    db: &mut PgConnection,
+    claims: &Claims,
+) -> Result<bool, ServiceError> {
+    use crate::db::schema::{clients, revoked_tokens, sessions, token_epochs, users};
+
+    if let Some(jti) = claims.jti.as_deref().and_then(|v| Uuid::parse_str(v).ok()) {
+        let revoked_count: i64 = revoked_tokens::table
+            .filter(revoked_tokens::jti.eq(jti))
+            .select(diesel::dsl::count_star())
+            .first(db)?;
+
+        if revoked_count > 0 {
+            return Ok(false);
+        }
+    }
+
+    if let Some(sid) = claims.sid.as_deref() {
+        let session_id = match Uuid::parse_str(sid) {
+            Ok(session_id) => session_id,
+            Err(_) => return Ok(false),
+        };
+
+        let session = sessions::table
+            .find(session_id)
+            .first::<Session>(db)
+            .optional()?;
+
+        match session {
+            Some(session) if session.revoked_at.is_none() => {}
+            _ => return Ok(false),
+        }
+    }
+
+    // 紧急吊销：全局或用户级有效起点之前签发的令牌失效
+    let global_valid_after = token_epochs::table
+        .select(token_epochs::tokens_valid_after)
+        .first::<DateTime<Utc>>(db)
+        .optional()?;
+
+    if global_valid_after.is_some_and(|valid_after| issued_before(claims, valid_after.timestamp())) {
+        return Ok(false);
+    }
+
+    if claims.sub_kind != Some(PrincipalKind::Client) {
+        if let Ok(subject_user_id) = Uuid::parse_str(&claims.sub) {
+            let user_valid_after = users::table
+                .find(subject_user_id)
+                .select(users::tokens_valid_after)
+                .first::<Option<DateTime<Utc>>>(db)
+                .optional()?
+                .flatten();
+
+            if user_valid_after.is_some_and(|valid_after| issued_before(claims, valid_after.timestamp())) {
+                return Ok(false);
+            }
+        }
+    }
+
+    // 服务客户端被停用后，其令牌随之失效
+    if claims.sub_kind == Some(PrincipalKind::Client) {
+        let client_id = match Uuid::parse_str(&claims.sub) {
+            Ok(client_id) => client_id,
+            Err(_) => return Ok(false),
+        };
+
+        let is_active = clients::table
+            .find(client_id)
+            .select(clients::is_active)
+            .first::<bool>(db)
+            .optional()?;
+
+        if is_active != Some(true) {
+            return Ok(false);
+        }
+    }
+
+    Ok(true)
+}
+
+// 查找未吊销且未过期的刷新令牌对应的会话
+fn find_active_session(
+    db: &mut PgConnection,
+    refresh_token: &str,
+) -> Result<Option<Session>, ServiceError> {
+    use crate::db::schema::sessions;
+
+    let session = sessions::table
+        .filter(sessions::refresh_token_hash.eq(sha256_hex(refresh_token)))
+        .filter(sessions::revoked_at.is_null())
+        .filter(sessions::expires_at.gt(Utc::now()))
+        .first::<Session>(db)
+        .optional()?;
+
+    Ok(session)
+}
+
+fn find_user_email(db: &mut PgConnection, user_id: Uuid) -> Result<Option<String>, ServiceError> {
+    use crate::db::schema::users;
+
+    let email = users::table
+        .find(user_id)
+        .select(users::email)
+        .first::<String>(db)
+        .optional()?;
+
+    Ok(email)
+}
+
+fn introspect_access_token(
+    db: &mut PgConnection,
+    token: &str,
+) -> Result<Option<IntrospectionResponse>, ServiceError> {
+    let claims = match decode_token_any_audience(token) {
+        Ok(claims) => claims,
+        Err(_) => return Ok(None),
+    };
+
+    // 受限用途的令牌（如仅可修改密码）对资源服务器而言不是有效的访问令牌
+    if claims.purpose.is_some() || !is_token_active(db, &claims)? {
+        return Ok(None);
+    }
+
+    let username = match (claims.sub_kind, Uuid::parse_str(&claims.sub)) {
+        (Some(PrincipalKind::Client), _) | (_, Err(_)) => None,
+        (_, Ok(user_id)) => find_user_email(db, user_id)?,
+    };
+
+    Ok(Some(IntrospectionResponse {
+        active: true,
+        sub: Some(claims.sub),
+        username,
+        scope: claims.scope,
+        client_id: claims.client_id,
+        token_type: Some("access_token".to_string()),
+        exp: Some(claims.exp),
+        iat: Some(claims.iat),
+        jti: claims.jti,
+        sid: claims.sid,
+        aud: claims.aud,
+        act: claims.act,
+    }))
+}
+
+fn introspect_refresh_token(
+    db: &mut PgConnection,
+    token: &str,
+) -> Result<Option<IntrospectionResponse>, ServiceError> {
+    let session = match find_active_session(db, token)? {
+        Some(session) => session,
+        None => return Ok(None),
+    };
+
+    Ok(Some(IntrospectionResponse {
+        active: true,
+        sub: Some(session.user_id.to_string()),
+        username: find_user_email(db, session.user_id)?,
+        token_type: Some("refresh_token".to_string()),
+        exp: Some(session.expires_at.timestamp()),
+        iat: Some(session.created_at.timestamp()),
+        sid: Some(session.id.to_string()),
+        ..Default::default()
+    }))
+}
+
+// 令牌内省（RFC 7662），token_type_hint 只决定查找顺序
+pub fn introspect_token(
+    db: &mut PgConnection,
+    token: &str,
+    token_type_hint: Option<&str>,
+) -> Result<IntrospectionResponse, ServiceError> {
+    let response = if token_type_hint == Some("refresh_token") {
+        match introspect_refresh_token(db, token)? {
+            Some(response) => Some(response),
+            None => introspect_access_token(db, token)?,
+        }
+    } else {
+        match introspect_access_token(db, token)? {
+            Some(response) => Some(response),
+            None => introspect_refresh_token(db, token)?,
+        }
+    };
+
+    Ok(response.unwrap_or_default())
+}
+
+// 返回值表示令牌是否已识别；签发给其他客户端的令牌视为已识别但不吊销
+fn revoke_refresh_token(db: &mut PgConnection, token: &str, client: Option<&Client>) -> Result<bool, ServiceError> {
+    use crate::db::schema::sessions;
+
+    let session = match find_active_session(db, token)? {
+        Some(session) => session,
+        None => return Ok(false),
+    };
+
+    if session.client_id != client.map(|c| c.id) {
+        return Ok(true);
+    }
+
+    diesel::update(sessions::table.find(session.id))
+        .set((
+            sessions::revoked_at.eq(diesel::dsl::now),
+            sessions::updated_at.eq(diesel::dsl::now),
+        ))
+        .execute(db)?;
+
+    Ok(true)
+}
+
+fn revoke_access_token(db: &mut PgConnection, token: &str, client: Option<&Client>) -> Result<bool, ServiceError> {
+    use crate::db::schema::revoked_tokens;
+
+    let claims = match decode_token_any_audience(token) {
+        Ok(claims) => claims,
+        Err(_) => return Ok(false),
+    };
+
+    if claims.client_id.as_deref() != client.map(|c| c.client_id.as_str()) {
+        return Ok(true);
+    }
+
+    let jti = match claims.jti.as_deref().and_then(|v| Uuid::parse_str(v).ok()) {
+        Some(jti) => jti,
+        None => return Ok(false),
+    };
+
+    let expires_at: DateTime<Utc> = Utc
+        .timestamp_opt(claims.exp, 0)
+        .single()
+        .unwrap_or_else(Utc::now);
+
+    diesel::insert_into(revoked_tokens::table)
+        .values((
+            revoked_tokens::jti.eq(jti),
+            revoked_tokens::expires_at.eq(expires_at),
+        ))
+        .on_conflict_do_nothing()
+        .execute(db)?;
+
+    // 顺带清理已自然过期的吊销记录
+    diesel::delete(revoked_tokens::table.filter(revoked_tokens::expires_at.lt(Utc::now())))
+        .execute(db)?;
+
+    Ok(true)
+}
+
+// 令牌吊销（RFC 7009），未知或无效令牌同样视为成功
+//
+// 只吊销签发给调用方客户端的令牌（第2.1节），其他令牌同样返回成功而不做任何处理；
+// client 为空时只吊销第一方会话的令牌，如退出登录。
+pub fn revoke_token(
+    db: &mut PgConnection,
+    token: &str,
+    token_type_hint: Option<&str>,
+    client: Option<&Client>,
+) -> Result<(), ServiceError> {
+    if token_type_hint == Some("access_token") {
+        if !revoke_access_token(db, token, client)? {
+            revoke_refresh_token(db, token, client)?;
+        }
+    } else if !revoke_refresh_token(db, token, client)? {
+        revoke_access_token(db, token, client)?;
+    }
+
+    Ok(())
+}
+
+// 为会话签发访问令牌，第三方应用会话带上其客户端及作用域
+fn issue_session_access_token(
+    db: &mut PgConnection,
+    session: &Session,
+) -> Result<String, ServiceError> {
+    use crate::db::schema::clients;
+
+    let mut claims = Claims::new(session.user_id.to_string());
+    claims.sid = Some(session.id.to_string());
+    claims.scope = session.scope.clone();
+
+    if let Some(session_client_id) = session.client_id {
+        // 第三方应用会话的作用域缺失时按空作用域处理，而不是不受限制
+        claims.scope.get_or_insert_with(String::new);
+
+        let public_client_id = clients::table
+            .find(session_client_id)
+            .select(clients::client_id)
+            .first::<String>(db)?;
+        claims.client_id = Some(public_client_id);
+    }
+
+    encode_claims(&claims)
+}
+
+// 使用刷新令牌换取新的访问令牌，并轮换刷新令牌
+//
+// 第三方应用的会话只能由签发时的客户端刷新。
+pub fn refresh_access_token(
+    db: &mut PgConnection,
+    refresh_token: &str,
+    client: Option<&Client>,
+) -> Result<TokenResponse, ServiceError> {
+    use crate::db::schema::{sessions, users};
+
+    let session = find_active_session(db, refresh_token)?
+        .ok_or_else(|| ServiceError::OAuthError("invalid_grant".to_string()))?;
+
+    if session.client_id.is_some() && session.client_id != client.map(|c| c.id) {
+        return Err(ServiceError::OAuthError("invalid_grant".to_string()));
+    }
+
+    let user = users::table
+        .find(session.user_id)
+        .first::<User>(db)
+        .optional()?
+        .ok_or_else(|| ServiceError::OAuthError("invalid_grant".to_string()))?;
+
+    if !user.is_active {
+        return Err(ServiceError::OAuthError("invalid_grant".to_string()));
+    }
+
+    let new_refresh_token = generate_random_token(32);
+
+    diesel::update(sessions::table.find(session.id))
+        .set((
+            sessions::refresh_token_hash.eq(sha256_hex(&new_refresh_token)),
+            sessions::updated_at.eq(diesel::dsl::now),
+        ))
+        .execute(db)?;
+
+    let access_token = issue_session_access_token(db, &session)?;
+
+    Ok(TokenResponse {
+        access_token,
+        token_type: "Bearer".to_string(),
+        expires_in: CONFIG.access_token_ttl_secs,
+        refresh_token: Some(new_refresh_token),
+        scope: session.scope,
+        issued_token_type: None,
+    })
+}
+
+// 客户端凭证授权（RFC 6749 第4.4节），令牌主体为服务客户端本身
+pub fn issue_client_credentials_token(
+    db: &mut PgConnection,
+    client: &Client,
+    scope: Option<&str>,
+) -> Result<TokenResponse, ServiceError> {
+    // 请求的作用域必须是客户端角色已拥有的权限
+    if let Some(scope) = scope {
+        for requested in scope.split_whitespace() {
+            let (resource, action) = requested
+                .split_once(':')
+                .ok_or_else(|| ServiceError::OAuthError("invalid_scope".to_string()))?;
+
+            if !check_client_permission(db, client.id, resource, action, None, &AccessContext::default())? {
+                return Err(ServiceError::OAuthError("invalid_scope".to_string()));
+            }
+        }
+    }
+
+    let mut claims = Claims::new(client.id.to_string());
+    claims.sub_kind = Some(PrincipalKind::Client);
+    claims.client_id = Some(client.client_id.clone());
+    claims.scope = scope.map(str::to_string);
+
+    let access_token = encode_claims(&claims)?;
+
+    Ok(TokenResponse {
+        access_token,
+        token_type: "Bearer".to_string(),
+        expires_in: CONFIG.access_token_ttl_secs,
+        refresh_token: None,
+        scope: claims.scope,
+        issued_token_type: None,
+    })
+}
+
+// 校验授权请求：客户端、回调地址、响应类型及PKCE参数
+fn validate_authorize_request(
+    db: &mut PgConnection,
+    request: &AuthorizeRequest,
+) -> Result<(Client, Vec<String>), ServiceError> {
+    use crate::db::schema::clients;
+
+    let client = clients::table
+        .filter(clients::client_id.eq(&request.client_id))
+        .filter(clients::is_active.eq(true))
+        .first::<Client>(db)
+        .optional()?
+        .ok_or_else(|| ServiceError::OAuthError("invalid_client".to_string()))?;
+
+    // 回调地址必须与注册时完全一致
+    if !client.redirect_uris.contains(&request.redirect_uri) {
+        return Err(ServiceError::OAuthError("invalid_request".to_string()));
+    }
+
+    if request.response_type != "code" {
+        return Err(ServiceError::OAuthError("unsupported_response_type".to_string()));
+    }
+
+    // 所有客户端均强制使用PKCE，且只接受 S256：plain 不能防止授权码在传输中被截获后使用
+    if !request.code_challenge.as_deref().is_some_and(is_valid_pkce_value)
+        || request.code_challenge_method.as_deref() != Some("S256")
+    {
+        return Err(ServiceError::OAuthError("invalid_request".to_string()));
+    }
+
+    let scopes = request
+        .scope
+        .as_deref()
+        .map(|scope| scope.split_whitespace().map(str::to_string).collect())
+        .unwrap_or_default();
+
+    Ok((client, scopes))
+}
+
+// 在回调地址上追加查询参数
+fn build_redirect(redirect_uri: &str, params: &[(&str, &str)]) -> Result<String, ServiceError> {
+    let mut url = url::Url::parse(redirect_uri)
+        .map_err(|_| ServiceError::OAuthError("invalid_request".to_string()))?;
+
+    {
+        let mut query = url.query_pairs_mut();
+        for (key, value) in params {
+            query.append_pair(key, value);
+        }
+    }
+
+    Ok(url.to_string())
+}
+
+fn find_grant(
+    db: &mut PgConnection,
+    grant_user_id: Uuid,
+    grant_client_id: Uuid,
+) -> Result<Option<OAuthGrant>, ServiceError> {
+    use crate::db::schema::oauth_grants;
+
+    let grant = oauth_grants::table
+        .filter(oauth_grants::user_id.eq(grant_user_id))
+        .filter(oauth_grants::client_id.eq(grant_client_id))
+        .first::<OAuthGrant>(db)
+        .optional()?;
+
+    Ok(grant)
+}
+
+// 记录用户对应用的授权，已有授权时合并作用域
+fn upsert_grant(
+    db: &mut PgConnection,
+    grant_user_id: Uuid,
+    grant_client_id: Uuid,
+    scopes: &[String],
+) -> Result<(), ServiceError> {
+    use crate::db::schema::oauth_grants;
+
+    match find_grant(db, grant_user_id, grant_client_id)? {
+        Some(grant) => {
+            let mut merged = grant.scopes.clone();
+            for scope in scopes {
+                if !merged.contains(scope) {
+                    merged.push(scope.clone());
+                }
+            }
+
+            diesel::update(oauth_grants::table.find(grant.id))
+                .set((
+                    oauth_grants::scopes.eq(merged),
+                    oauth_grants::updated_at.eq(diesel::dsl::now),
+                ))
+                .execute(db)?;
+        }
+        None => {
+            diesel::insert_into(oauth_grants::table)
+                .values((
+                    oauth_grants::user_id.eq(grant_user_id),
+                    oauth_grants::client_id.eq(grant_client_id),
+                    oauth_grants::scopes.eq(scopes),
+                ))
+                .execute(db)?;
+        }
+    }
+
+    Ok(())
+}
+
+// 签发授权码并生成带 code 和 state 的回调地址
+fn issue_authorization_code(
+    db: &mut PgConnection,
+    code_user_id: Uuid,
+    client: &Client,
+    request: &AuthorizeRequest,
+    scopes: &[String],
+) -> Result<String, ServiceError> {
+    use crate::db::schema::authorization_codes;
+
+    let code = generate_random_token(32);
+
+    let new_code = NewAuthorizationCode {
+        code_hash: sha256_hex(&code),
+        client_id: client.id,
+        user_id: code_user_id,
+        redirect_uri: request.redirect_uri.clone(),
+        // 未请求作用域时记录为空作用域，第三方应用不能因省略 scope 获得用户的全部权限
+        scope: Some(scopes.join(" ")),
+        code_challenge: request.code_challenge.clone().unwrap_or_default(),
+        code_challenge_method: "S256".to_string(),
+        expires_at: Utc::now() + Duration::minutes(AUTHORIZATION_CODE_TTL_MINUTES),
+    };
+
+    diesel::insert_into(authorization_codes::table)
+        .values(&new_code)
+        .execute(db)?;
+
+    let mut params = vec![("code", code.as_str())];
+    if let Some(state) = request.state.as_deref() {
+        params.push(("state", state));
+    }
+
+    build_redirect(&request.redirect_uri, &params)
+}
+
+// 处理授权请求：已同意过全部作用域时直接签发授权码，否则要求用户同意
+pub fn authorize(
+    db: &mut PgConnection,
+    authorizing_user_id: Uuid,
+    request: AuthorizeRequest,
+) -> Result<AuthorizeResponse, ServiceError> {
+    let (client, scopes) = validate_authorize_request(db, &request)?;
+
+    let already_granted = match find_grant(db, authorizing_user_id, client.id)? {
+        Some(grant) => scopes.iter().all(|scope| grant.scopes.contains(scope)),
+        None => false,
+    };
+
+    let redirect_to = if already_granted {
+        Some(issue_authorization_code(db, authorizing_user_id, &client, &request, &scopes)?)
+    } else {
+        None
+    };
+
+    Ok(AuthorizeResponse {
+        consent_required: !already_granted,
+        client: ConsentClientInfo {
+            client_id: client.client_id,
+            name: client.name,
+        },
+        scopes,
+        redirect_to,
+    })
+}
+
+// 记录用户的同意决定；同意时合并授权作用域并签发授权码
+pub fn submit_consent(
+    db: &mut PgConnection,
+    consenting_user_id: Uuid,
+    consent: ConsentRequest,
+) -> Result<AuthorizeResponse, ServiceError> {
+    let request = consent.authorization;
+    let (client, scopes) = validate_authorize_request(db, &request)?;
+
+    let redirect_to = if consent.approve {
+        upsert_grant(db, consenting_user_id, client.id, &scopes)?;
+
+        issue_authorization_code(db, consenting_user_id, &client, &request, &scopes)?
+    } else {
+        let mut params = vec![("error", "access_denied")];
+        if let Some(state) = request.state.as_deref() {
+            params.push(("state", state));
+        }
+        build_redirect(&request.redirect_uri, &params)?
+    };
+
+    Ok(AuthorizeResponse {
+        consent_required: false,
+        client: ConsentClientInfo {
+            client_id: client.client_id,
+            name: client.name,
+        },
+        scopes,
+        redirect_to: Some(redirect_to),
+    })
+}
+
+// code_challenge 和 code_verifier 均为 43-128 个非保留字符（RFC 7636 第4.1、4.2节）
+fn is_valid_pkce_value(value: &str) -> bool {
+    (43..=128).contains(&value.len())
+        && value
+            .bytes()
+            .all(|b| b.is_ascii_alphanumeric() || matches!(b, b'-' | b'.' | b'_' | b'~'))
+}
+
+// 校验PKCE code_verifier（RFC 7636 第4.6节）
+fn verify_pkce(code: &AuthorizationCode, code_verifier: &str) -> bool {
+    is_valid_pkce_value(code_verifier)
+        && code.code_challenge_method == "S256"
+        && constant_time_eq(&sha256_base64url(code_verifier), &code.code_challenge)
+}
+
+// 授权码换取令牌（RFC 6749 第4.1.3节）
+//
+// 先确认授权码签发给调用方客户端且 PKCE 校验通过，其他客户端无法消耗授权码；
+// 已使用的授权码被再次提交时，吊销其换取的会话（第4.1.2节）。
+pub fn exchange_authorization_code(
+    db: &mut PgConnection,
+    client: &Client,
+    code: &str,
+    redirect_uri: &str,
+    code_verifier: &str,
+) -> Result<TokenResponse, ServiceError> {
+    use crate::db::schema::{authorization_codes, sessions, users};
+
+    let authorization_code = authorization_codes::table
+        .find(sha256_hex(code))
+        .first::<AuthorizationCode>(db)
+        .optional()?
+        .ok_or_else(|| ServiceError::OAuthError("invalid_grant".to_string()))?;
+
+    if authorization_code.client_id != client.id
+        || authorization_code.redirect_uri != redirect_uri
+        || !verify_pkce(&authorization_code, code_verifier)
+    {
+        return Err(ServiceError::OAuthError("invalid_grant".to_string()));
+    }
+
+    let issued = db.transaction::<_, ServiceError, _>(|conn| {
+        // 授权码只能使用一次，先原子地标记为已使用
+        let claimed = diesel::update(
+            authorization_codes::table
+                .find(&authorization_code.code_hash)
+                .filter(authorization_codes::used_at.is_null()),
+        )
+        .set(authorization_codes::used_at.eq(diesel::dsl::now))
+        .execute(conn)?;
+
+        if claimed == 0 {
+            return Ok(None);
+        }
+
+        if authorization_code.expires_at <= Utc::now() {
+            return Err(ServiceError::OAuthError("invalid_grant".to_string()));
+        }
+
+        let is_active = users::table
+            .find(authorization_code.user_id)
+            .select(users::is_active)
+            .first::<bool>(conn)
+            .optional()?;
+
+        if is_active != Some(true) {
+            return Err(ServiceError::OAuthError("invalid_grant".to_string()));
+        }
+
+        let (session, refresh_token) = create_session(
+            conn,
+            authorization_code.user_id,
+            Some(client.id),
+            authorization_code.scope.clone(),
+        )?;
+
+        diesel::update(authorization_codes::table.find(&authorization_code.code_hash))
+            .set(authorization_codes::session_id.eq(session.id))
+            .execute(conn)?;
+
+        Ok(Some((session, refresh_token)))
+    })?;
+
+    let (session, refresh_token) = match issued {
+        Some(issued) => issued,
+        None => {
+            // 授权码被重放：吊销第一次换取的会话，其访问令牌随之失效
+            let session_id = authorization_codes::table
+                .find(&authorization_code.code_hash)
+                .select(authorization_codes::session_id)
+                .first::<Option<Uuid>>(db)?;
+
+            if let Some(session_id) = session_id {
+                log::warn!("授权码被重放，吊销会话: {}", session_id);
+                diesel::update(
+                    sessions::table
+                        .find(session_id)
+                        .filter(sessions::revoked_at.is_null()),
+                )
+                .set((
+                    sessions::revoked_at.eq(diesel::dsl::now),
+                    sessions::updated_at.eq(diesel::dsl::now),
+                ))
+                .execute(db)?;
+            }
+
+            return Err(ServiceError::OAuthError("invalid_grant".to_string()));
+        }
+    };
+
+    let access_token = issue_session_access_token(db, &session)?;
+
+    Ok(TokenResponse {
+        access_token,
+        token_type: "Bearer".to_string(),
+        expires_in: CONFIG.access_token_ttl_secs,
+        refresh_token: Some(refresh_token),
+        scope: authorization_code.scope,
+        issued_token_type: None,
+    })
+}
+
+// 获取用户已授权的应用
+pub fn list_authorized_apps(
+    db: &mut PgConnection,
+    grant_user_id: Uuid,
+) -> Result<Vec<AuthorizedAppResponse>, ServiceError> {
+    use crate::db::schema::{clients, oauth_grants};
+
+    let grants = oauth_grants::table
+        .inner_join(clients::table)
+        .filter(oauth_grants::user_id.eq(grant_user_id))
+        .order(oauth_grants::created_at.desc())
+        .load::<(OAuthGrant, Client)>(db)?;
+
+    Ok(grants
+        .into_iter()
+        .map(|(grant, client)| AuthorizedAppResponse {
+            client_id: client.client_id,
+            name: client.name,
+            scopes: grant.scopes,
+            created_at: grant.created_at,
+            updated_at: grant.updated_at,
+        })
+        .collect())
+}
+
+// 撤销用户对应用的授权，并吊销该应用持有的全部会话
+pub fn revoke_authorized_app(
+    db: &mut PgConnection,
+    grant_user_id: Uuid,
+    public_client_id: &str,
+) -> Result<(), ServiceError> {
+    use crate::db::schema::{clients, oauth_grants, sessions};
+
+    let client = clients::table
+        .filter(clients::client_id.eq(public_client_id))
+        .first::<Client>(db)
+        .optional()?
+        .ok_or_else(|| ServiceError::NotFound("Authorized app not found".to_string()))?;
+
+    db.transaction::<_, ServiceError, _>(|conn| {
+        let deleted = diesel::delete(
+            oauth_grants::table
+                .filter(oauth_grants::user_id.eq(grant_user_id))
+                .filter(oauth_grants::client_id.eq(client.id)),
+        )
+        .execute(conn)?;
+
+        if deleted == 0 {
+            return Err(ServiceError::NotFound("Authorized app not found".to_string()));
+        }
+
+        diesel::update(
+            sessions::table
+                .filter(sessions::user_id.eq(grant_user_id))
+                .filter(sessions::client_id.eq(client.id))
+                .filter(sessions::revoked_at.is_null()),
+        )
+        .set((
+            sessions::revoked_at.eq(diesel::dsl::now),
+            sessions::updated_at.eq(diesel::dsl::now),
+        ))
+        .execute(conn)?;
+
+        Ok(())
+    })
+}
+
+// 生成形如 BDFG-HJKL 的用户码
+fn generate_user_code() -> String {
+    let mut code = String::with_capacity(9);
+    while code.len() < 9 {
+        if code.len() == 4 {
+            code.push('-');
+            continue;
+        }
+
+        // 拒绝采样，避免取模偏差
+        let byte = (OsRng.next_u32() & 0xff) as u8;
+        if (byte as usize) < 256 - 256 % USER_CODE_ALPHABET.len() {
+            code.push(USER_CODE_ALPHABET[byte as usize % USER_CODE_ALPHABET.len()] as char);
+        }
+    }
+    code
+}
+
+// 规范化用户输入的用户码：忽略大小写、空格和连字符
+fn normalize_user_code(input: &str) -> String {
+    let chars: String = input
+        .chars()
+        .filter(|c| c.is_ascii_alphanumeric())
+        .map(|c| c.to_ascii_uppercase())
+        .collect();
+
+    if chars.len() == 8 {
+        format!("{}-{}", &chars[..4], &chars[4..])
+    } else {
+        chars
+    }
+}
+
+// 发起设备授权（RFC 8628 第3.2节）
+pub fn start_device_authorization(
+    db: &mut PgConnection,
+    client: &Client,
+    scope: Option<&str>,
+) -> Result<DeviceAuthorizationResponse, ServiceError> {
+    use crate::db::schema::device_codes;
+
+    let device_code = generate_random_token(32);
+    let expires_at = Utc::now() + Duration::minutes(DEVICE_CODE_TTL_MINUTES);
+
+    // 用户码较短，冲突时重新生成
+    for _ in 0..5 {
+        let user_code = generate_user_code();
+
+        let new_device_code = NewDeviceCode {
+            device_code_hash: sha256_hex(&device_code),
+            user_code: user_code.clone(),
+            client_id: client.id,
+            // 同授权码，未请求作用域时记录为空作用域
+            scope: Some(scope.unwrap_or_default().to_string()),
+            interval_secs: DEVICE_POLL_INTERVAL_SECS,
+            expires_at,
+        };
+
+        let inserted = diesel::insert_into(device_codes::table)
+            .values(&new_device_code)
+            .on_conflict_do_nothing()
+            .execute(db)?;
+
+        if inserted == 1 {
+            let verification_uri = CONFIG.device_verification_uri.clone();
+            let verification_uri_complete =
+                build_redirect(&verification_uri, &[("user_code", user_code.as_str())])?;
+
+            return Ok(DeviceAuthorizationResponse {
+                device_code,
+                user_code,
+                verification_uri,
+                verification_uri_complete,
+                expires_in: DEVICE_CODE_TTL_MINUTES * 60,
+                interval: DEVICE_POLL_INTERVAL_SECS,
+            });
+        }
+    }
+
+    Err(ServiceError::InternalServerError)
+}
+
+fn find_pending_device_code(
+    db: &mut PgConnection,
+    user_code: &str,
+) -> Result<(DeviceCode, Client), ServiceError> {
+    use crate::db::schema::{clients, device_codes};
+
+    device_codes::table
+        .inner_join(clients::table)
+        .filter(device_codes::user_code.eq(normalize_user_code(user_code)))
+        .filter(device_codes::status.eq("pending"))
+        .filter(device_codes::expires_at.gt(Utc::now()))
+        .first::<(DeviceCode, Client)>(db)
+        .optional()?
+        .ok_or_else(|| ServiceError::NotFound("Invalid or expired user code".to_string()))
+}
+
+fn device_verification_response(device_code: DeviceCode, client: Client, status: &str) -> DeviceVerificationResponse {
+    DeviceVerificationResponse {
+        user_code: device_code.user_code,
+        client: ConsentClientInfo {
+            client_id: client.client_id,
+            name: client.name,
+        },
+        scopes: device_code
+            .scope
+            .as_deref()
+            .map(|scope| scope.split_whitespace().map(str::to_string).collect())
+            .unwrap_or_default(),
+        status: status.to_string(),
+    }
+}
+
+// 查询待批准的设备请求，供用户确认客户端和作用域
+pub fn lookup_device_code(
+    db: &mut PgConnection,
+    user_code: &str,
+) -> Result<DeviceVerificationResponse, ServiceError> {
+    let (device_code, client) = find_pending_device_code(db, user_code)?;
+    let status = device_code.status.clone();
+
+    Ok(device_verification_response(device_code, client, &status))
+}
+
+// 已登录用户批准或拒绝设备请求
+pub fn approve_device_code(
+    db: &mut PgConnection,
+    approving_user_id: Uuid,
+    approval: DeviceApprovalRequest,
+) -> Result<DeviceVerificationResponse, ServiceError> {
+    use crate::db::schema::device_codes;
+
+    let (device_code, client) = find_pending_device_code(db, &approval.user_code)?;
+    let status = if approval.approve { "approved" } else { "denied" };
+
+    let updated = diesel::update(
+        device_codes::table
+            .find(&device_code.device_code_hash)
+            .filter(device_codes::status.eq("pending")),
+    )
+    .set((
+        device_codes::status.eq(status),
+        device_codes::user_id.eq(approving_user_id),
+    ))
+    .execute(db)?;
+
+    if updated == 0 {
+        return Err(ServiceError::NotFound("Invalid or expired user code".to_string()));
+    }
+
+    if approval.approve {
+        let scopes: Vec<String> = device_code
+            .scope
+            .as_deref()
+            .map(|scope| scope.split_whitespace().map(str::to_string).collect())
+            .unwrap_or_default();
+        upsert_grant(db, approving_user_id, client.id, &scopes)?;
+    }
+
+    Ok(device_verification_response(device_code, client, status))
+}
+
+// 设备轮询令牌（RFC 8628 第3.4、3.5节）
+pub fn poll_device_token(
+    db: &mut PgConnection,
+    client: &Client,
+    device_code: &str,
+) -> Result<TokenResponse, ServiceError> {
+    use crate::db::schema::{device_codes, users};
+
+    let code = device_codes::table
+        .find(sha256_hex(device_code))
+        .first::<DeviceCode>(db)
+        .optional()?
+        .ok_or_else(|| ServiceError::OAuthError("invalid_grant".to_string()))?;
+
+    if code.client_id != client.id {
+        return Err(ServiceError::OAuthError("invalid_grant".to_string()));
+    }
+
+    let now = Utc::now();
+    if code.expires_at <= now {
+        return Err(ServiceError::OAuthError("expired_token".to_string()));
+    }
+
+    // 轮询过快时要求客户端放慢，并永久增大其轮询间隔
+    let too_fast = code
+        .last_polled_at
+        .is_some_and(|last| now - last < Duration::seconds(code.interval_secs as i64));
+
+    if too_fast {
+        diesel::update(device_codes::table.find(&code.device_code_hash))
+            .set((
+                device_codes::interval_secs.eq(code.interval_secs + DEVICE_SLOW_DOWN_INCREMENT_SECS),
+                device_codes::last_polled_at.eq(now),
+            ))
+            .execute(db)?;
+        return Err(ServiceError::OAuthError("slow_down".to_string()));
+    }
+
+    diesel::update(device_codes::table.find(&code.device_code_hash))
+        .set(device_codes::last_polled_at.eq(now))
+        .execute(db)?;
+
+    match code.status.as_str() {
+        "pending" => return Err(ServiceError::OAuthError("authorization_pending".to_string())),
+        "denied" => return Err(ServiceError::OAuthError("access_denied".to_string())),
+        "approved" => {}
+        _ => return Err(ServiceError::OAuthError("invalid_grant".to_string())),
+    }
+
+    // 设备码只能兑换一次
+    let claimed = diesel::update(
+        device_codes::table
+            .find(&code.device_code_hash)
+            .filter(device_codes::status.eq("approved")),
+    )
+    .set(device_codes::status.eq("consumed"))
+    .execute(db)?;
+
+    let approving_user_id = match (claimed, code.user_id) {
+        (1, Some(approving_user_id)) => approving_user_id,
+        _ => return Err(ServiceError::OAuthError("invalid_grant".to_string())),
+    };
+
+    let is_active = users::table
+        .find(approving_user_id)
+        .select(users::is_active)
+        .first::<bool>(db)
+        .optional()?;
+
+    if is_active != Some(true) {
+        return Err(ServiceError::OAuthError("invalid_grant".to_string()));
+    }
+
+    let (session, refresh_token) = create_session(db, approving_user_id, Some(client.id), code.scope.clone())?;
+    let access_token = issue_session_access_token(db, &session)?;
+
+    Ok(TokenResponse {
+        access_token,
+        token_type: "Bearer".to_string(),
+        expires_in: CONFIG.access_token_ttl_secs,
+        refresh_token: Some(refresh_token),
+        scope: code.scope,
+        issued_token_type: None,
+    })
+}
+
+fn is_exchangeable_token_type(token_type: &str) -> bool {
+    token_type == TOKEN_TYPE_ACCESS_TOKEN || token_type == TOKEN_TYPE_JWT
+}
+
+// 解码令牌并确认其仍有效
+fn decode_active_token(db: &mut PgConnection, token: &str) -> Result<Claims, ServiceError> {
+    let claims = decode_token_any_audience(token).map_err(|_| ServiceError::OAuthError("invalid_grant".to_string()))?;
+
+    // 受限用途的令牌（如仅可修改密码）不能用于交换
+    if claims.purpose.is_some() || !is_token_active(db, &claims)? {
+        return Err(ServiceError::OAuthError("invalid_grant".to_string()));
+    }
+
+    Ok(claims)
+}
+
+// 令牌交换（RFC 8693）：换取受众更窄、作用域更小的令牌，或带 act 声明的代理令牌
+//
+// 新令牌的作用域必须是主体令牌已拥有权限的子集，有效期不超过主体令牌，
+// 且沿用主体令牌的会话，因此无论如何交换都不会获得主体本身没有的权限。
+pub fn exchange_token(
+    db: &mut PgConnection,
+    client: &Client,
+    request: &TokenRequest,
+) -> Result<TokenResponse, ServiceError> {
+    let (subject_token, subject_token_type) = match (
+        request.subject_token.as_deref(),
+        request.subject_token_type.as_deref(),
+    ) {
+        (Some(token), Some(token_type)) => (token, token_type),
+        _ => return Err(ServiceError::OAuthError("invalid_request".to_string())),
+    };
+
+    if !is_exchangeable_token_type(subject_token_type)
+        || !request.requested_token_type.as_deref().is_none_or(is_exchangeable_token_type)
+    {
+        return Err(ServiceError::OAuthError("invalid_request".to_string()));
+    }
+
+    let subject_claims = decode_active_token(db, subject_token)?;
+    let subject_id = Uuid::parse_str(&subject_claims.sub)
+        .map_err(|_| ServiceError::OAuthError("invalid_grant".to_string()))?;
+    let subject_scopes = parse_scope(subject_claims.scope.as_deref());
+    let subject = match subject_claims.sub_kind {
+        Some(PrincipalKind::Client) => Principal::client(subject_id, subject_scopes),
+        _ => Principal::user(subject_id, subject_scopes),
+    };
+
+    // 代理场景：actor_token 标识实际操作方，服务客户端只能以自己的身份代理
+    let act = match request.actor_token.as_deref() {
+        Some(actor_token) => {
+            if !request.actor_token_type.as_deref().is_some_and(is_exchangeable_token_type) {
+                return Err(ServiceError::OAuthError("invalid_request".to_string()));
+            }
+
+            let actor_claims = decode_active_token(db, actor_token)?;
+            if actor_claims.sub_kind == Some(PrincipalKind::Client) && actor_claims.sub != client.id.to_string() {
+                return Err(ServiceError::OAuthError("invalid_grant".to_string()));
+            }
+
+            Some(Actor {
+                sub: actor_claims.sub,
+                client_id: actor_claims.client_id,
+                act: subject_claims.act.clone().map(Box::new),
+            })
+        }
+        None => subject_claims.act.clone(),
+    };
+
+    // 请求的作用域必须是主体当前确实拥有的权限
+    let scope = match request.scope.as_deref() {
+        Some(scope) => {
+            for requested in scope.split_whitespace() {
+                let (resource, action) = requested
+                    .split_once(':')
+                    .ok_or_else(|| ServiceError::OAuthError("invalid_scope".to_string()))?;
+
+                if !check_principal_permission(db, &subject, resource, action, None, &AccessContext::default())? {
+                    return Err(ServiceError::OAuthError("invalid_scope".to_string()));
+                }
+            }
+            Some(scope.to_string())
+        }
+        None => subject_claims.scope.clone(),
+    };
+
+    let mut claims = Claims::new(subject_claims.sub.clone());
+    claims.exp = claims.exp.min(subject_claims.exp);
+    claims.sid = subject_claims.sid.clone();
+    claims.sub_kind = subject_claims.sub_kind;
+    claims.scope = scope;
+    claims.client_id = Some(client.client_id.clone());
+    if let Some(audience) = request.audience.as_deref() {
+        claims.aud = Some(audience.to_string());
+    }
+    claims.act = act;
+
+    let access_token = encode_claims(&claims)?;
+
+    Ok(TokenResponse {
+        access_token,
+        token_type: "Bearer".to_string(),
+        expires_in: claims.exp - claims.iat,
+        refresh_token: None,
+        scope: claims.scope,
+        issued_token_type: Some(TOKEN_TYPE_ACCESS_TOKEN.to_string()),
+    })
+}
+
+// 当前事务中批量吊销的会话不再逐行广播通知，由同一事务写入的令牌有效起点覆盖
+fn suppress_session_notify(conn: &mut PgConnection) -> Result<(), ServiceError> {
+    diesel::sql_query("SET LOCAL auth.suppress_session_notify = 'on'").execute(conn)?;
+    Ok(())
+}
+
+/// 紧急吊销全部令牌：此前签发的所有访问令牌失效，所有会话（刷新令牌）被吊销
+///
+/// 通过令牌有效起点实现，无需更换签名密钥。API密钥不受影响。
+pub fn revoke_all_tokens(db: &mut PgConnection) -> Result<DateTime<Utc>, ServiceError> {
+    use crate::db::schema::{sessions, token_epochs};
+
+    let valid_after = Utc::now();
+
+    db.transaction::<_, ServiceError, _>(|conn| {
+        diesel::insert_into(token_epochs::table)
+            .values((
+                token_epochs::id.eq(true),
+                token_epochs::tokens_valid_after.eq(valid_after),
+            ))
+            .on_conflict(token_epochs::id)
+            .do_update()
+            .set((
+                token_epochs::tokens_valid_after.eq(valid_after),
+                token_epochs::updated_at.eq(diesel::dsl::now),
+            ))
+            .execute(conn)?;
+
+        suppress_session_notify(conn)?;
+        diesel::update(sessions::table.filter(sessions::revoked_at.is_null()))
+            .set((
+                sessions::revoked_at.eq(diesel::dsl::now),
+                sessions::updated_at.eq(diesel::dsl::now),
+            ))
+            .execute(conn)?;
+
+        Ok(())
+    })?;
+
+    Ok(valid_after)
+}
+
+/// 吊销指定用户此前签发的全部令牌和会话
+pub fn revoke_user_tokens(
+    db: &mut PgConnection,
+    target_user_id: Uuid,
+) -> Result<DateTime<Utc>, ServiceError> {
+    use crate::db::schema::{sessions, users};
+
+    let valid_after = Utc::now();
+
+    db.transaction::<_, ServiceError, _>(|conn| {
+        let updated = diesel::update(users::table.find(target_user_id))
+            .set((
+                users::tokens_valid_after.eq(valid_after),
+                users::updated_at.eq(diesel::dsl::now),
+            ))
+            .execute(conn)?;
+
+        if updated == 0 {
+            return Err(ServiceError::NotFound("User not found".to_string()));
+        }
+
+        suppress_session_notify(conn)?;
+        diesel::update(
+            sessions::table
+                .filter(sessions::user_id.eq(target_user_id))
+                .filter(sessions::revoked_at.is_null()),
+        )
+        .set((
+            sessions::revoked_at.eq(diesel::dsl::now),
+            sessions::updated_at.eq(diesel::dsl::now),
+        ))
+        .execute(conn)?;
+
+        Ok(())
+    })?;
+
+    Ok(valid_after)
+}
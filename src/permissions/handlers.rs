@@ -1,10 +1,19 @@
-use actix_web::{web, HttpResponse, Responder};
+use actix_web::{web, HttpRequest, HttpResponse, Responder};
 use uuid::Uuid;
 
-use crate::db::DbPool;
+use crate::auth::models::Principal;
+use crate::db::{run_blocking, DbPool};
 use crate::errors::ServiceError;
-use crate::permissions::models::{CreatePermissionRequest, CreateRoleRequest};
-use crate::permissions::services::{assign_permission_to_role, assign_role_to_user, create_permission, create_role};
+use crate::permissions::models::{
+    AssignPermissionRequest, CreatePermissionRequest, CreateResourceGrantRequest, CreateRoleRequest,
+    PasswordMaxAgeRequest, PermissionCheckRequest, PermissionCheckResponse, ResourceGrantQuery,
+};
+use crate::permissions::services::{
+    add_role_parent, assign_permission_to_role, assign_role_to_client, assign_role_to_user,
+    check_principal_permission, create_permission, create_resource_grant, create_role, delete_resource_grant,
+    get_effective_permissions, list_resource_grants, remove_role_parent, set_role_password_max_age,
+};
+use crate::utils::middleware::AccessContextBuilder;
 
 pub async fn create_role_handler(
     pool: web::Data<DbPool>,
@@ -12,17 +21,8 @@ pub async fn create_role_handler(
 ) -> impl Responder {
     let role_data = role_data.into_inner();
     
-    // 获取数据库连接
-    let mut conn = match pool.get() {
-        Ok(conn) => conn,
-        Err(e) => {
-            eprintln!("Database connection error: {:?}", e);
-            return HttpResponse::InternalServerError().json("Database connection error");
-        }
-    };
-    
     // 创建角色
-    match create_role(&mut conn, role_data) {
+    match run_blocking(&pool, move |conn| create_role(conn, role_data)).await {
         Ok(role) => HttpResponse::Created().json(role),
         Err(e) => {
             eprintln!("Error creating role: {:?}", e);
@@ -42,17 +42,8 @@ pub async fn create_permission_handler(
 ) -> impl Responder {
     let permission_data = permission_data.into_inner();
     
-    // 获取数据库连接
-    let mut conn = match pool.get() {
-        Ok(conn) => conn,
-        Err(e) => {
-            eprintln!("Database connection error: {:?}", e);
-            return HttpResponse::InternalServerError().json("Database connection error");
-        }
-    };
-    
     // 创建权限
-    match create_permission(&mut conn, permission_data) {
+    match run_blocking(&pool, move |conn| create_permission(conn, permission_data)).await {
         Ok(permission) => HttpResponse::Created().json(permission),
         Err(e) => {
             eprintln!("Error creating permission: {:?}", e);
@@ -60,6 +51,7 @@ pub async fn create_permission_handler(
                 ServiceError::PermissionAlreadyExists => {
                     HttpResponse::Conflict().json("Permission already exists")
                 }
+                ServiceError::BadRequest(msg) => HttpResponse::BadRequest().json(msg),
                 _ => HttpResponse::InternalServerError().json("Failed to create permission")
             }
         }
@@ -72,17 +64,8 @@ pub async fn assign_role_handler(
 ) -> impl Responder {
     let (user_id, role_id) = path.into_inner();
     
-    // 获取数据库连接
-    let mut conn = match pool.get() {
-        Ok(conn) => conn,
-        Err(e) => {
-            eprintln!("Database connection error: {:?}", e);
-            return HttpResponse::InternalServerError().json("Database connection error");
-        }
-    };
-    
     // 分配角色
-    match assign_role_to_user(&mut conn, user_id, role_id) {
+    match run_blocking(&pool, move |conn| assign_role_to_user(conn, user_id, role_id)).await {
         Ok(user_role) => HttpResponse::Created().json(user_role),
         Err(e) => {
             eprintln!("Error assigning role: {:?}", e);
@@ -105,20 +88,24 @@ pub async fn assign_role_handler(
 pub async fn assign_permission_handler(
     pool: web::Data<DbPool>,
     path: web::Path<(Uuid, Uuid)>,
+    request: Option<web::Json<AssignPermissionRequest>>,
 ) -> impl Responder {
     let (role_id, permission_id) = path.into_inner();
-    
-    // 获取数据库连接
-    let mut conn = match pool.get() {
-        Ok(conn) => conn,
-        Err(e) => {
-            eprintln!("Database connection error: {:?}", e);
-            return HttpResponse::InternalServerError().json("Database connection error");
-        }
-    };
+    // 请求体可省略，新建时默认授予 allow，已存在时保持不变
+    let request = request.map(web::Json::into_inner).unwrap_or_default();
     
     // 分配权限
-    match assign_permission_to_role(&mut conn, role_id, permission_id) {
+    match run_blocking(&pool, move |conn| {
+        assign_permission_to_role(
+            conn,
+            role_id,
+            permission_id,
+            request.effect.as_deref(),
+            request.condition.as_ref().map(Option::as_deref),
+        )
+    })
+    .await
+    {
         Ok(role_permission) => HttpResponse::Created().json(role_permission),
         Err(e) => {
             eprintln!("Error assigning permission: {:?}", e);
@@ -132,8 +119,213 @@ pub async fn assign_permission_handler(
                 ServiceError::PermissionNotFound => {
                     HttpResponse::NotFound().json("Permission not found")
                 }
+                ServiceError::BadRequest(msg) => HttpResponse::BadRequest().json(msg),
                 _ => HttpResponse::InternalServerError().json("Failed to assign permission")
             }
         }
     }
 }
+
+pub async fn assign_client_role_handler(
+    pool: web::Data<DbPool>,
+    path: web::Path<(Uuid, Uuid)>,
+) -> impl Responder {
+    let (client_id, role_id) = path.into_inner();
+    
+    // 分配角色给服务客户端
+    match run_blocking(&pool, move |conn| assign_role_to_client(conn, client_id, role_id)).await {
+        Ok(client_role) => HttpResponse::Created().json(client_role),
+        Err(e) => {
+            eprintln!("Error assigning client role: {:?}", e);
+            match e {
+                ServiceError::NotFound(_) => {
+                    HttpResponse::NotFound().json("Client not found")
+                }
+                ServiceError::RoleNotFound => {
+                    HttpResponse::NotFound().json("Role not found")
+                }
+                _ => HttpResponse::InternalServerError().json("Failed to assign role")
+            }
+        }
+    }
+}
+
+pub async fn set_role_password_max_age_handler(
+    pool: web::Data<DbPool>,
+    role_id: web::Path<Uuid>,
+    request: web::Json<PasswordMaxAgeRequest>,
+) -> impl Responder {
+    let role_id = role_id.into_inner();
+    let max_age_days = request.into_inner().max_password_age_days;
+
+    // 设置角色的密码最长使用天数
+    match run_blocking(&pool, move |conn| set_role_password_max_age(conn, role_id, max_age_days)).await {
+        Ok(role) => HttpResponse::Ok().json(role),
+        Err(e) => {
+            eprintln!("Error setting password max age: {:?}", e);
+            match e {
+                ServiceError::BadRequest(msg) => HttpResponse::BadRequest().json(msg),
+                ServiceError::RoleNotFound => {
+                    HttpResponse::NotFound().json("Role not found")
+                }
+                _ => HttpResponse::InternalServerError().json("Failed to update role")
+            }
+        }
+    }
+}
+
+pub async fn add_role_parent_handler(
+    pool: web::Data<DbPool>,
+    path: web::Path<(Uuid, Uuid)>,
+) -> impl Responder {
+    let (role_id, parent_role_id) = path.into_inner();
+
+    // 添加父角色
+    match run_blocking(&pool, move |conn| add_role_parent(conn, role_id, parent_role_id)).await {
+        Ok(role_parent) => HttpResponse::Created().json(role_parent),
+        Err(e) => {
+            eprintln!("Error adding parent role: {:?}", e);
+            match e {
+                ServiceError::Conflict(msg) => HttpResponse::Conflict().json(msg),
+                ServiceError::RoleNotFound => {
+                    HttpResponse::NotFound().json("Role not found")
+                }
+                _ => HttpResponse::InternalServerError().json("Failed to add parent role")
+            }
+        }
+    }
+}
+
+pub async fn remove_role_parent_handler(
+    pool: web::Data<DbPool>,
+    path: web::Path<(Uuid, Uuid)>,
+) -> impl Responder {
+    let (role_id, parent_role_id) = path.into_inner();
+
+    // 移除父角色
+    match run_blocking(&pool, move |conn| remove_role_parent(conn, role_id, parent_role_id)).await {
+        Ok(()) => HttpResponse::NoContent().finish(),
+        Err(e) => {
+            eprintln!("Error removing parent role: {:?}", e);
+            match e {
+                ServiceError::NotFound(msg) => HttpResponse::NotFound().json(msg),
+                _ => HttpResponse::InternalServerError().json("Failed to remove parent role")
+            }
+        }
+    }
+}
+
+pub async fn effective_permissions_handler(
+    pool: web::Data<DbPool>,
+    role_id: web::Path<Uuid>,
+) -> impl Responder {
+    let role_id = role_id.into_inner();
+
+    // 获取角色的有效权限
+    match run_blocking(&pool, move |conn| get_effective_permissions(conn, role_id)).await {
+        Ok(response) => HttpResponse::Ok().json(response),
+        Err(e) => {
+            eprintln!("Error loading effective permissions: {:?}", e);
+            match e {
+                ServiceError::RoleNotFound => {
+                    HttpResponse::NotFound().json("Role not found")
+                }
+                _ => HttpResponse::InternalServerError().json("Failed to load effective permissions")
+            }
+        }
+    }
+}
+
+pub async fn create_resource_grant_handler(
+    pool: web::Data<DbPool>,
+    grant_data: web::Json<CreateResourceGrantRequest>,
+) -> impl Responder {
+    let grant_data = grant_data.into_inner();
+
+    // 创建资源实例级授权
+    match run_blocking(&pool, move |conn| create_resource_grant(conn, grant_data)).await {
+        Ok(grant) => HttpResponse::Created().json(grant),
+        Err(e) => {
+            eprintln!("Error creating resource grant: {:?}", e);
+            match e {
+                ServiceError::BadRequest(msg) => HttpResponse::BadRequest().json(msg),
+                ServiceError::UserNotFound => {
+                    HttpResponse::NotFound().json("User not found")
+                }
+                ServiceError::RoleNotFound => {
+                    HttpResponse::NotFound().json("Role not found")
+                }
+                _ => HttpResponse::InternalServerError().json("Failed to create resource grant")
+            }
+        }
+    }
+}
+
+pub async fn list_resource_grants_handler(
+    pool: web::Data<DbPool>,
+    query: web::Query<ResourceGrantQuery>,
+) -> impl Responder {
+    let query = query.into_inner();
+
+    // 列出资源实例上的授权
+    match run_blocking(&pool, move |conn| list_resource_grants(conn, &query)).await {
+        Ok(grants) => HttpResponse::Ok().json(grants),
+        Err(e) => {
+            eprintln!("Error listing resource grants: {:?}", e);
+            HttpResponse::InternalServerError().json("Failed to list resource grants")
+        }
+    }
+}
+
+pub async fn delete_resource_grant_handler(
+    pool: web::Data<DbPool>,
+    grant_id: web::Path<Uuid>,
+) -> impl Responder {
+    let grant_id = grant_id.into_inner();
+
+    // 删除资源实例级授权
+    match run_blocking(&pool, move |conn| delete_resource_grant(conn, grant_id)).await {
+        Ok(()) => HttpResponse::NoContent().finish(),
+        Err(e) => {
+            eprintln!("Error deleting resource grant: {:?}", e);
+            match e {
+                ServiceError::NotFound(msg) => HttpResponse::NotFound().json(msg),
+                _ => HttpResponse::InternalServerError().json("Failed to delete resource grant")
+            }
+        }
+    }
+}
+
+pub async fn check_permission_handler(
+    req: HttpRequest,
+    pool: web::Data<DbPool>,
+    principal: Principal,
+    request: web::Json<PermissionCheckRequest>,
+) -> impl Responder {
+    let request = request.into_inner();
+
+    // 授权条件的求值上下文，资源属性由调用方提供
+    let context = AccessContextBuilder::from_request(&req)
+        .resource_attributes(request.resource_attributes)
+        .build();
+
+    // 检查当前主体的权限
+    match run_blocking(&pool, move |conn| {
+        check_principal_permission(
+            conn,
+            &principal,
+            &request.resource,
+            &request.action,
+            request.resource_id.as_deref(),
+            &context,
+        )
+    })
+    .await
+    {
+        Ok(allowed) => HttpResponse::Ok().json(PermissionCheckResponse { allowed }),
+        Err(e) => {
+            eprintln!("Error checking permission: {:?}", e);
+            HttpResponse::InternalServerError().json("Failed to check permission")
+        }
+    }
+}
@@ -1,3 +1,4 @@
 pub mod models;
+pub mod pattern;
 pub mod services;
 pub mod handlers;
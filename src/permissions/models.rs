@@ -3,8 +3,9 @@ use serde::{Deserialize, Serialize};
 use uuid::Uuid;
 use chrono::{DateTime, Utc};
 
-use crate::db::schema::{roles, user_roles, permissions, role_permissions};
+use crate::db::schema::{client_roles, permissions, resource_grants, role_parents, role_permissions, roles, user_roles};
 use crate::auth::models::User;
+use crate::oauth::models::Client;
 
 #[derive(Debug, Serialize, Deserialize, Queryable, Identifiable)]
 #[diesel(table_name = roles)]
@@ -14,6 +15,8 @@ pub struct Role {
     pub description: Option<String>,
     pub created_at: DateTime<Utc>,
     pub updated_at: DateTime<Utc>,
+    // 密码最长使用天数
+    pub max_password_age_days: Option<i32>,
 }
 
 #[derive(Debug, Serialize, Deserialize, Insertable)]
@@ -21,6 +24,7 @@ pub struct Role {
 pub struct NewRole {
     pub name: String,
     pub description: Option<String>,
+    pub max_password_age_days: Option<i32>,
 }
 
 #[derive(Debug, Serialize, Deserialize, Queryable, Identifiable)]
@@ -55,6 +59,17 @@ pub struct UserRole {
     pub created_at: DateTime<Utc>,
 }
 
+#[derive(Debug, Serialize, Deserialize, Queryable, Identifiable, Associations)]
+#[diesel(belongs_to(Role))]
+#[diesel(belongs_to(Client))]
+#[diesel(table_name = client_roles)]
+pub struct ClientRole {
+    pub id: Uuid,
+    pub client_id: Uuid,
+    pub role_id: Uuid,
+    pub created_at: DateTime<Utc>,
+}
+
 #[derive(Debug, Serialize, Deserialize, Queryable, Identifiable, Associations)]
 #[diesel(belongs_to(Role))]
 #[diesel(belongs_to(Permission))]
@@ -64,12 +79,71 @@ pub struct RolePermission {
     pub role_id: Uuid,
     pub permission_id: Uuid,
     pub created_at: DateTime<Utc>,
+    // 授权效果：allow 或 deny
+    pub effect: String,
+    // 授权条件表达式，满足时授权才生效
+    pub condition: Option<String>,
+}
+
+// 授权效果，deny 优先于 allow
+pub const EFFECT_ALLOW: &str = "allow";
+pub const EFFECT_DENY: &str = "deny";
+
+// 为角色分配权限时的可选参数
+//
+// 新建时未指定效果为 allow，未指定条件为无条件生效；已存在时只更新显式提供的字段，
+// condition 显式设为 null 表示清除条件。
+#[derive(Debug, Default, Serialize, Deserialize)]
+pub struct AssignPermissionRequest {
+    pub effect: Option<String>,
+    #[serde(default, deserialize_with = "deserialize_present")]
+    pub condition: Option<Option<String>>,
+}
+
+// 区分字段缺失（None）与显式的 null（Some(None)）
+fn deserialize_present<'de, D, T>(deserializer: D) -> Result<Option<Option<T>>, D::Error>
+where
+    D: serde::Deserializer<'de>,
+    T: Deserialize<'de>,
+{
+    Option::<T>::deserialize(deserializer).map(Some)
+}
+
+// 角色继承关系，role_id 继承 parent_role_id 的权限
+#[derive(Debug, Serialize, Deserialize, Queryable, Identifiable)]
+#[diesel(table_name = role_parents)]
+pub struct RoleParent {
+    pub id: Uuid,
+    pub role_id: Uuid,
+    pub parent_role_id: Uuid,
+    pub created_at: DateTime<Utc>,
+}
+
+// 资源实例级授权，授予用户或角色对某个具体对象的操作权限
+#[derive(Debug, Serialize, Deserialize, Queryable, Identifiable)]
+#[diesel(table_name = resource_grants)]
+pub struct ResourceGrant {
+    pub id: Uuid,
+    pub resource_type: String,
+    pub resource_id: String,
+    pub action: String,
+    pub user_id: Option<Uuid>,
+    pub role_id: Option<Uuid>,
+    pub effect: String,
+    pub created_at: DateTime<Utc>,
 }
 
 #[derive(Debug, Serialize, Deserialize)]
 pub struct CreateRoleRequest {
     pub name: String,
     pub description: Option<String>,
+    pub max_password_age_days: Option<i32>,
+}
+
+// 设置角色的密码最长使用天数，为空表示不限制
+#[derive(Debug, Serialize, Deserialize)]
+pub struct PasswordMaxAgeRequest {
+    pub max_password_age_days: Option<i32>,
 }
 
 #[derive(Debug, Serialize, Deserialize)]
@@ -95,3 +169,57 @@ pub struct PermissionResponse {
     pub resource: String,
     pub action: String,
 }
+
+// 有效权限中的一项；同一权限同时被允许和禁止，或被覆盖它的无条件 deny 模式否决时按 deny 展示
+#[derive(Debug, Serialize, Deserialize)]
+pub struct EffectivePermission {
+    #[serde(flatten)]
+    pub permission: Permission,
+    pub effect: String,
+    pub condition: Option<String>,
+    // 覆盖该 allow 的 deny 权限，如 documents:* 覆盖 documents:read；带条件的 deny 仅在条件成立时生效
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub overridden_by: Vec<Uuid>,
+}
+
+// 角色的有效权限，包含从所有祖先角色继承的权限
+#[derive(Debug, Serialize, Deserialize)]
+pub struct EffectivePermissionsResponse {
+    pub role_id: Uuid,
+    pub inherited_role_ids: Vec<Uuid>,
+    pub permissions: Vec<EffectivePermission>,
+}
+
+// 创建资源实例级授权，user_id 和 role_id 必须且只能提供一个
+#[derive(Debug, Serialize, Deserialize)]
+pub struct CreateResourceGrantRequest {
+    pub resource_type: String,
+    pub resource_id: String,
+    pub action: String,
+    pub user_id: Option<Uuid>,
+    pub role_id: Option<Uuid>,
+    pub effect: Option<String>,
+}
+
+// 按资源实例查询授权
+#[derive(Debug, Serialize, Deserialize)]
+pub struct ResourceGrantQuery {
+    pub resource_type: String,
+    pub resource_id: String,
+}
+
+// 检查当前主体的权限，提供 resource_id 时同时考虑该实例上的授权
+#[derive(Debug, Serialize, Deserialize)]
+pub struct PermissionCheckRequest {
+    pub resource: String,
+    pub action: String,
+    pub resource_id: Option<String>,
+    // 资源属性，供授权条件中的 resource.* 引用
+    #[serde(default)]
+    pub resource_attributes: serde_json::Map<String, serde_json::Value>,
+}
+
+#[derive(Debug, Serialize, Deserialize)]
+pub struct PermissionCheckResponse {
+    pub allowed: bool,
+}
@@ -0,0 +1,164 @@
+use crate::errors::ServiceError;
+
+const WILDCARD: &str = "*";
+
+/// 权限模式 `(pattern_resource, pattern_action)` 是否覆盖对 `resource` 执行 `action`
+///
+/// - `action` 为 `*` 时匹配任意操作，否则必须完全相等；
+/// - `resource` 按 `/` 分段逐段比较，`*` 段匹配恰好一段；位于末尾的 `*` 段匹配剩余的
+///   一段或多段，因此单独的 `*` 匹配任意资源，`billing/*` 匹配 `billing/invoices` 和
+///   `billing/invoices/42`，但不匹配 `billing` 本身。
+///
+/// 模式按字面存储，`(resource, action)` 唯一约束针对字面值：`documents:*` 与
+/// `documents:read` 是两条不同的权限，可以同时存在。
+pub fn permission_matches(pattern_resource: &str, pattern_action: &str, resource: &str, action: &str) -> bool {
+    action_matches(pattern_action, action) && resource_matches(pattern_resource, resource)
+}
+
+fn action_matches(pattern: &str, action: &str) -> bool {
+    pattern == WILDCARD || pattern == action
+}
+
+fn resource_matches(pattern: &str, resource: &str) -> bool {
+    let pattern_segments: Vec<&str> = pattern.split('/').collect();
+    let resource_segments: Vec<&str> = resource.split('/').collect();
+
+    for (index, pattern_segment) in pattern_segments.iter().enumerate() {
+        let resource_segment = match resource_segments.get(index) {
+            Some(segment) => segment,
+            None => return false,
+        };
+
+        if *pattern_segment == WILDCARD {
+            if resource_segment.is_empty() {
+                return false;
+            }
+            // 末尾通配匹配剩余的全部分段
+            if index == pattern_segments.len() - 1 {
+                return true;
+            }
+        } else if pattern_segment != resource_segment {
+            return false;
+        }
+    }
+
+    pattern_segments.len() == resource_segments.len()
+}
+
+/// 校验权限模式的语法，不合法时返回 `BadRequest`
+///
+/// `*` 只能作为完整的段出现（`doc*` 之类的部分通配不合法），`:` 在作用域中分隔资源和操作，
+/// 不允许出现在资源或操作中。
+pub fn validate_permission_pattern(resource: &str, action: &str) -> Result<(), ServiceError> {
+    let valid_segment = |segment: &str| {
+        segment == WILDCARD || (!segment.is_empty() && !segment.contains(['*', ':']))
+    };
+
+    if !resource.split('/').all(valid_segment) {
+        return Err(ServiceError::BadRequest(format!(
+            "Invalid resource pattern '{}': segments must be non-empty and '*' must be a whole segment",
+            resource
+        )));
+    }
+
+    if !valid_segment(action) || action.contains('/') {
+        return Err(ServiceError::BadRequest(format!(
+            "Invalid action pattern '{}': use '*' or a plain action name",
+            action
+        )));
+    }
+
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn exact_permission_matches_only_itself() {
+        assert!(permission_matches("documents", "read", "documents", "read"));
+        assert!(!permission_matches("documents", "read", "documents", "write"));
+        assert!(!permission_matches("documents", "read", "documents/42", "read"));
+        assert!(!permission_matches("documents/42", "read", "documents", "read"));
+    }
+
+    #[test]
+    fn wildcard_action_matches_any_action() {
+        assert!(permission_matches("documents", "*", "documents", "read"));
+        assert!(permission_matches("documents", "*", "documents", "delete"));
+        assert!(!permission_matches("documents", "*", "billing", "read"));
+    }
+
+    #[test]
+    fn wildcard_resource_matches_any_resource() {
+        assert!(permission_matches("*", "read", "documents", "read"));
+        assert!(permission_matches("*", "read", "billing/invoices/42", "read"));
+        assert!(!permission_matches("*", "read", "documents", "write"));
+        assert!(permission_matches("*", "*", "billing/invoices", "delete"));
+    }
+
+    #[test]
+    fn trailing_wildcard_matches_one_or_more_segments() {
+        assert!(permission_matches("billing/*", "read", "billing/invoices", "read"));
+        assert!(permission_matches("billing/*", "read", "billing/invoices/42", "read"));
+        assert!(permission_matches("billing/*", "read", "billing/invoices/42/lines", "read"));
+    }
+
+    #[test]
+    fn trailing_wildcard_does_not_match_parent() {
+        assert!(!permission_matches("billing/*", "read", "billing", "read"));
+        assert!(!permission_matches("billing/*", "read", "billing/", "read"));
+        assert!(!permission_matches("billing/*", "read", "billingx/invoices", "read"));
+    }
+
+    #[test]
+    fn middle_wildcard_matches_exactly_one_segment() {
+        assert!(permission_matches("billing/*/lines", "read", "billing/42/lines", "read"));
+        assert!(!permission_matches("billing/*/lines", "read", "billing/lines", "read"));
+        assert!(!permission_matches("billing/*/lines", "read", "billing/42/43/lines", "read"));
+        assert!(!permission_matches("billing/*/lines", "read", "billing//lines", "read"));
+        assert!(!permission_matches("billing/*/lines", "read", "billing/42/lines/1", "read"));
+    }
+
+    #[test]
+    fn empty_resource_segments_do_not_match_wildcards() {
+        assert!(!permission_matches("*", "read", "", "read"));
+        assert!(!permission_matches("*/lines", "read", "/lines", "read"));
+        assert!(!permission_matches("billing/*", "read", "billing//", "read"));
+    }
+
+    #[test]
+    fn validate_accepts_whole_segment_wildcards() {
+        assert!(validate_permission_pattern("documents", "read").is_ok());
+        assert!(validate_permission_pattern("*", "read").is_ok());
+        assert!(validate_permission_pattern("documents", "*").is_ok());
+        assert!(validate_permission_pattern("*", "*").is_ok());
+        assert!(validate_permission_pattern("billing/*", "read").is_ok());
+        assert!(validate_permission_pattern("billing/*/lines", "read").is_ok());
+    }
+
+    #[test]
+    fn validate_rejects_partial_wildcards() {
+        assert!(validate_permission_pattern("doc*", "read").is_err());
+        assert!(validate_permission_pattern("billing/inv*", "read").is_err());
+        assert!(validate_permission_pattern("**", "read").is_err());
+        assert!(validate_permission_pattern("documents", "re*").is_err());
+    }
+
+    #[test]
+    fn validate_rejects_empty_segments() {
+        assert!(validate_permission_pattern("", "read").is_err());
+        assert!(validate_permission_pattern("billing/", "read").is_err());
+        assert!(validate_permission_pattern("/billing", "read").is_err());
+        assert!(validate_permission_pattern("billing//invoices", "read").is_err());
+        assert!(validate_permission_pattern("documents", "").is_err());
+    }
+
+    #[test]
+    fn validate_rejects_separators() {
+        assert!(validate_permission_pattern("documents:read", "read").is_err());
+        assert!(validate_permission_pattern("documents", "read:all").is_err());
+        assert!(validate_permission_pattern("documents", "read/all").is_err());
+    }
+}
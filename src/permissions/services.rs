@@ -1,7 +1,12 @@
 use diesel::prelude::*;
+use serde_json::{Map, Value};
 use uuid::Uuid;
 
+use crate::auth::models::{Principal, PrincipalKind};
+use crate::config::CONFIG;
 use crate::permissions::models::*;
+use crate::permissions::pattern::{permission_matches, validate_permission_pattern};
+use crate::utils::condition::{AccessContext, Condition};
 use crate::errors::ServiceError;
 
 // 创建角色
@@ -25,6 +30,7 @@ pub fn create_role(
     let new_role = NewRole {
         name: role_data.name,
         description: role_data.description,
+        max_password_age_days: role_data.max_password_age_days,
     };
     
     let role = diesel::insert_into(roles)
@@ -41,9 +47,15 @@ pub fn create_permission(
 ) -> Result<Permission, ServiceError> {
     use crate::db::schema::permissions::dsl::*;
     
-    // 检查权限是否已存在
+    // 资源和操作可以是通配模式，先校验语法
+    validate_permission_pattern(&permission_data.resource, &permission_data.action)?;
+    
+    // 检查权限是否已存在（名称或资源与操作的组合相同）
     let existing_permission_count = permissions
-        .filter(name.eq(&permission_data.name))
+        .filter(
+            name.eq(&permission_data.name)
+                .or(resource.eq(&permission_data.resource).and(action.eq(&permission_data.action))),
+        )
         .count()
         .get_result::<i64>(db)?;
     
@@ -122,30 +134,50 @@ pub fn assign_role_to_user(
     Ok(user_role)
 }
 
-// 分配权限给角色
+// 分配权限给角色，effect 为 allow 或 deny，condition 为可选的授权条件；已分配时更新两者
 pub fn assign_permission_to_role(
     db: &mut PgConnection,
     role_id: Uuid,
     permission_id: Uuid,
+    effect: Option<&str>,
+    condition: Option<Option<&str>>,
 ) -> Result<RolePermission, ServiceError> {
     use crate::db::schema::role_permissions;
     use crate::db::schema::roles;
     use crate::db::schema::permissions;
     
-    // 检查角色权限是否已存在
-    let existing_count: i64 = role_permissions::table
+    if effect.is_some_and(|effect| effect != EFFECT_ALLOW && effect != EFFECT_DENY) {
+        return Err(ServiceError::BadRequest("effect must be 'allow' or 'deny'".to_string()));
+    }
+    
+    // 保存前先确认条件表达式可以解析
+    if let Some(Some(condition)) = condition {
+        Condition::parse(condition)?;
+    }
+    
+    // 检查角色权限是否已存在，已存在时只更新显式提供的效果和条件
+    let existing = role_permissions::table
         .filter(role_permissions::role_id.eq(role_id).and(role_permissions::permission_id.eq(permission_id)))
-        .select(diesel::dsl::count_star())
-        .first(db)?;
+        .first::<RolePermission>(db)
+        .optional()?;
     
-    if existing_count > 0 {
-        let existing = role_permissions::table
-            .filter(role_permissions::role_id.eq(role_id).and(role_permissions::permission_id.eq(permission_id)))
-            .first::<RolePermission>(db)?;
+    if let Some(existing) = existing {
+        if effect.is_none() && condition.is_none() {
+            return Ok(existing);
+        }
         
-        return Ok(existing);
+        let updated = diesel::update(role_permissions::table.find(existing.id))
+            .set((
+                role_permissions::effect.eq(effect.unwrap_or(&existing.effect)),
+                role_permissions::condition.eq(condition.unwrap_or(existing.condition.as_deref())),
+            ))
+            .get_result::<RolePermission>(db)?;
+        return Ok(updated);
     }
     
+    let effect = effect.unwrap_or(EFFECT_ALLOW);
+    let condition = condition.flatten();
+    
     // 检查角色是否存在
     let role_exists: i64 = roles::table
         .filter(roles::id.eq(role_id))
@@ -171,6 +203,8 @@ pub fn assign_permission_to_role(
         role_permissions::role_id.eq(role_id),
         role_permissions::permission_id.eq(permission_id),
         role_permissions::created_at.eq(diesel::dsl::now),
+        role_permissions::effect.eq(effect),
+        role_permissions::condition.eq(condition),
     );
     
     let role_permission = diesel::insert_into(role_permissions::table)
@@ -208,48 +242,662 @@ pub fn get_role_permissions(
     Ok(role_permissions_list)
 }
 
-// 检查用户是否有特定权限
-pub fn check_user_permission(
+// 获取一组角色及其全部祖先角色（传递闭包），结果包含原角色
+fn expand_role_ancestors(
     db: &mut PgConnection,
-    user_id: Uuid,
+    role_ids: &[Uuid],
+) -> Result<Vec<Uuid>, ServiceError> {
+    use crate::db::schema::role_parents;
+
+    let mut expanded: Vec<Uuid> = role_ids.to_vec();
+    let mut frontier: Vec<Uuid> = role_ids.to_vec();
+
+    // 逐层向上查找父角色，已访问的角色不再展开，即使数据中存在环也能终止
+    while !frontier.is_empty() {
+        let parents = role_parents::table
+            .filter(role_parents::role_id.eq_any(&frontier))
+            .select(role_parents::parent_role_id)
+            .load::<Uuid>(db)?;
+
+        frontier.clear();
+        for parent in parents {
+            if !expanded.contains(&parent) {
+                expanded.push(parent);
+                frontier.push(parent);
+            }
+        }
+    }
+
+    Ok(expanded)
+}
+
+/// 获取一组角色及其全部后代角色（继承这些角色的角色），结果包含原角色
+///
+/// 拥有后代角色的用户同样拥有原角色，按角色作用于用户（如强制修改密码）时使用。
+pub fn expand_role_descendants(
+    db: &mut PgConnection,
+    role_ids: &[Uuid],
+) -> Result<Vec<Uuid>, ServiceError> {
+    use crate::db::schema::role_parents;
+
+    let mut expanded: Vec<Uuid> = role_ids.to_vec();
+    let mut frontier: Vec<Uuid> = role_ids.to_vec();
+
+    // 逐层向下查找子角色，已访问的角色不再展开，即使数据中存在环也能终止
+    while !frontier.is_empty() {
+        let children = role_parents::table
+            .filter(role_parents::parent_role_id.eq_any(&frontier))
+            .select(role_parents::role_id)
+            .load::<Uuid>(db)?;
+
+        frontier.clear();
+        for child in children {
+            if !expanded.contains(&child) {
+                expanded.push(child);
+                frontier.push(child);
+            }
+        }
+    }
+
+    Ok(expanded)
+}
+
+/// 为角色添加父角色，使其继承父角色（及其祖先）的全部权限
+///
+/// 如果父角色本身已经（直接或间接）继承该角色，添加后会形成环，返回 `Conflict`。
+pub fn add_role_parent(
+    db: &mut PgConnection,
+    target_role_id: Uuid,
+    parent_role_id: Uuid,
+) -> Result<RoleParent, ServiceError> {
+    use crate::db::schema::{role_parents, roles};
+
+    if target_role_id == parent_role_id {
+        return Err(ServiceError::Conflict("A role cannot inherit itself".to_string()));
+    }
+
+    db.transaction(|db| {
+        // 串行化继承关系的修改，避免两个并发插入各自通过检查后共同形成环
+        diesel::sql_query("LOCK TABLE role_parents IN SHARE ROW EXCLUSIVE MODE").execute(db)?;
+
+        let existing = role_parents::table
+            .filter(role_parents::role_id.eq(target_role_id).and(role_parents::parent_role_id.eq(parent_role_id)))
+            .first::<RoleParent>(db)
+            .optional()?;
+
+        if let Some(existing) = existing {
+            return Ok(existing);
+        }
+
+        // 检查两个角色是否存在
+        let role_count: i64 = roles::table
+            .filter(roles::id.eq_any([target_role_id, parent_role_id]))
+            .select(diesel::dsl::count_star())
+            .first(db)?;
+
+        if role_count < 2 {
+            return Err(ServiceError::RoleNotFound);
+        }
+
+        // 父角色的祖先中包含该角色，说明会形成环
+        if expand_role_ancestors(db, &[parent_role_id])?.contains(&target_role_id) {
+            return Err(ServiceError::Conflict("Role inheritance would create a cycle".to_string()));
+        }
+
+        let new_role_parent = (
+            role_parents::role_id.eq(target_role_id),
+            role_parents::parent_role_id.eq(parent_role_id),
+            role_parents::created_at.eq(diesel::dsl::now),
+        );
+
+        let role_parent = diesel::insert_into(role_parents::table)
+            .values(new_role_parent)
+            .get_result::<RoleParent>(db)?;
+
+        Ok(role_parent)
+    })
+}
+
+/// 移除角色的父角色
+pub fn remove_role_parent(
+    db: &mut PgConnection,
+    target_role_id: Uuid,
+    parent_role_id: Uuid,
+) -> Result<(), ServiceError> {
+    use crate::db::schema::role_parents;
+
+    let deleted = diesel::delete(
+        role_parents::table
+            .filter(role_parents::role_id.eq(target_role_id).and(role_parents::parent_role_id.eq(parent_role_id))),
+    )
+    .execute(db)?;
+
+    if deleted == 0 {
+        return Err(ServiceError::NotFound("Role parent not found".to_string()));
+    }
+
+    Ok(())
+}
+
+/// 获取角色的有效权限，包含从所有祖先角色继承的权限
+pub fn get_effective_permissions(
+    db: &mut PgConnection,
+    target_role_id: Uuid,
+) -> Result<EffectivePermissionsResponse, ServiceError> {
+    use crate::db::schema::{permissions, role_permissions, roles};
+
+    let role_exists: i64 = roles::table
+        .filter(roles::id.eq(target_role_id))
+        .select(diesel::dsl::count_star())
+        .first(db)?;
+
+    if role_exists == 0 {
+        return Err(ServiceError::RoleNotFound);
+    }
+
+    let role_ids = expand_role_ancestors(db, &[target_role_id])?;
+
+    let grants = role_permissions::table
+        .inner_join(permissions::table)
+        .filter(role_permissions::role_id.eq_any(&role_ids))
+        .order((permissions::resource.asc(), permissions::action.asc()))
+        .select((permissions::all_columns, role_permissions::effect, role_permissions::condition))
+        .load::<(Permission, String, Option<String>)>(db)?;
+
+    // 同一权限可能由多个祖先角色授予，条件相同的授权合并时 deny 优先；
+    // 条件不同的授权分别列出，由检查时的上下文决定哪些生效
+    let mut effective_permissions: Vec<EffectivePermission> = Vec::new();
+    for (permission, effect, condition) in grants {
+        match effective_permissions
+            .iter_mut()
+            .find(|p| p.permission.id == permission.id && p.condition == condition)
+        {
+            Some(existing) => {
+                if effect == EFFECT_DENY {
+                    existing.effect = effect;
+                }
+            }
+            None => effective_permissions.push(EffectivePermission {
+                permission,
+                effect,
+                condition,
+                overridden_by: Vec::new(),
+            }),
+        }
+    }
+
+    // 与检查时的 deny 优先一致：模式完全覆盖某条 allow 的 deny（包括同一权限上条件不同的 deny）
+    // 会否决它，如 documents:* deny 否决 documents:read allow；无条件的 deny 直接按 deny 展示
+    let denies: Vec<(Uuid, String, String, bool)> = effective_permissions
+        .iter()
+        .filter(|p| p.effect == EFFECT_DENY)
+        .map(|p| {
+            (p.permission.id, p.permission.resource.clone(), p.permission.action.clone(), p.condition.is_none())
+        })
+        .collect();
+    for allowed in effective_permissions.iter_mut().filter(|p| p.effect == EFFECT_ALLOW) {
+        for (deny_id, deny_resource, deny_action, unconditional) in &denies {
+            if !permission_matches(deny_resource, deny_action, &allowed.permission.resource, &allowed.permission.action) {
+                continue;
+            }
+            allowed.overridden_by.push(*deny_id);
+            if *unconditional {
+                allowed.effect = EFFECT_DENY.to_string();
+            }
+        }
+    }
+
+    Ok(EffectivePermissionsResponse {
+        role_id: target_role_id,
+        inherited_role_ids: role_ids.into_iter().skip(1).collect(),
+        permissions: effective_permissions,
+    })
+}
+
+// 检查主体（用户本人和/或一组角色，含继承的祖先角色）是否拥有特定权限
+//
+// 类型级权限可以是通配模式并可带条件；提供 resource_id 时同时考虑该资源实例上的授权。
+// 采用 deny 优先：任一匹配的 deny 授权都会否决其他来源的 allow。
+fn roles_have_permission(
+    db: &mut PgConnection,
+    user_id: Option<Uuid>,
+    role_ids: &[Uuid],
     resource: &str,
     action: &str,
+    resource_id: Option<&str>,
+    mut context: AccessContext,
 ) -> Result<bool, ServiceError> {
+    use crate::db::schema::roles;
+    
+    // 既没有角色也没有实例级授权时，没有权限
+    if role_ids.is_empty() && (user_id.is_none() || resource_id.is_none()) {
+        return Ok(false);
+    }
+    
+    // 展开继承的祖先角色
+    let role_ids = expand_role_ancestors(db, role_ids)?;
+    
+    // 补充条件表达式可引用的角色名和资源标识
+    let role_names = roles::table
+        .filter(roles::id.eq_any(&role_ids))
+        .select(roles::name)
+        .load::<String>(db)?;
+    context.user.insert("roles".to_string(), role_names.into());
+    context.resource.insert("type".to_string(), resource.into());
+    if let Some(resource_id) = resource_id {
+        context.resource.insert("id".to_string(), resource_id.into());
+    }
+    
+    let mut effects = role_permission_effects(db, &role_ids, resource, action, &context)?;
+    if let Some(resource_id) = resource_id {
+        effects.extend(resource_grant_effects(db, user_id, &role_ids, resource, resource_id, action)?);
+    }
+    
+    Ok(!effects.iter().any(|effect| effect == EFFECT_DENY) && effects.iter().any(|effect| effect == EFFECT_ALLOW))
+}
+
+// 一组角色的类型级权限中与请求匹配、且条件成立的授权效果
+//
+// 条件无法解析或求值失败（如引用了上下文中没有的属性）时按最保守的方式处理：
+// allow 授权不生效，deny 授权生效。
+fn role_permission_effects(
+    db: &mut PgConnection,
+    role_ids: &[Uuid],
+    resource: &str,
+    action: &str,
+    context: &AccessContext,
+) -> Result<Vec<String>, ServiceError> {
     use crate::db::schema::permissions;
     use crate::db::schema::role_permissions;
     
-    // 获取用户的所有角色
-    let user_roles_list = get_user_roles(db, user_id)?;
+    if role_ids.is_empty() {
+        return Ok(Vec::new());
+    }
     
-    // 如果用户没有角色，则没有权限
-    if user_roles_list.is_empty() {
-        return Ok(false);
+    // 加载角色拥有的全部权限模式，操作不符的在数据库中先行排除
+    let granted = role_permissions::table
+        .inner_join(permissions::table)
+        .filter(role_permissions::role_id.eq_any(role_ids))
+        .filter(permissions::action.eq(action).or(permissions::action.eq("*")))
+        .select((permissions::resource, permissions::action, role_permissions::effect, role_permissions::condition))
+        .distinct()
+        .load::<(String, String, String, Option<String>)>(db)?;
+    
+    Ok(granted
+        .into_iter()
+        .filter(|(pattern_resource, pattern_action, _, _)| {
+            permission_matches(pattern_resource, pattern_action, resource, action)
+        })
+        .filter(|(_, _, effect, condition)| {
+            let condition = match condition {
+                Some(condition) => condition,
+                None => return true,
+            };
+            match Condition::parse(condition).and_then(|condition| condition.evaluate(context)) {
+                Ok(satisfied) => satisfied,
+                Err(e) => {
+                    log::warn!("授权条件求值失败 ({}): {}", condition, e);
+                    effect == EFFECT_DENY
+                }
+            }
+        })
+        .map(|(_, _, effect, _)| effect)
+        .collect())
+}
+
+// 资源实例上授予用户本人或其角色、且与操作匹配的授权效果
+fn resource_grant_effects(
+    db: &mut PgConnection,
+    user_id: Option<Uuid>,
+    role_ids: &[Uuid],
+    resource: &str,
+    resource_id: &str,
+    action: &str,
+) -> Result<Vec<String>, ServiceError> {
+    use crate::db::schema::resource_grants;
+    
+    let mut query = resource_grants::table
+        .filter(resource_grants::resource_type.eq(resource))
+        .filter(resource_grants::resource_id.eq(resource_id))
+        .filter(resource_grants::action.eq(action).or(resource_grants::action.eq("*")))
+        .filter(resource_grants::role_id.eq_any(role_ids))
+        .select(resource_grants::effect)
+        .into_boxed();
+    
+    if let Some(user_id) = user_id {
+        query = query.or_filter(
+            resource_grants::resource_type.eq(resource)
+                .and(resource_grants::resource_id.eq(resource_id))
+                .and(resource_grants::action.eq(action).or(resource_grants::action.eq("*")))
+                .and(resource_grants::user_id.eq(user_id)),
+        );
     }
     
-    // 获取权限
-    let permission = permissions::table
-        .filter(permissions::resource.eq(resource).and(permissions::action.eq(action)))
-        .first::<Permission>(db)
-        .optional()?;
+    Ok(query.load::<String>(db)?)
+}
+
+// 检查用户是否有特定权限，提供 resource_id 时同时考虑该资源实例上的授权，
+// 带条件的授权在 context 中求值
+pub fn check_user_permission(
+    db: &mut PgConnection,
+    user_id: Uuid,
+    resource: &str,
+    action: &str,
+    resource_id: Option<&str>,
+    context: &AccessContext,
+) -> Result<bool, ServiceError> {
+    use crate::db::schema::users;
     
-    let permission = match permission {
-        Some(p) => p,
+    // 获取用户的所有角色
+    let role_ids: Vec<Uuid> = get_user_roles(db, user_id)?
+        .into_iter()
+        .map(|user_role| user_role.role_id)
+        .collect();
+    
+    // 条件表达式可引用的用户属性，用户已不存在时没有任何权限
+    let (email, full_name) = match users::table
+        .find(user_id)
+        .select((users::email, users::full_name))
+        .first::<(String, Option<String>)>(db)
+        .optional()?
+    {
+        Some(user) => user,
         None => return Ok(false),
     };
     
-    // 检查用户的角色是否有该权限
+    let mut context = context.clone();
+    context.user.insert("id".to_string(), user_id.to_string().into());
+    context.user.insert("kind".to_string(), "user".into());
+    context.user.insert("email".to_string(), email.into());
+    context.user.insert("full_name".to_string(), full_name.into());
+    
+    roles_have_permission(db, Some(user_id), &role_ids, resource, action, resource_id, context)
+}
 
-    for user_role in user_roles_list {
-        // --- 修改后的查询 ---
-        let role_permission_count: i64 = role_permissions::table // 1. 先指定 table
-            .filter(role_permissions::role_id.eq(user_role.role_id).and(role_permissions::permission_id.eq(permission.id))) // 2. 再 filter
-            .select(diesel::dsl::count_star())                   // 3. 再 select (count(*))
-            .first(db)?;                                         // 4. 执行
-        // --- 修改结束 ---
+// 分配角色给服务客户端
+pub fn assign_role_to_client(
+    db: &mut PgConnection,
+    client_id: Uuid,
+    role_id: Uuid,
+) -> Result<ClientRole, ServiceError> {
+    use crate::db::schema::client_roles;
+    use crate::db::schema::clients;
+    use crate::db::schema::roles;
+    
+    // 检查客户端角色是否已存在
+    let existing = client_roles::table
+        .filter(client_roles::client_id.eq(client_id).and(client_roles::role_id.eq(role_id)))
+        .first::<ClientRole>(db)
+        .optional()?;
+    
+    if let Some(existing) = existing {
+        return Ok(existing);
+    }
+    
+    // 检查客户端是否存在
+    let client_exists: i64 = clients::table
+        .filter(clients::id.eq(client_id))
+        .select(diesel::dsl::count_star())
+        .first(db)?;
+    
+    if client_exists == 0 {
+        return Err(ServiceError::NotFound("Client not found".to_string()));
+    }
+    
+    // 检查角色是否存在
+    let role_exists: i64 = roles::table
+        .filter(roles::id.eq(role_id))
+        .select(diesel::dsl::count_star())
+        .first(db)?;
+    
+    if role_exists == 0 {
+        return Err(ServiceError::RoleNotFound);
+    }
+    
+    // 创建客户端角色关联
+    let new_client_role = (
+        client_roles::client_id.eq(client_id),
+        client_roles::role_id.eq(role_id),
+        client_roles::created_at.eq(diesel::dsl::now),
+    );
+    
+    let client_role = diesel::insert_into(client_roles::table)
+        .values(new_client_role)
+        .get_result::<ClientRole>(db)?;
+    
+    Ok(client_role)
+}
+
+// 获取服务客户端的所有角色
+pub fn get_client_roles(
+    db: &mut PgConnection,
+    target_client_id: Uuid,
+) -> Result<Vec<ClientRole>, ServiceError> {
+    use crate::db::schema::client_roles;
+    
+    let client_roles_list = client_roles::table
+        .filter(client_roles::client_id.eq(target_client_id))
+        .load::<ClientRole>(db)?;
+    
+    Ok(client_roles_list)
+}
 
-        if role_permission_count > 0 {
-            return Ok(true);
+// 检查服务客户端是否有特定权限
+pub fn check_client_permission(
+    db: &mut PgConnection,
+    client_id: Uuid,
+    resource: &str,
+    action: &str,
+    resource_id: Option<&str>,
+    context: &AccessContext,
+) -> Result<bool, ServiceError> {
+    let role_ids: Vec<Uuid> = get_client_roles(db, client_id)?
+        .into_iter()
+        .map(|client_role| client_role.role_id)
+        .collect();
+    
+    // 客户端在条件表达式中同样通过 user.* 引用
+    let mut context = context.clone();
+    context.user.insert("id".to_string(), client_id.to_string().into());
+    context.user.insert("kind".to_string(), "client".into());
+    
+    // 实例级授权只能授予用户或角色，客户端通过其角色获得
+    roles_have_permission(db, None, &role_ids, resource, action, resource_id, context)
+}
+
+// 检查主体（用户或服务客户端）是否有特定权限，同时受令牌作用域限制
+pub fn check_principal_permission(
+    db: &mut PgConnection,
+    principal: &Principal,
+    resource: &str,
+    action: &str,
+    resource_id: Option<&str>,
+    context: &AccessContext,
+) -> Result<bool, ServiceError> {
+    if !principal.scope_allows(resource, action) {
+        return Ok(false);
+    }
+    
+    match principal.kind {
+        PrincipalKind::User => check_user_permission(db, principal.id, resource, action, resource_id, context),
+        PrincipalKind::Client => {
+            check_client_permission(db, principal.id, resource, action, resource_id, context)
         }
     }
-    Ok(false)
+}
+
+/// 创建资源实例级授权；相同资源、操作和授权对象已存在时更新其效果
+pub fn create_resource_grant(
+    db: &mut PgConnection,
+    grant_data: CreateResourceGrantRequest,
+) -> Result<ResourceGrant, ServiceError> {
+    use crate::db::schema::{resource_grants, roles, users};
+
+    // 资源类型必须是具体类型，操作可以是 `*`
+    validate_permission_pattern(&grant_data.resource_type, &grant_data.action)?;
+    if grant_data.resource_type.contains('*') || grant_data.resource_id.is_empty() {
+        return Err(ServiceError::BadRequest(
+            "resource_type must not contain wildcards and resource_id must not be empty".to_string(),
+        ));
+    }
+
+    let effect = grant_data.effect.unwrap_or_else(|| EFFECT_ALLOW.to_string());
+    if effect != EFFECT_ALLOW && effect != EFFECT_DENY {
+        return Err(ServiceError::BadRequest("effect must be 'allow' or 'deny'".to_string()));
+    }
+
+    match (grant_data.user_id, grant_data.role_id) {
+        (Some(target_user_id), None) => {
+            let user_exists: i64 = users::table
+                .filter(users::id.eq(target_user_id))
+                .select(diesel::dsl::count_star())
+                .first(db)?;
+            if user_exists == 0 {
+                return Err(ServiceError::UserNotFound);
+            }
+        }
+        (None, Some(target_role_id)) => {
+            let role_exists: i64 = roles::table
+                .filter(roles::id.eq(target_role_id))
+                .select(diesel::dsl::count_star())
+                .first(db)?;
+            if role_exists == 0 {
+                return Err(ServiceError::RoleNotFound);
+            }
+        }
+        _ => {
+            return Err(ServiceError::BadRequest(
+                "Exactly one of user_id or role_id must be provided".to_string(),
+            ))
+        }
+    }
+
+    // 已存在时更新效果
+    let existing = diesel::update(
+        resource_grants::table
+            .filter(resource_grants::resource_type.eq(&grant_data.resource_type))
+            .filter(resource_grants::resource_id.eq(&grant_data.resource_id))
+            .filter(resource_grants::action.eq(&grant_data.action))
+            .filter(resource_grants::user_id.is_not_distinct_from(grant_data.user_id))
+            .filter(resource_grants::role_id.is_not_distinct_from(grant_data.role_id)),
+    )
+    .set(resource_grants::effect.eq(&effect))
+    .get_result::<ResourceGrant>(db)
+    .optional()?;
+
+    if let Some(existing) = existing {
+        return Ok(existing);
+    }
+
+    let new_grant = (
+        resource_grants::resource_type.eq(grant_data.resource_type),
+        resource_grants::resource_id.eq(grant_data.resource_id),
+        resource_grants::action.eq(grant_data.action),
+        resource_grants::user_id.eq(grant_data.user_id),
+        resource_grants::role_id.eq(grant_data.role_id),
+        resource_grants::effect.eq(effect),
+    );
+
+    let grant = diesel::insert_into(resource_grants::table)
+        .values(new_grant)
+        .get_result::<ResourceGrant>(db)?;
+
+    Ok(grant)
+}
+
+/// 列出某个资源实例上的全部授权
+pub fn list_resource_grants(
+    db: &mut PgConnection,
+    query: &ResourceGrantQuery,
+) -> Result<Vec<ResourceGrant>, ServiceError> {
+    use crate::db::schema::resource_grants;
+
+    let grants = resource_grants::table
+        .filter(resource_grants::resource_type.eq(&query.resource_type))
+        .filter(resource_grants::resource_id.eq(&query.resource_id))
+        .order(resource_grants::created_at.asc())
+        .load::<ResourceGrant>(db)?;
+
+    Ok(grants)
+}
+
+/// 删除资源实例级授权
+pub fn delete_resource_grant(
+    db: &mut PgConnection,
+    grant_id: Uuid,
+) -> Result<(), ServiceError> {
+    use crate::db::schema::resource_grants;
+
+    let deleted = diesel::delete(resource_grants::table.find(grant_id)).execute(db)?;
+
+    if deleted == 0 {
+        return Err(ServiceError::NotFound("Resource grant not found".to_string()));
+    }
+
+    Ok(())
+}
+
+/// 加载角色属性供授权条件引用（`resource.name`、`resource.max_password_age_days`），
+/// 如只允许管理 `resource.name != 'admin'` 的角色；角色不存在时返回空集合
+pub fn load_role_attributes(
+    db: &mut PgConnection,
+    target_role_id: &str,
+) -> Result<Map<String, Value>, ServiceError> {
+    use crate::db::schema::roles;
+
+    let mut attributes = Map::new();
+    let role = match Uuid::parse_str(target_role_id) {
+        Ok(target_role_id) => roles::table.find(target_role_id).first::<Role>(db).optional()?,
+        Err(_) => None,
+    };
+
+    if let Some(role) = role {
+        attributes.insert("name".to_string(), role.name.into());
+        attributes.insert("max_password_age_days".to_string(), role.max_password_age_days.into());
+    }
+
+    Ok(attributes)
+}
+
+/// 设置角色的密码最长使用天数
+pub fn set_role_password_max_age(
+    db: &mut PgConnection,
+    target_role_id: Uuid,
+    max_age_days: Option<i32>,
+) -> Result<Role, ServiceError> {
+    use crate::db::schema::roles;
+
+    if max_age_days.is_some_and(|days| days <= 0) {
+        return Err(ServiceError::BadRequest("max_password_age_days must be positive".to_string()));
+    }
+
+    diesel::update(roles::table.find(target_role_id))
+        .set((
+            roles::max_password_age_days.eq(max_age_days),
+            roles::updated_at.eq(diesel::dsl::now),
+        ))
+        .get_result::<Role>(db)
+        .optional()?
+        .ok_or(ServiceError::RoleNotFound)
+}
+
+/// 用户适用的密码最长使用天数：各角色（含继承的祖先角色）中最严格的，均未设置时使用全局配置
+pub fn get_user_password_max_age_days(
+    db: &mut PgConnection,
+    target_user_id: Uuid,
+) -> Result<Option<i32>, ServiceError> {
+    use crate::db::schema::{roles, user_roles};
+
+    let direct_role_ids = user_roles::table
+        .filter(user_roles::user_id.eq(target_user_id))
+        .select(user_roles::role_id)
+        .load::<Uuid>(db)?;
+    let role_ids = expand_role_ancestors(db, &direct_role_ids)?;
+
+    let role_max_age = roles::table
+        .filter(roles::id.eq_any(role_ids))
+        .select(diesel::dsl::min(roles::max_password_age_days))
+        .first::<Option<i32>>(db)?;
+
+    Ok(role_max_age.or((CONFIG.password_max_age_days > 0).then_some(CONFIG.password_max_age_days)))
 }
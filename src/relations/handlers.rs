@@ -0,0 +1,126 @@
+use actix_web::{web, HttpResponse, Responder};
+
+use crate::db::{run_blocking, DbPool};
+use crate::errors::ServiceError;
+use crate::relations::models::{
+    ExpandQuery, ListObjectsQuery, ListObjectsResponse, ListSubjectsQuery, ListSubjectsResponse,
+    RelationCheckResponse, RelationTupleRequest,
+};
+use crate::relations::services::{
+    check_relation, delete_tuple, expand_relation, list_objects, list_subjects, write_tuple,
+};
+
+pub async fn write_tuple_handler(
+    pool: web::Data<DbPool>,
+    request: web::Json<RelationTupleRequest>,
+) -> impl Responder {
+    let request = request.into_inner();
+
+    // 写入关系元组
+    match run_blocking(&pool, move |conn| write_tuple(conn, request)).await {
+        Ok(tuple) => HttpResponse::Created().json(tuple),
+        Err(e) => {
+            eprintln!("Error writing relation tuple: {:?}", e);
+            match e {
+                ServiceError::BadRequest(msg) => HttpResponse::BadRequest().json(msg),
+                _ => HttpResponse::InternalServerError().json("Failed to write relation tuple")
+            }
+        }
+    }
+}
+
+pub async fn delete_tuple_handler(
+    pool: web::Data<DbPool>,
+    request: web::Json<RelationTupleRequest>,
+) -> impl Responder {
+    let request = request.into_inner();
+
+    // 删除关系元组
+    match run_blocking(&pool, move |conn| delete_tuple(conn, request)).await {
+        Ok(()) => HttpResponse::NoContent().finish(),
+        Err(e) => {
+            eprintln!("Error deleting relation tuple: {:?}", e);
+            match e {
+                ServiceError::BadRequest(msg) => HttpResponse::BadRequest().json(msg),
+                ServiceError::NotFound(msg) => HttpResponse::NotFound().json(msg),
+                _ => HttpResponse::InternalServerError().json("Failed to delete relation tuple")
+            }
+        }
+    }
+}
+
+pub async fn check_relation_handler(
+    pool: web::Data<DbPool>,
+    request: web::Json<RelationTupleRequest>,
+) -> impl Responder {
+    let request = request.into_inner();
+
+    // 检查关系
+    match run_blocking(&pool, move |conn| check_relation(conn, request)).await {
+        Ok(allowed) => HttpResponse::Ok().json(RelationCheckResponse { allowed }),
+        Err(e) => {
+            eprintln!("Error checking relation: {:?}", e);
+            match e {
+                ServiceError::BadRequest(msg) => HttpResponse::BadRequest().json(msg),
+                _ => HttpResponse::InternalServerError().json("Failed to check relation")
+            }
+        }
+    }
+}
+
+pub async fn expand_relation_handler(
+    pool: web::Data<DbPool>,
+    query: web::Query<ExpandQuery>,
+) -> impl Responder {
+    let query = query.into_inner();
+
+    // 展开用户集
+    match run_blocking(&pool, move |conn| expand_relation(conn, &query)).await {
+        Ok(tree) => HttpResponse::Ok().json(tree),
+        Err(e) => {
+            eprintln!("Error expanding relation: {:?}", e);
+            match e {
+                ServiceError::BadRequest(msg) => HttpResponse::BadRequest().json(msg),
+                _ => HttpResponse::InternalServerError().json("Failed to expand relation")
+            }
+        }
+    }
+}
+
+pub async fn list_objects_handler(
+    pool: web::Data<DbPool>,
+    query: web::Query<ListObjectsQuery>,
+) -> impl Responder {
+    let query = query.into_inner();
+
+    // 列出主体拥有关系的对象
+    match run_blocking(&pool, move |conn| list_objects(conn, &query)).await {
+        Ok(objects) => HttpResponse::Ok().json(ListObjectsResponse { objects }),
+        Err(e) => {
+            eprintln!("Error listing objects: {:?}", e);
+            match e {
+                ServiceError::BadRequest(msg) => HttpResponse::BadRequest().json(msg),
+                _ => HttpResponse::InternalServerError().json("Failed to list objects")
+            }
+        }
+    }
+}
+
+pub async fn list_subjects_handler(
+    pool: web::Data<DbPool>,
+    query: web::Query<ListSubjectsQuery>,
+) -> impl Responder {
+    let query = query.into_inner();
+
+    // 列出对象关系中的主体
+    match run_blocking(&pool, move |conn| list_subjects(conn, &query)).await {
+        Ok(subjects) => HttpResponse::Ok().json(ListSubjectsResponse { subjects }),
+        Err(e) => {
+            eprintln!("Error listing subjects: {:?}", e);
+            match e {
+                ServiceError::BadRequest(msg) => HttpResponse::BadRequest().json(msg),
+                _ => HttpResponse::InternalServerError().json("Failed to list subjects")
+            }
+        }
+    }
+}
@@ -0,0 +1,4 @@
+pub mod models;
+pub mod namespace;
+pub mod services;
+pub mod handlers;
@@ -0,0 +1,175 @@
+use chrono::{DateTime, Utc};
+use diesel::prelude::*;
+use serde::{Deserialize, Serialize};
+use std::fmt;
+use uuid::Uuid;
+
+use crate::db::schema::relation_tuples;
+use crate::errors::ServiceError;
+
+// 对象标识中不允许出现的分隔符
+const RESERVED_CHARS: [char; 3] = [':', '#', '@'];
+const MAX_OBJECT_ID_LENGTH: usize = 256;
+
+#[derive(Debug, Serialize, Deserialize, Queryable, Identifiable)]
+#[diesel(table_name = relation_tuples)]
+pub struct RelationTuple {
+    pub id: Uuid,
+    pub namespace: String,
+    pub object_id: String,
+    pub relation: String,
+    pub subject_namespace: String,
+    pub subject_object_id: String,
+    // 为空表示直接主体，否则为用户集 subject_namespace:subject_object_id#subject_relation
+    pub subject_relation: Option<String>,
+    pub created_at: DateTime<Utc>,
+}
+
+impl RelationTuple {
+    pub fn subject(&self) -> Subject {
+        Subject {
+            namespace: self.subject_namespace.clone(),
+            object_id: self.subject_object_id.clone(),
+            relation: self.subject_relation.clone(),
+        }
+    }
+}
+
+impl fmt::Display for RelationTuple {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "{}:{}#{}@{}", self.namespace, self.object_id, self.relation, self.subject())
+    }
+}
+
+/// 对象 `namespace:object_id`
+#[derive(Debug, Clone, PartialEq, Eq, Hash)]
+pub struct Object {
+    pub namespace: String,
+    pub object_id: String,
+}
+
+impl Object {
+    pub fn parse(value: &str) -> Result<Self, ServiceError> {
+        let (namespace, object_id) = value
+            .split_once(':')
+            .ok_or_else(|| ServiceError::BadRequest(format!("Invalid object '{}', expected namespace:id", value)))?;
+
+        let valid_namespace = !namespace.is_empty()
+            && namespace.chars().all(|c| c.is_ascii_lowercase() || c.is_ascii_digit() || c == '_');
+        let valid_object_id = !object_id.is_empty()
+            && object_id.len() <= MAX_OBJECT_ID_LENGTH
+            && !object_id.contains(RESERVED_CHARS)
+            && !object_id.contains(char::is_whitespace);
+
+        if !valid_namespace || !valid_object_id {
+            return Err(ServiceError::BadRequest(format!("Invalid object '{}'", value)));
+        }
+
+        Ok(Object {
+            namespace: namespace.to_string(),
+            object_id: object_id.to_string(),
+        })
+    }
+}
+
+impl fmt::Display for Object {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "{}:{}", self.namespace, self.object_id)
+    }
+}
+
+/// 主体：直接主体 `namespace:object_id`，或用户集 `namespace:object_id#relation`
+#[derive(Debug, Clone, PartialEq, Eq, Hash)]
+pub struct Subject {
+    pub namespace: String,
+    pub object_id: String,
+    pub relation: Option<String>,
+}
+
+impl Subject {
+    pub fn parse(value: &str) -> Result<Self, ServiceError> {
+        let (object, relation) = match value.split_once('#') {
+            Some((object, relation)) => (object, Some(relation)),
+            None => (value, None),
+        };
+        let object = Object::parse(object)?;
+
+        if relation.is_some_and(|relation| relation.is_empty() || relation.contains(RESERVED_CHARS)) {
+            return Err(ServiceError::BadRequest(format!("Invalid subject '{}'", value)));
+        }
+
+        Ok(Subject {
+            namespace: object.namespace,
+            object_id: object.object_id,
+            relation: relation.map(str::to_string),
+        })
+    }
+
+    // 主体本身是否就是该用户集
+    pub fn is_userset(&self, object: &Object, relation: &str) -> bool {
+        self.namespace == object.namespace
+            && self.object_id == object.object_id
+            && self.relation.as_deref() == Some(relation)
+    }
+}
+
+impl fmt::Display for Subject {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match &self.relation {
+            Some(relation) => write!(f, "{}:{}#{}", self.namespace, self.object_id, relation),
+            None => write!(f, "{}:{}", self.namespace, self.object_id),
+        }
+    }
+}
+
+// 写入、删除和检查关系元组，object 形如 doc:readme，subject 形如 user:alice 或 group:eng#member
+#[derive(Debug, Serialize, Deserialize)]
+pub struct RelationTupleRequest {
+    pub object: String,
+    pub relation: String,
+    pub subject: String,
+}
+
+#[derive(Debug, Serialize, Deserialize)]
+pub struct RelationCheckResponse {
+    pub allowed: bool,
+}
+
+#[derive(Debug, Serialize, Deserialize)]
+pub struct ExpandQuery {
+    pub object: String,
+    pub relation: String,
+}
+
+/// 用户集展开树：`subjects` 为直接主体，`children` 为引用到的其他用户集
+#[derive(Debug, Serialize, Deserialize)]
+pub struct UsersetTree {
+    pub userset: String,
+    pub subjects: Vec<String>,
+    pub children: Vec<UsersetTree>,
+}
+
+#[derive(Debug, Serialize, Deserialize)]
+pub struct ListObjectsQuery {
+    pub namespace: String,
+    pub relation: String,
+    pub subject: String,
+}
+
+#[derive(Debug, Serialize, Deserialize)]
+pub struct ListObjectsResponse {
+    pub objects: Vec<String>,
+}
+
+#[derive(Debug, Serialize, Deserialize)]
+pub struct ListSubjectsQuery {
+    pub object: String,
+    pub relation: String,
+    // 只返回该命名空间的主体，如 user
+    pub subject_namespace: Option<String>,
+}
+
+#[derive(Debug, Serialize, Deserialize)]
+pub struct ListSubjectsResponse {
+    pub subjects: Vec<String>,
+}
@@ -0,0 +1,138 @@
+use lazy_static::lazy_static;
+use serde::Deserialize;
+use std::collections::HashMap;
+use std::fs;
+use std::path::Path;
+
+use crate::config::CONFIG;
+use crate::errors::ServiceError;
+
+/// 关系的用户集改写规则
+///
+/// - `this`：直接存储在该关系上的元组；
+/// - `computed_userset`：同一对象上另一个关系的用户集，如 editor 自动拥有 viewer；
+/// - `tuple_to_userset`：沿 `tupleset` 关系找到相关对象，取其 `computed_userset` 关系的用户集，
+///   如文档的 viewer 包含其父文件夹的 viewer；
+/// - `union`：多个规则的并集。
+///
+/// 配置示例：
+///
+/// ```json
+/// {
+///   "doc": {
+///     "relations": {
+///       "owner": "this",
+///       "parent": "this",
+///       "editor": { "union": ["this", { "computed_userset": { "relation": "owner" } }] },
+///       "viewer": { "union": [
+///         "this",
+///         { "computed_userset": { "relation": "editor" } },
+///         { "tuple_to_userset": { "tupleset": "parent", "computed_userset": "viewer" } }
+///       ] }
+///     }
+///   }
+/// }
+/// ```
+#[derive(Debug, Clone, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum Rewrite {
+    This,
+    ComputedUserset { relation: String },
+    TupleToUserset { tupleset: String, computed_userset: String },
+    Union(Vec<Rewrite>),
+}
+
+impl Rewrite {
+    /// 展开 union，返回全部非 union 规则
+    pub fn leaves(&self) -> Vec<&Rewrite> {
+        match self {
+            Rewrite::Union(children) => children.iter().flat_map(Rewrite::leaves).collect(),
+            leaf => vec![leaf],
+        }
+    }
+
+    /// 是否接受直接存储在该关系上的元组
+    pub fn includes_this(&self) -> bool {
+        self.leaves().into_iter().any(|leaf| matches!(leaf, Rewrite::This))
+    }
+}
+
+#[derive(Debug, Deserialize)]
+pub struct NamespaceConfig {
+    pub relations: HashMap<String, Rewrite>,
+}
+
+/// 全部命名空间的配置
+#[derive(Debug, Default, Deserialize)]
+#[serde(transparent)]
+pub struct Namespaces(HashMap<String, NamespaceConfig>);
+
+lazy_static! {
+    pub static ref NAMESPACES: Namespaces = Namespaces::from_config();
+}
+
+impl Namespaces {
+    fn from_config() -> Self {
+        match &CONFIG.relation_namespaces {
+            Some(path) => Namespaces::load(Path::new(path))
+                .unwrap_or_else(|e| panic!("关系命名空间配置加载失败 {}: {}", path, e)),
+            None => Namespaces::default(),
+        }
+    }
+
+    /// 从 JSON 文件加载并校验命名空间配置
+    pub fn load(path: &Path) -> Result<Self, String> {
+        let content = fs::read_to_string(path).map_err(|e| e.to_string())?;
+        let namespaces: Namespaces = serde_json::from_str(&content).map_err(|e| e.to_string())?;
+        namespaces.validate()?;
+        Ok(namespaces)
+    }
+
+    // computed_userset 和 tupleset 必须引用同一命名空间中已定义的关系；
+    // tuple_to_userset 的 computed_userset 属于相关对象的命名空间，求值时不存在则跳过
+    fn validate(&self) -> Result<(), String> {
+        for (namespace, config) in &self.0 {
+            for (relation, rewrite) in &config.relations {
+                for leaf in rewrite.leaves() {
+                    let referenced = match leaf {
+                        Rewrite::ComputedUserset { relation } => relation,
+                        Rewrite::TupleToUserset { tupleset, .. } => tupleset,
+                        _ => continue,
+                    };
+                    if !config.relations.contains_key(referenced) {
+                        return Err(format!(
+                            "{}#{} references undefined relation '{}'",
+                            namespace, relation, referenced
+                        ));
+                    }
+                }
+            }
+        }
+        Ok(())
+    }
+
+    /// 查找关系的改写规则
+    pub fn rewrite(&self, namespace: &str, relation: &str) -> Option<&Rewrite> {
+        self.0.get(namespace)?.relations.get(relation)
+    }
+
+    /// 查找关系的改写规则，未定义时返回 `BadRequest`
+    pub fn require(&self, namespace: &str, relation: &str) -> Result<&Rewrite, ServiceError> {
+        self.rewrite(namespace, relation)
+            .ok_or_else(|| ServiceError::BadRequest(format!("Unknown relation '{}#{}'", namespace, relation)))
+    }
+
+    pub fn contains_namespace(&self, namespace: &str) -> bool {
+        self.0.contains_key(namespace)
+    }
+
+    /// 遍历全部 `(命名空间, 关系, 改写规则)`
+    pub fn relations(&self) -> impl Iterator<Item = (&str, &str, &Rewrite)> {
+        self.0.iter().flat_map(|(namespace, config)| {
+            config
+                .relations
+                .iter()
+                .map(move |(relation, rewrite)| (namespace.as_str(), relation.as_str(), rewrite))
+        })
+    }
+}
@@ -0,0 +1,467 @@
+use diesel::prelude::*;
+use std::collections::{BTreeSet, HashSet, VecDeque};
+
+use crate::errors::ServiceError;
+use crate::relations::models::*;
+use crate::relations::namespace::{Rewrite, NAMESPACES};
+
+// 改写规则的最大递归深度，超过时视为不可达
+const MAX_DEPTH: usize = 16;
+// list_objects 反向遍历的用户集数量上限
+const MAX_REACHED_USERSETS: usize = 10_000;
+
+// 用户集 (命名空间, 对象ID, 关系)
+type UsersetKey = (String, String, String);
+
+// 加载对象上某个关系的全部元组
+fn load_tuples(
+    db: &mut PgConnection,
+    object: &Object,
+    relation: &str,
+) -> Result<Vec<RelationTuple>, ServiceError> {
+    use crate::db::schema::relation_tuples;
+
+    let tuples = relation_tuples::table
+        .filter(relation_tuples::namespace.eq(&object.namespace))
+        .filter(relation_tuples::object_id.eq(&object.object_id))
+        .filter(relation_tuples::relation.eq(relation))
+        .load::<RelationTuple>(db)?;
+
+    Ok(tuples)
+}
+
+// 加载主体完全相同的全部元组
+fn load_tuples_with_subject(
+    db: &mut PgConnection,
+    subject: &Subject,
+) -> Result<Vec<RelationTuple>, ServiceError> {
+    use crate::db::schema::relation_tuples;
+
+    let tuples = relation_tuples::table
+        .filter(relation_tuples::subject_namespace.eq(&subject.namespace))
+        .filter(relation_tuples::subject_object_id.eq(&subject.object_id))
+        .filter(relation_tuples::subject_relation.is_not_distinct_from(&subject.relation))
+        .load::<RelationTuple>(db)?;
+
+    Ok(tuples)
+}
+
+// 解析并校验请求中的元组
+fn parse_tuple(request: &RelationTupleRequest) -> Result<(Object, Subject), ServiceError> {
+    let object = Object::parse(&request.object)?;
+    let subject = Subject::parse(&request.subject)?;
+
+    if !NAMESPACES.require(&object.namespace, &request.relation)?.includes_this() {
+        return Err(ServiceError::BadRequest(format!(
+            "Relation '{}#{}' is computed and does not accept tuples",
+            object.namespace, request.relation
+        )));
+    }
+
+    match &subject.relation {
+        Some(relation) => {
+            NAMESPACES.require(&subject.namespace, relation)?;
+        }
+        None if !NAMESPACES.contains_namespace(&subject.namespace) => {
+            return Err(ServiceError::BadRequest(format!("Unknown namespace '{}'", subject.namespace)));
+        }
+        None => {}
+    }
+
+    Ok((object, subject))
+}
+
+/// 写入关系元组，已存在时返回现有元组
+///
+/// 依赖唯一索引 `ON CONFLICT DO NOTHING` 写入，并发写入同一元组时不会因唯一约束报错。
+pub fn write_tuple(
+    db: &mut PgConnection,
+    request: RelationTupleRequest,
+) -> Result<RelationTuple, ServiceError> {
+    use crate::db::schema::relation_tuples;
+
+    let (object, subject) = parse_tuple(&request)?;
+
+    let new_tuple = (
+        relation_tuples::namespace.eq(&object.namespace),
+        relation_tuples::object_id.eq(&object.object_id),
+        relation_tuples::relation.eq(&request.relation),
+        relation_tuples::subject_namespace.eq(&subject.namespace),
+        relation_tuples::subject_object_id.eq(&subject.object_id),
+        relation_tuples::subject_relation.eq(&subject.relation),
+    );
+
+    let inserted = diesel::insert_into(relation_tuples::table)
+        .values(new_tuple)
+        .on_conflict_do_nothing()
+        .get_result::<RelationTuple>(db)
+        .optional()?;
+
+    if let Some(tuple) = inserted {
+        return Ok(tuple);
+    }
+
+    // 元组已存在，返回现有元组
+    let existing = relation_tuples::table
+        .filter(relation_tuples::namespace.eq(&object.namespace))
+        .filter(relation_tuples::object_id.eq(&object.object_id))
+        .filter(relation_tuples::relation.eq(&request.relation))
+        .filter(relation_tuples::subject_namespace.eq(&subject.namespace))
+        .filter(relation_tuples::subject_object_id.eq(&subject.object_id))
+        .filter(relation_tuples::subject_relation.is_not_distinct_from(&subject.relation))
+        .first::<RelationTuple>(db)?;
+
+    Ok(existing)
+}
+
+/// 删除关系元组
+pub fn delete_tuple(
+    db: &mut PgConnection,
+    request: RelationTupleRequest,
+) -> Result<(), ServiceError> {
+    use crate::db::schema::relation_tuples;
+
+    let object = Object::parse(&request.object)?;
+    let subject = Subject::parse(&request.subject)?;
+
+    let deleted = diesel::delete(
+        relation_tuples::table
+            .filter(relation_tuples::namespace.eq(&object.namespace))
+            .filter(relation_tuples::object_id.eq(&object.object_id))
+            .filter(relation_tuples::relation.eq(&request.relation))
+            .filter(relation_tuples::subject_namespace.eq(&subject.namespace))
+            .filter(relation_tuples::subject_object_id.eq(&subject.object_id))
+            .filter(relation_tuples::subject_relation.is_not_distinct_from(&subject.relation)),
+    )
+    .execute(db)?;
+
+    if deleted == 0 {
+        return Err(ServiceError::NotFound("Relation tuple not found".to_string()));
+    }
+
+    Ok(())
+}
+
+/// 检查主体是否属于对象某个关系的用户集（含改写规则推导出的成员）
+pub fn check_relation(
+    db: &mut PgConnection,
+    request: RelationTupleRequest,
+) -> Result<bool, ServiceError> {
+    let object = Object::parse(&request.object)?;
+    let subject = Subject::parse(&request.subject)?;
+    NAMESPACES.require(&object.namespace, &request.relation)?;
+
+    check_userset(db, &object, &request.relation, &subject, 0, &mut HashSet::new())
+}
+
+// 正向求值：按改写规则逐层展开，命中主体即返回；
+// visiting 记录当前路径上的用户集，遇到环时该分支视为不可达
+fn check_userset(
+    db: &mut PgConnection,
+    object: &Object,
+    relation: &str,
+    subject: &Subject,
+    depth: usize,
+    visiting: &mut HashSet<UsersetKey>,
+) -> Result<bool, ServiceError> {
+    if subject.is_userset(object, relation) {
+        return Ok(true);
+    }
+
+    let rewrite = match NAMESPACES.rewrite(&object.namespace, relation) {
+        Some(rewrite) => rewrite,
+        None => return Ok(false),
+    };
+
+    if depth > MAX_DEPTH {
+        log::warn!("关系检查超过最大深度: {}#{}", object, relation);
+        return Ok(false);
+    }
+
+    let key = (object.namespace.clone(), object.object_id.clone(), relation.to_string());
+    if !visiting.insert(key.clone()) {
+        return Ok(false);
+    }
+
+    let mut found = false;
+    for leaf in rewrite.leaves() {
+        found = match leaf {
+            Rewrite::This => {
+                let mut found = false;
+                for tuple in load_tuples(db, object, relation)? {
+                    let tuple_subject = tuple.subject();
+                    if tuple_subject == *subject {
+                        found = true;
+                    } else if let Some(subject_relation) = &tuple.subject_relation {
+                        let subject_object = Object {
+                            namespace: tuple.subject_namespace,
+                            object_id: tuple.subject_object_id,
+                        };
+                        found = check_userset(db, &subject_object, subject_relation, subject, depth + 1, visiting)?;
+                    }
+                    if found {
+                        break;
+                    }
+                }
+                found
+            }
+            Rewrite::ComputedUserset { relation } => {
+                check_userset(db, object, relation, subject, depth + 1, visiting)?
+            }
+            Rewrite::TupleToUserset { tupleset, computed_userset } => {
+                let mut found = false;
+                for tuple in load_tuples(db, object, tupleset)? {
+                    let related = Object {
+                        namespace: tuple.subject_namespace,
+                        object_id: tuple.subject_object_id,
+                    };
+                    if check_userset(db, &related, computed_userset, subject, depth + 1, visiting)? {
+                        found = true;
+                        break;
+                    }
+                }
+                found
+            }
+            Rewrite::Union(_) => unreachable!("leaves() 已展开 union"),
+        };
+        if found {
+            break;
+        }
+    }
+
+    visiting.remove(&key);
+    Ok(found)
+}
+
+/// 展开对象某个关系的用户集，返回完整的展开树
+pub fn expand_relation(
+    db: &mut PgConnection,
+    query: &ExpandQuery,
+) -> Result<UsersetTree, ServiceError> {
+    let object = Object::parse(&query.object)?;
+    NAMESPACES.require(&object.namespace, &query.relation)?;
+
+    expand_userset(db, &object, &query.relation, 0, &mut HashSet::new())
+}
+
+fn expand_userset(
+    db: &mut PgConnection,
+    object: &Object,
+    relation: &str,
+    depth: usize,
+    visiting: &mut HashSet<UsersetKey>,
+) -> Result<UsersetTree, ServiceError> {
+    let mut tree = UsersetTree {
+        userset: format!("{}#{}", object, relation),
+        subjects: Vec::new(),
+        children: Vec::new(),
+    };
+
+    let rewrite = match NAMESPACES.rewrite(&object.namespace, relation) {
+        Some(rewrite) => rewrite,
+        None => return Ok(tree),
+    };
+
+    // 超过深度或遇到环时不再展开，该节点只保留用户集名称
+    let key = (object.namespace.clone(), object.object_id.clone(), relation.to_string());
+    if depth > MAX_DEPTH || !visiting.insert(key.clone()) {
+        return Ok(tree);
+    }
+
+    for leaf in rewrite.leaves() {
+        match leaf {
+            Rewrite::This => {
+                for tuple in load_tuples(db, object, relation)? {
+                    match &tuple.subject_relation {
+                        Some(subject_relation) => {
+                            let subject_object = Object {
+                                namespace: tuple.subject_namespace.clone(),
+                                object_id: tuple.subject_object_id.clone(),
+                            };
+                            tree.children
+                                .push(expand_userset(db, &subject_object, subject_relation, depth + 1, visiting)?);
+                        }
+                        None => tree.subjects.push(tuple.subject().to_string()),
+                    }
+                }
+            }
+            Rewrite::ComputedUserset { relation } => {
+                tree.children.push(expand_userset(db, object, relation, depth + 1, visiting)?);
+            }
+            Rewrite::TupleToUserset { tupleset, computed_userset } => {
+                for tuple in load_tuples(db, object, tupleset)? {
+                    let related = Object {
+                        namespace: tuple.subject_namespace,
+                        object_id: tuple.subject_object_id,
+                    };
+                    if NAMESPACES.rewrite(&related.namespace, computed_userset).is_some() {
+                        tree.children
+                            .push(expand_userset(db, &related, computed_userset, depth + 1, visiting)?);
+                    }
+                }
+            }
+            Rewrite::Union(_) => unreachable!("leaves() 已展开 union"),
+        }
+    }
+
+    visiting.remove(&key);
+    tree.subjects.sort();
+    tree.subjects.dedup();
+    Ok(tree)
+}
+
+/// 列出对象某个关系用户集中的全部直接主体，可按主体命名空间筛选
+pub fn list_subjects(
+    db: &mut PgConnection,
+    query: &ListSubjectsQuery,
+) -> Result<Vec<String>, ServiceError> {
+    let tree = expand_relation(
+        db,
+        &ExpandQuery {
+            object: query.object.clone(),
+            relation: query.relation.clone(),
+        },
+    )?;
+
+    let mut subjects = BTreeSet::new();
+    let mut pending = vec![&tree];
+    while let Some(node) = pending.pop() {
+        subjects.extend(node.subjects.iter().cloned());
+        pending.extend(node.children.iter());
+    }
+
+    let prefix = query.subject_namespace.as_ref().map(|namespace| format!("{}:", namespace));
+    Ok(subjects
+        .into_iter()
+        .filter(|subject| prefix.as_ref().is_none_or(|prefix| subject.starts_with(prefix)))
+        .collect())
+}
+
+// 记录新到达的用户集及其层数；超过遍历上限时返回错误，而不是返回不完整的结果
+fn reach(
+    reached: &mut HashSet<UsersetKey>,
+    queue: &mut VecDeque<(UsersetKey, usize)>,
+    key: UsersetKey,
+    level: usize,
+) -> Result<(), ServiceError> {
+    if reached.insert(key.clone()) {
+        if reached.len() > MAX_REACHED_USERSETS {
+            return Err(ServiceError::BadRequest(format!(
+                "Listing objects exceeds the traversal limit of {} usersets",
+                MAX_REACHED_USERSETS
+            )));
+        }
+        queue.push_back((key, level));
+    }
+    Ok(())
+}
+
+/// 列出主体在某个命名空间中拥有指定关系的全部对象
+///
+/// 从主体出发反向遍历：引用该主体（或已到达用户集）的元组、通过 `computed_userset`
+/// 引用已到达关系的同对象关系、以及通过 `tuple_to_userset` 指向已到达对象的关系，
+/// 与 `check` 的正向求值结果一致。
+///
+/// 层数对应 `check` 中的递归深度：主体自身为第 0 层，直接引用主体的元组为第 1 层，
+/// 超过 `MAX_DEPTH` 的用户集不再向上展开；到达的用户集超过 `MAX_REACHED_USERSETS`
+/// 时返回 `BadRequest`。
+pub fn list_objects(
+    db: &mut PgConnection,
+    query: &ListObjectsQuery,
+) -> Result<Vec<String>, ServiceError> {
+    use crate::db::schema::relation_tuples;
+
+    NAMESPACES.require(&query.namespace, &query.relation)?;
+    let subject = Subject::parse(&query.subject)?;
+
+    let accepts_tuple = |tuple: &RelationTuple| {
+        NAMESPACES
+            .rewrite(&tuple.namespace, &tuple.relation)
+            .is_some_and(Rewrite::includes_this)
+    };
+
+    let mut reached: HashSet<UsersetKey> = HashSet::new();
+    let mut queue: VecDeque<(UsersetKey, usize)> = VecDeque::new();
+
+    // 主体本身是用户集时，它包含于自身；否则从直接引用该主体的元组开始
+    match &subject.relation {
+        Some(relation) => reach(
+            &mut reached,
+            &mut queue,
+            (subject.namespace.clone(), subject.object_id.clone(), relation.clone()),
+            0,
+        )?,
+        None => {
+            for tuple in load_tuples_with_subject(db, &subject)? {
+                if accepts_tuple(&tuple) {
+                    reach(&mut reached, &mut queue, (tuple.namespace, tuple.object_id, tuple.relation), 1)?;
+                }
+            }
+        }
+    }
+
+    while let Some(((namespace, object_id, relation), level)) = queue.pop_front() {
+        // 与 check_userset 一致，超过最大深度的用户集视为不可达，不再向上展开
+        if level > MAX_DEPTH {
+            log::warn!("list_objects 超过最大深度: {}#{}", subject, relation);
+            continue;
+        }
+
+        // 以该用户集为主体的元组
+        let userset = Subject {
+            namespace: namespace.clone(),
+            object_id: object_id.clone(),
+            relation: Some(relation.clone()),
+        };
+        for tuple in load_tuples_with_subject(db, &userset)? {
+            if accepts_tuple(&tuple) {
+                reach(&mut reached, &mut queue, (tuple.namespace, tuple.object_id, tuple.relation), level + 1)?;
+            }
+        }
+
+        for (rewrite_namespace, rewrite_relation, rewrite) in NAMESPACES.relations() {
+            for leaf in rewrite.leaves() {
+                match leaf {
+                    // 同一对象上以该关系为 computed_userset 的关系
+                    Rewrite::ComputedUserset { relation: computed }
+                        if rewrite_namespace == namespace && *computed == relation =>
+                    {
+                        reach(
+                            &mut reached,
+                            &mut queue,
+                            (namespace.clone(), object_id.clone(), rewrite_relation.to_string()),
+                            level + 1,
+                        )?;
+                    }
+                    // 通过 tupleset 指向该对象、并取其该关系的对象
+                    Rewrite::TupleToUserset { tupleset, computed_userset } if *computed_userset == relation => {
+                        let parents = relation_tuples::table
+                            .filter(relation_tuples::namespace.eq(rewrite_namespace))
+                            .filter(relation_tuples::relation.eq(tupleset))
+                            .filter(relation_tuples::subject_namespace.eq(&namespace))
+                            .filter(relation_tuples::subject_object_id.eq(&object_id))
+                            .select(relation_tuples::object_id)
+                            .load::<String>(db)?;
+                        for parent in parents {
+                            reach(
+                                &mut reached,
+                                &mut queue,
+                                (rewrite_namespace.to_string(), parent, rewrite_relation.to_string()),
+                                level + 1,
+                            )?;
+                        }
+                    }
+                    _ => {}
+                }
+            }
+        }
+    }
+
+    let objects: BTreeSet<String> = reached
+        .into_iter()
+        .filter(|(namespace, _, relation)| *namespace == query.namespace && *relation == query.relation)
+        .map(|(namespace, object_id, _)| format!("{}:{}", namespace, object_id))
+        .collect();
+
+    Ok(objects.into_iter().collect())
+}
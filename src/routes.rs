@@ -1,21 +1,128 @@
 use actix_web::{web, Scope};
 
-use crate::auth::handlers::{register_handler, login_handler, get_user_handler};
-use crate::permissions::handlers::{create_role_handler, create_permission_handler, assign_role_handler, assign_permission_handler};
-use crate::utils::middleware::AuthMiddleware;
+use crate::api_keys::handlers::{create_api_key_handler, create_client_api_key_handler, list_api_keys_handler, revoke_api_key_handler};
+use crate::auth::handlers::{
+    admin_set_password_handler, change_password_handler, force_role_password_change_handler,
+    force_user_password_change_handler, get_user_handler, login_handler, logout_handler, refresh_session_handler,
+    register_handler,
+};
+use crate::oauth::handlers::{
+    authorize_handler, consent_handler, create_client_handler, device_approval_handler, device_authorization_handler,
+    device_lookup_handler, introspect_handler, list_authorized_apps_handler, revoke_all_tokens_handler,
+    revoke_authorized_app_handler, revoke_handler, revoke_user_tokens_handler, token_handler,
+};
+use crate::permissions::handlers::{
+    add_role_parent_handler, assign_client_role_handler, assign_permission_handler, assign_role_handler,
+    check_permission_handler, create_permission_handler, create_resource_grant_handler, create_role_handler,
+    delete_resource_grant_handler, effective_permissions_handler, list_resource_grants_handler,
+    remove_role_parent_handler, set_role_password_max_age_handler,
+};
+use crate::permissions::services::load_role_attributes;
+use crate::relations::handlers::{
+    check_relation_handler, delete_tuple_handler, expand_relation_handler, list_objects_handler,
+    list_subjects_handler, write_tuple_handler,
+};
+use crate::utils::middleware::{AuthMiddleware, PermissionCheckMiddleware};
 
 pub fn configure_routes(cfg: &mut web::ServiceConfig) {
     // 认证路由
     cfg.service(auth_routes());
 
+    // OAuth路由
+    cfg.service(oauth_routes());
+
+    // API密钥路由
+    cfg.service(
+        web::scope("/api-keys")
+            .wrap(AuthMiddleware::new())
+            .route("", web::post().to(create_api_key_handler))
+            .route("", web::get().to(list_api_keys_handler))
+            .route("/{key_id}", web::delete().to(revoke_api_key_handler))
+    );
+
     // 权限管理路由
     cfg.service(
         web::scope("/permissions")
             .wrap(AuthMiddleware::new())
-            .route("/roles", web::post().to(create_role_handler))
+            .service(
+                // 创建角色需要 roles:manage 权限
+                web::resource("/roles")
+                    .wrap(PermissionCheckMiddleware::new("roles", "manage"))
+                    .route(web::post().to(create_role_handler))
+            )
             .route("/permissions", web::post().to(create_permission_handler))
-            .route("/users/{user_id}/roles/{role_id}", web::post().to(assign_role_handler))
-            .route("/roles/{role_id}/permissions/{permission_id}", web::post().to(assign_permission_handler))
+            .service(
+                // 为用户分配角色需要 roles:assign 权限，否则任何用户都可为自己分配高权限角色
+                web::resource("/users/{user_id}/roles/{role_id}")
+                    .wrap(PermissionCheckMiddleware::new("roles", "assign"))
+                    .route(web::post().to(assign_role_handler))
+            )
+            .service(
+                // 为服务客户端分配角色需要 clients:assign_role 权限，不接受实例级授权以免客户端管理者自行提权
+                web::resource("/clients/{client_id}/roles/{role_id}")
+                    .wrap(PermissionCheckMiddleware::new("clients", "assign_role"))
+                    .route(web::post().to(assign_client_role_handler))
+            )
+            .service(
+                // 为角色分配权限（含 deny 效果和授权条件）需要 roles:manage 权限
+                web::resource("/roles/{role_id}/permissions/{permission_id}")
+                    .wrap(PermissionCheckMiddleware::new("roles", "manage"))
+                    .route(web::post().to(assign_permission_handler))
+            )
+            .service(
+                // 修改角色继承关系需要 roles:manage 权限，否则可把高权限角色挂为自身角色的父角色
+                web::resource("/roles/{role_id}/parents/{parent_role_id}")
+                    .wrap(PermissionCheckMiddleware::new("roles", "manage"))
+                    .route(web::post().to(add_role_parent_handler))
+                    .route(web::delete().to(remove_role_parent_handler))
+            )
+            .service(
+                // 查看角色的有效权限需要 roles:read 权限
+                web::resource("/roles/{role_id}/effective-permissions")
+                    .wrap(PermissionCheckMiddleware::new("roles", "read"))
+                    .route(web::get().to(effective_permissions_handler))
+            )
+            .service(
+                // 管理资源实例级授权需要 grants:manage 权限，否则任何用户都可为自己授予任意对象的访问权
+                web::scope("/grants")
+                    .wrap(PermissionCheckMiddleware::new("grants", "manage"))
+                    .route("", web::post().to(create_resource_grant_handler))
+                    .route("", web::get().to(list_resource_grants_handler))
+                    .route("/{grant_id}", web::delete().to(delete_resource_grant_handler))
+            )
+            .route("/check", web::post().to(check_permission_handler))
+    );
+
+    // 关系授权路由，写入需要 relations:write 权限，查询需要 relations:read 权限
+    cfg.service(
+        web::scope("/relations")
+            .wrap(AuthMiddleware::new())
+            .service(
+                web::resource("/tuples")
+                    .wrap(PermissionCheckMiddleware::new("relations", "write"))
+                    .route(web::post().to(write_tuple_handler))
+                    .route(web::delete().to(delete_tuple_handler))
+            )
+            .service(
+                web::resource("/check")
+                    .wrap(PermissionCheckMiddleware::new("relations", "read"))
+                    .route(web::post().to(check_relation_handler))
+            )
+            .service(
+                web::resource("/expand")
+                    .wrap(PermissionCheckMiddleware::new("relations", "read"))
+                    .route(web::get().to(expand_relation_handler))
+            )
+            .service(
+                web::resource("/objects")
+                    .wrap(PermissionCheckMiddleware::new("relations", "read"))
+                    .route(web::get().to(list_objects_handler))
+            )
+            .service(
+                web::resource("/subjects")
+                    .wrap(PermissionCheckMiddleware::new("relations", "read"))
+                    .route(web::get().to(list_subjects_handler))
+            )
     );
 
     // 示例：使用权限中间件保护的路由
@@ -23,6 +130,41 @@ pub fn configure_routes(cfg: &mut web::ServiceConfig) {
         web::scope("/admin")
             .wrap(AuthMiddleware::new())
             .route("/dashboard", web::get().to(|| async { "管理员仪表盘" }))
+            .service(
+                // 紧急吊销令牌，需要 tokens:revoke 权限
+                web::scope("/tokens")
+                    .wrap(PermissionCheckMiddleware::new("tokens", "revoke"))
+                    .route("/revoke-all", web::post().to(revoke_all_tokens_handler))
+                    .route("/users/{user_id}/revoke", web::post().to(revoke_user_tokens_handler))
+            )
+            .service(
+                // 管理员设置用户密码，需要 users:set_password 权限
+                web::scope("/users")
+                    .wrap(PermissionCheckMiddleware::new("users", "set_password"))
+                    .route("/{user_id}/password", web::put().to(admin_set_password_handler))
+                    .route("/{user_id}/force-password-change", web::post().to(force_user_password_change_handler))
+            )
+            .service(
+                // 角色级密码策略，需要 roles:manage_password_policy 权限或对该角色的实例级授权，
+                // 授权条件可引用角色名等属性
+                web::scope("/roles")
+                    .service(
+                        web::resource("/{role_id}/password-max-age")
+                            .wrap(
+                                PermissionCheckMiddleware::for_resource_param("roles", "manage_password_policy", "role_id")
+                                    .with_resource_attributes(load_role_attributes),
+                            )
+                            .route(web::put().to(set_role_password_max_age_handler))
+                    )
+                    .service(
+                        web::resource("/{role_id}/force-password-change")
+                            .wrap(
+                                PermissionCheckMiddleware::for_resource_param("roles", "manage_password_policy", "role_id")
+                                    .with_resource_attributes(load_role_attributes),
+                            )
+                            .route(web::post().to(force_role_password_change_handler))
+                    )
+            )
     );
 }
 
@@ -30,9 +172,58 @@ pub fn auth_routes() -> Scope {
     web::scope("/auth")
         .route("/register", web::post().to(register_handler))
         .route("/login", web::post().to(login_handler))
+        .route("/session/refresh", web::post().to(refresh_session_handler))
+        .route("/logout", web::post().to(logout_handler))
+        .service(
+            web::scope("/password")
+                // 同时接受登录时签发的修改密码受限令牌
+                .wrap(AuthMiddleware::allowing_password_change())
+                .route("", web::post().to(change_password_handler))
+        )
         .service(
             web::scope("/users")
                 .wrap(AuthMiddleware::new())
                 .route("/{user_id}", web::get().to(get_user_handler))
         )
 }
+
+pub fn oauth_routes() -> Scope {
+    web::scope("/oauth")
+        .route("/token", web::post().to(token_handler))
+        .route("/introspect", web::post().to(introspect_handler))
+        .route("/revoke", web::post().to(revoke_handler))
+        .route("/device/code", web::post().to(device_authorization_handler))
+        .service(
+            web::scope("/device")
+                .wrap(AuthMiddleware::new())
+                .route("", web::get().to(device_lookup_handler))
+                .route("", web::post().to(device_approval_handler))
+        )
+        .service(
+            web::scope("/authorize")
+                .wrap(AuthMiddleware::new())
+                .route("", web::get().to(authorize_handler))
+                .route("", web::post().to(consent_handler))
+        )
+        .service(
+            web::scope("/grants")
+                .wrap(AuthMiddleware::new())
+                .route("", web::get().to(list_authorized_apps_handler))
+                .route("/{client_id}", web::delete().to(revoke_authorized_app_handler))
+        )
+        .service(
+            // 注册客户端需要 clients:create 权限，签发客户端API密钥需要 clients:manage_keys 权限或对该客户端的实例级授权
+            web::scope("/clients")
+                .wrap(AuthMiddleware::new())
+                .service(
+                    web::resource("")
+                        .wrap(PermissionCheckMiddleware::new("clients", "create"))
+                        .route(web::post().to(create_client_handler))
+                )
+                .service(
+                    web::resource("/{client_id}/api-keys")
+                        .wrap(PermissionCheckMiddleware::for_resource_param("clients", "manage_keys", "client_id"))
+                        .route(web::post().to(create_client_api_key_handler))
+                )
+        )
+}
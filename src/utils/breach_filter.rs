@@ -0,0 +1,285 @@
+use sha1::{Digest, Sha1};
+use std::fs::File;
+use std::io::{self, BufRead, BufReader, BufWriter, Read, Write};
+use std::path::Path;
+
+const MAGIC: &[u8; 4] = b"SABF";
+const VERSION: u8 = 1;
+
+/// 泄露密码的布隆过滤器，由 HIBP 格式（`SHA1:出现次数`）的语料构建
+///
+/// 只存储 SHA-1 摘要派生的位，无法还原密码；判断存在时有可配置的误报率，
+/// 不存在时一定准确。
+pub struct BreachFilter {
+    bits: Vec<u64>,
+    num_bits: u64,
+    num_hashes: u8,
+}
+
+// SHA-1 摘要本身均匀分布，直接取前16字节做双重哈希
+fn bit_indexes(digest: &[u8; 20], num_bits: u64, num_hashes: u8) -> impl Iterator<Item = u64> {
+    let mut h1 = [0u8; 8];
+    let mut h2 = [0u8; 8];
+    h1.copy_from_slice(&digest[..8]);
+    h2.copy_from_slice(&digest[8..16]);
+    let h1 = u64::from_le_bytes(h1);
+    let h2 = u64::from_le_bytes(h2) | 1;
+
+    (0..num_hashes as u64).map(move |i| h1.wrapping_add(i.wrapping_mul(h2)) % num_bits)
+}
+
+// 解析一行 HIBP 语料，返回摘要和出现次数
+fn parse_line(line: &str) -> Option<([u8; 20], u64)> {
+    let (hash, count) = match line.trim().split_once(':') {
+        Some((hash, count)) => (hash, count.trim().parse().ok()?),
+        None => (line.trim(), 1),
+    };
+
+    let mut digest = [0u8; 20];
+    hex::decode_to_slice(hash, &mut digest).ok()?;
+    Some((digest, count))
+}
+
+impl BreachFilter {
+    fn with_capacity(entries: u64, false_positive_rate: f64) -> Self {
+        let entries = entries.max(1) as f64;
+        let ln2 = std::f64::consts::LN_2;
+        let num_bits = (-(entries * false_positive_rate.ln()) / (ln2 * ln2)).ceil().max(64.0) as u64;
+        let num_hashes = ((num_bits as f64 / entries) * ln2).round().clamp(1.0, 32.0) as u8;
+
+        BreachFilter {
+            bits: vec![0; num_bits.div_ceil(64) as usize],
+            num_bits,
+            num_hashes,
+        }
+    }
+
+    fn insert(&mut self, digest: &[u8; 20]) {
+        for index in bit_indexes(digest, self.num_bits, self.num_hashes) {
+            self.bits[(index / 64) as usize] |= 1 << (index % 64);
+        }
+    }
+
+    fn contains_digest(&self, digest: &[u8; 20]) -> bool {
+        bit_indexes(digest, self.num_bits, self.num_hashes)
+            .all(|index| self.bits[(index / 64) as usize] & (1 << (index % 64)) != 0)
+    }
+
+    /// 密码是否（可能）出现在泄露语料中
+    pub fn contains_password(&self, password: &str) -> bool {
+        let digest: [u8; 20] = Sha1::digest(password.as_bytes()).into();
+        self.contains_digest(&digest)
+    }
+
+    /// 从 HIBP 格式的语料文件构建，只收录出现次数不少于 `min_count` 的条目
+    ///
+    /// 文件会被读取两遍：先统计条目数以确定过滤器大小，再逐条插入。
+    pub fn build(input: &Path, false_positive_rate: f64, min_count: u64) -> io::Result<Self> {
+        let entries = BufReader::new(File::open(input)?)
+            .lines()
+            .filter_map(|line| line.ok().and_then(|line| parse_line(&line)))
+            .filter(|(_, count)| *count >= min_count)
+            .count() as u64;
+
+        let mut filter = BreachFilter::with_capacity(entries, false_positive_rate);
+        for line in BufReader::new(File::open(input)?).lines() {
+            if let Some((digest, count)) = parse_line(&line?) {
+                if count >= min_count {
+                    filter.insert(&digest);
+                }
+            }
+        }
+
+        Ok(filter)
+    }
+
+    /// 写入过滤器文件：魔数、版本、哈希函数个数、位数，随后是位数组
+    pub fn save(&self, output: &Path) -> io::Result<()> {
+        let mut writer = BufWriter::new(File::create(output)?);
+        writer.write_all(MAGIC)?;
+        writer.write_all(&[VERSION, self.num_hashes])?;
+        writer.write_all(&self.num_bits.to_le_bytes())?;
+        for word in &self.bits {
+            writer.write_all(&word.to_le_bytes())?;
+        }
+        writer.flush()
+    }
+
+    pub fn load(path: &Path) -> io::Result<Self> {
+        let invalid = |msg: &str| io::Error::new(io::ErrorKind::InvalidData, msg.to_string());
+        let mut reader = BufReader::new(File::open(path)?);
+
+        let mut header = [0u8; 14];
+        reader.read_exact(&mut header)?;
+        if &header[..4] != MAGIC || header[4] != VERSION {
+            return Err(invalid("不是有效的泄露密码过滤器文件"));
+        }
+
+        let num_hashes = header[5];
+        let mut num_bits = [0u8; 8];
+        num_bits.copy_from_slice(&header[6..14]);
+        let num_bits = u64::from_le_bytes(num_bits);
+        if num_hashes == 0 || num_bits == 0 {
+            return Err(invalid("过滤器参数无效"));
+        }
+
+        let mut bytes = Vec::new();
+        reader.read_to_end(&mut bytes)?;
+        if bytes.len() as u64 != num_bits.div_ceil(64) * 8 {
+            return Err(invalid("过滤器文件长度不匹配"));
+        }
+
+        let bits = bytes
+            .chunks_exact(8)
+            .map(|chunk| {
+                let mut word = [0u8; 8];
+                word.copy_from_slice(chunk);
+                u64::from_le_bytes(word)
+            })
+            .collect();
+
+        Ok(BreachFilter { bits, num_bits, num_hashes })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::path::PathBuf;
+
+    // 测试结束时删除的临时文件
+    struct TempFile(PathBuf);
+
+    impl TempFile {
+        fn new(contents: &[u8]) -> Self {
+            let path = std::env::temp_dir().join(format!("breach-filter-{}", uuid::Uuid::new_v4()));
+            std::fs::write(&path, contents).unwrap();
+            TempFile(path)
+        }
+    }
+
+    impl Drop for TempFile {
+        fn drop(&mut self) {
+            let _ = std::fs::remove_file(&self.0);
+        }
+    }
+
+    fn sha1_hex(password: &str) -> String {
+        hex::encode_upper(Sha1::digest(password.as_bytes()))
+    }
+
+    fn filter_with(passwords: &[&str]) -> BreachFilter {
+        let mut filter = BreachFilter::with_capacity(passwords.len() as u64, 0.001);
+        for password in passwords {
+            filter.insert(&Sha1::digest(password.as_bytes()).into());
+        }
+        filter
+    }
+
+    #[test]
+    fn parse_line_reads_hibp_format() {
+        let (digest, count) = parse_line("5BAA61E4C9B93F3F0682250B6CF8331B7EE68FD8:3861493").unwrap();
+        assert_eq!(hex::encode_upper(digest), sha1_hex("password"));
+        assert_eq!(count, 3861493);
+
+        let (digest, count) = parse_line("  5baa61e4c9b93f3f0682250b6cf8331b7ee68fd8 \r").unwrap();
+        assert_eq!(hex::encode_upper(digest), sha1_hex("password"));
+        assert_eq!(count, 1);
+    }
+
+    #[test]
+    fn parse_line_rejects_malformed_entries() {
+        assert!(parse_line("").is_none());
+        assert!(parse_line("not-a-hash:1").is_none());
+        assert!(parse_line("5BAA61E4C9B93F3F0682250B6CF8331B7EE68F:1").is_none());
+        assert!(parse_line("5BAA61E4C9B93F3F0682250B6CF8331B7EE68FD8:many").is_none());
+    }
+
+    #[test]
+    fn inserted_passwords_are_always_found() {
+        let passwords: Vec<String> = (0..1000).map(|i| format!("password{}", i)).collect();
+        let refs: Vec<&str> = passwords.iter().map(String::as_str).collect();
+        let filter = filter_with(&refs);
+
+        assert!(passwords.iter().all(|password| filter.contains_password(password)));
+    }
+
+    #[test]
+    fn false_positive_rate_stays_near_target() {
+        let passwords: Vec<String> = (0..1000).map(|i| format!("password{}", i)).collect();
+        let refs: Vec<&str> = passwords.iter().map(String::as_str).collect();
+        let filter = filter_with(&refs);
+
+        let false_positives = (0..10_000)
+            .filter(|i| filter.contains_password(&format!("unrelated{}", i)))
+            .count();
+        assert!(false_positives < 50, "误报 {} 次", false_positives);
+    }
+
+    #[test]
+    fn empty_filter_contains_nothing() {
+        let filter = BreachFilter::with_capacity(0, 0.001);
+        assert!(!filter.contains_password("password"));
+        assert!(!filter.contains_password(""));
+    }
+
+    #[test]
+    fn build_honours_min_count_and_skips_bad_lines() {
+        let corpus = format!(
+            "{}:10\n{}:1\ngarbage\n{}\n",
+            sha1_hex("common"),
+            sha1_hex("rare"),
+            sha1_hex("uncounted"),
+        );
+        let input = TempFile::new(corpus.as_bytes());
+
+        let filter = BreachFilter::build(&input.0, 0.001, 1).unwrap();
+        assert!(filter.contains_password("common"));
+        assert!(filter.contains_password("rare"));
+        assert!(filter.contains_password("uncounted"));
+
+        let filter = BreachFilter::build(&input.0, 0.001, 2).unwrap();
+        assert!(filter.contains_password("common"));
+        assert!(!filter.contains_password("rare"));
+        assert!(!filter.contains_password("uncounted"));
+    }
+
+    #[test]
+    fn save_and_load_round_trip() {
+        let filter = filter_with(&["hunter2", "correct horse battery staple"]);
+        let output = TempFile::new(b"");
+        filter.save(&output.0).unwrap();
+
+        let loaded = BreachFilter::load(&output.0).unwrap();
+        assert_eq!(loaded.num_bits, filter.num_bits);
+        assert_eq!(loaded.num_hashes, filter.num_hashes);
+        assert_eq!(loaded.bits, filter.bits);
+        assert!(loaded.contains_password("hunter2"));
+        assert!(!loaded.contains_password("hunter3"));
+    }
+
+    #[test]
+    fn load_rejects_invalid_files() {
+        let filter = filter_with(&["hunter2"]);
+        let output = TempFile::new(b"");
+        filter.save(&output.0).unwrap();
+        let bytes = std::fs::read(&output.0).unwrap();
+
+        let mut bad_magic = bytes.clone();
+        bad_magic[0] = b'X';
+        assert!(BreachFilter::load(&TempFile::new(&bad_magic).0).is_err());
+
+        let mut bad_version = bytes.clone();
+        bad_version[4] = VERSION + 1;
+        assert!(BreachFilter::load(&TempFile::new(&bad_version).0).is_err());
+
+        let mut no_hashes = bytes.clone();
+        no_hashes[5] = 0;
+        assert!(BreachFilter::load(&TempFile::new(&no_hashes).0).is_err());
+
+        let truncated = &bytes[..bytes.len() - 1];
+        assert!(BreachFilter::load(&TempFile::new(truncated).0).is_err());
+
+        assert!(BreachFilter::load(&TempFile::new(&bytes[..10]).0).is_err());
+    }
+}
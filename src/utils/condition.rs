@@ -0,0 +1,558 @@
+use serde_json::{Map, Value};
+use std::net::IpAddr;
+
+use crate::errors::ServiceError;
+
+// 条件表达式的长度和嵌套深度上限，防止恶意表达式耗尽资源
+const MAX_EXPRESSION_LENGTH: usize = 1024;
+const MAX_DEPTH: usize = 32;
+
+// 表达式中可以引用的根对象
+const ROOTS: &[&str] = &["user", "resource", "ctx"];
+
+/// 条件表达式的求值上下文
+///
+/// - `user`：当前主体的属性，如 `id`、`email`、`kind`、`roles`；
+/// - `resource`：被访问资源的属性，如 `type`、`id` 以及从数据库加载的属性；
+/// - `ctx`：请求本身的属性，如 `ip`、`time`、`hour`、`weekday`、`mfa_level` 以及路径参数 `params`。
+#[derive(Debug, Default, Clone)]
+pub struct AccessContext {
+    pub user: Map<String, Value>,
+    pub resource: Map<String, Value>,
+    pub ctx: Map<String, Value>,
+}
+
+impl AccessContext {
+    fn root(&self, name: &str) -> Option<&Map<String, Value>> {
+        match name {
+            "user" => Some(&self.user),
+            "resource" => Some(&self.resource),
+            "ctx" => Some(&self.ctx),
+            _ => None,
+        }
+    }
+}
+
+#[derive(Debug, Clone, PartialEq)]
+enum Token {
+    LParen,
+    RParen,
+    LBracket,
+    RBracket,
+    Comma,
+    Not,
+    And,
+    Or,
+    Eq,
+    Ne,
+    Lt,
+    Le,
+    Gt,
+    Ge,
+    In,
+    Literal(Value),
+    Path(Vec<String>),
+}
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+enum CompareOp {
+    Eq,
+    Ne,
+    Lt,
+    Le,
+    Gt,
+    Ge,
+    In,
+}
+
+#[derive(Debug, Clone)]
+enum Expr {
+    Literal(Value),
+    Path(Vec<String>),
+    List(Vec<Expr>),
+    Not(Box<Expr>),
+    And(Box<Expr>, Box<Expr>),
+    Or(Box<Expr>, Box<Expr>),
+    Compare(CompareOp, Box<Expr>, Box<Expr>),
+}
+
+fn syntax_error(message: impl Into<String>) -> ServiceError {
+    ServiceError::BadRequest(format!("Invalid condition: {}", message.into()))
+}
+
+fn tokenize(source: &str) -> Result<Vec<Token>, ServiceError> {
+    let chars: Vec<char> = source.chars().collect();
+    let mut tokens = Vec::new();
+    let mut i = 0;
+
+    while i < chars.len() {
+        let c = chars[i];
+        let next = chars.get(i + 1).copied();
+
+        let (token, width) = match c {
+            _ if c.is_whitespace() => {
+                i += 1;
+                continue;
+            }
+            '(' => (Token::LParen, 1),
+            ')' => (Token::RParen, 1),
+            '[' => (Token::LBracket, 1),
+            ']' => (Token::RBracket, 1),
+            ',' => (Token::Comma, 1),
+            '&' if next == Some('&') => (Token::And, 2),
+            '|' if next == Some('|') => (Token::Or, 2),
+            '=' if next == Some('=') => (Token::Eq, 2),
+            '!' if next == Some('=') => (Token::Ne, 2),
+            '!' => (Token::Not, 1),
+            '<' if next == Some('=') => (Token::Le, 2),
+            '<' => (Token::Lt, 1),
+            '>' if next == Some('=') => (Token::Ge, 2),
+            '>' => (Token::Gt, 1),
+            '"' | '\'' => {
+                let end = chars[i + 1..]
+                    .iter()
+                    .position(|&ch| ch == c)
+                    .ok_or_else(|| syntax_error("unterminated string"))?;
+                let text: String = chars[i + 1..i + 1 + end].iter().collect();
+                (Token::Literal(Value::String(text)), end + 2)
+            }
+            // 数字，或不加引号的 IPv4 地址和网段（如 10.0.0.0/8）
+            _ if c.is_ascii_digit() || (c == '-' && next.is_some_and(|n| n.is_ascii_digit())) => {
+                let len = 1 + chars[i + 1..]
+                    .iter()
+                    .take_while(|ch| ch.is_ascii_digit() || **ch == '.' || **ch == '/')
+                    .count();
+                let text: String = chars[i..i + len].iter().collect();
+                let literal = match text.parse::<f64>() {
+                    Ok(number) => serde_json::Number::from_f64(number)
+                        .map(Value::Number)
+                        .ok_or_else(|| syntax_error(format!("invalid number '{}'", text)))?,
+                    Err(_) if parse_cidr(&text).is_some() => Value::String(text),
+                    Err(_) => return Err(syntax_error(format!("invalid literal '{}'", text))),
+                };
+                (Token::Literal(literal), len)
+            }
+            _ if c.is_ascii_alphabetic() || c == '_' => {
+                let len = chars[i..]
+                    .iter()
+                    .take_while(|ch| ch.is_ascii_alphanumeric() || **ch == '_' || **ch == '.')
+                    .count();
+                let text: String = chars[i..i + len].iter().collect();
+                let token = match text.as_str() {
+                    "true" => Token::Literal(Value::Bool(true)),
+                    "false" => Token::Literal(Value::Bool(false)),
+                    "null" => Token::Literal(Value::Null),
+                    "in" => Token::In,
+                    _ => {
+                        let segments: Vec<String> = text.split('.').map(str::to_string).collect();
+                        if segments.len() < 2 || segments.iter().any(String::is_empty) {
+                            return Err(syntax_error(format!("invalid attribute '{}'", text)));
+                        }
+                        if !ROOTS.contains(&segments[0].as_str()) {
+                            return Err(syntax_error(format!(
+                                "attribute '{}' must start with user, resource or ctx",
+                                text
+                            )));
+                        }
+                        Token::Path(segments)
+                    }
+                };
+                (token, len)
+            }
+            _ => return Err(syntax_error(format!("unexpected character '{}'", c))),
+        };
+
+        tokens.push(token);
+        i += width;
+    }
+
+    Ok(tokens)
+}
+
+// 递归下降解析器，优先级从低到高：|| → && → ! → 比较 → 基本项
+struct Parser {
+    tokens: Vec<Token>,
+    position: usize,
+}
+
+impl Parser {
+    fn peek(&self) -> Option<&Token> {
+        self.tokens.get(self.position)
+    }
+
+    fn advance(&mut self) -> Option<Token> {
+        let token = self.tokens.get(self.position).cloned();
+        self.position += 1;
+        token
+    }
+
+    fn expect(&mut self, expected: Token) -> Result<(), ServiceError> {
+        match self.advance() {
+            Some(token) if token == expected => Ok(()),
+            _ => Err(syntax_error(format!("expected {:?}", expected))),
+        }
+    }
+
+    fn parse_or(&mut self, depth: usize) -> Result<Expr, ServiceError> {
+        if depth > MAX_DEPTH {
+            return Err(syntax_error("expression is nested too deeply"));
+        }
+
+        let mut left = self.parse_and(depth)?;
+        while self.peek() == Some(&Token::Or) {
+            self.advance();
+            let right = self.parse_and(depth)?;
+            left = Expr::Or(Box::new(left), Box::new(right));
+        }
+        Ok(left)
+    }
+
+    fn parse_and(&mut self, depth: usize) -> Result<Expr, ServiceError> {
+        let mut left = self.parse_not(depth)?;
+        while self.peek() == Some(&Token::And) {
+            self.advance();
+            let right = self.parse_not(depth)?;
+            left = Expr::And(Box::new(left), Box::new(right));
+        }
+        Ok(left)
+    }
+
+    fn parse_not(&mut self, depth: usize) -> Result<Expr, ServiceError> {
+        if self.peek() == Some(&Token::Not) {
+            if depth > MAX_DEPTH {
+                return Err(syntax_error("expression is nested too deeply"));
+            }
+            self.advance();
+            return Ok(Expr::Not(Box::new(self.parse_not(depth + 1)?)));
+        }
+        self.parse_comparison(depth)
+    }
+
+    fn parse_comparison(&mut self, depth: usize) -> Result<Expr, ServiceError> {
+        let left = self.parse_primary(depth)?;
+
+        let op = match self.peek() {
+            Some(Token::Eq) => CompareOp::Eq,
+            Some(Token::Ne) => CompareOp::Ne,
+            Some(Token::Lt) => CompareOp::Lt,
+            Some(Token::Le) => CompareOp::Le,
+            Some(Token::Gt) => CompareOp::Gt,
+            Some(Token::Ge) => CompareOp::Ge,
+            Some(Token::In) => CompareOp::In,
+            _ => return Ok(left),
+        };
+        self.advance();
+
+        let right = self.parse_primary(depth)?;
+        Ok(Expr::Compare(op, Box::new(left), Box::new(right)))
+    }
+
+    fn parse_primary(&mut self, depth: usize) -> Result<Expr, ServiceError> {
+        // 列表可以嵌套列表，同样受深度限制
+        if depth > MAX_DEPTH {
+            return Err(syntax_error("expression is nested too deeply"));
+        }
+
+        match self.advance() {
+            Some(Token::Literal(value)) => Ok(Expr::Literal(value)),
+            Some(Token::Path(segments)) => Ok(Expr::Path(segments)),
+            Some(Token::LParen) => {
+                let expr = self.parse_or(depth + 1)?;
+                self.expect(Token::RParen)?;
+                Ok(expr)
+            }
+            Some(Token::LBracket) => {
+                let mut items = Vec::new();
+                if self.peek() != Some(&Token::RBracket) {
+                    loop {
+                        items.push(self.parse_primary(depth + 1)?);
+                        if self.peek() != Some(&Token::Comma) {
+                            break;
+                        }
+                        self.advance();
+                    }
+                }
+                self.expect(Token::RBracket)?;
+                Ok(Expr::List(items))
+            }
+            _ => Err(syntax_error("expected a value, attribute or '('")),
+        }
+    }
+}
+
+/// 解析 IPv4/IPv6 网段，省略前缀长度时视为单个地址
+pub fn parse_cidr(text: &str) -> Option<(IpAddr, u8)> {
+    let (address, prefix) = match text.split_once('/') {
+        Some((address, prefix)) => (address, Some(prefix)),
+        None => (text, None),
+    };
+    let address: IpAddr = address.parse().ok()?;
+    let max_prefix = if address.is_ipv4() { 32 } else { 128 };
+    let prefix = match prefix {
+        Some(prefix) => prefix.parse::<u8>().ok().filter(|p| *p <= max_prefix)?,
+        None => max_prefix,
+    };
+    Some((address, prefix))
+}
+
+/// 网段是否包含该地址，IPv4 与 IPv6 互不包含
+pub fn cidr_contains(network: IpAddr, prefix: u8, address: IpAddr) -> bool {
+    match (network, address) {
+        (IpAddr::V4(network), IpAddr::V4(address)) => {
+            let mask = u32::MAX.checked_shl(32 - prefix as u32).unwrap_or(0);
+            u32::from(network) & mask == u32::from(address) & mask
+        }
+        (IpAddr::V6(network), IpAddr::V6(address)) => {
+            let mask = u128::MAX.checked_shl(128 - prefix as u32).unwrap_or(0);
+            u128::from(network) & mask == u128::from(address) & mask
+        }
+        _ => false,
+    }
+}
+
+fn values_equal(left: &Value, right: &Value) -> bool {
+    match (left.as_f64(), right.as_f64()) {
+        (Some(left), Some(right)) => left == right,
+        _ => left == right,
+    }
+}
+
+fn evaluation_error(message: impl Into<String>) -> ServiceError {
+    ServiceError::BadRequest(format!("Condition evaluation failed: {}", message.into()))
+}
+
+fn as_bool(value: Value) -> Result<bool, ServiceError> {
+    value
+        .as_bool()
+        .ok_or_else(|| evaluation_error(format!("expected a boolean, found {}", value)))
+}
+
+fn evaluate(expr: &Expr, context: &AccessContext) -> Result<Value, ServiceError> {
+    match expr {
+        Expr::Literal(value) => Ok(value.clone()),
+        Expr::Path(segments) => {
+            let mut current = context
+                .root(&segments[0])
+                .and_then(|root| root.get(&segments[1]));
+            for segment in &segments[2..] {
+                current = current.and_then(|value| value.get(segment));
+            }
+            // 缺失的属性视为求值失败，而不是 null，避免条件被意外满足或跳过
+            current
+                .cloned()
+                .ok_or_else(|| evaluation_error(format!("unknown attribute '{}'", segments.join("."))))
+        }
+        Expr::List(items) => items
+            .iter()
+            .map(|item| evaluate(item, context))
+            .collect::<Result<Vec<_>, _>>()
+            .map(Value::Array),
+        Expr::Not(inner) => Ok(Value::Bool(!as_bool(evaluate(inner, context)?)?)),
+        Expr::And(left, right) => {
+            let result = as_bool(evaluate(left, context)?)? && as_bool(evaluate(right, context)?)?;
+            Ok(Value::Bool(result))
+        }
+        Expr::Or(left, right) => {
+            let result = as_bool(evaluate(left, context)?)? || as_bool(evaluate(right, context)?)?;
+            Ok(Value::Bool(result))
+        }
+        Expr::Compare(op, left, right) => {
+            let left = evaluate(left, context)?;
+            let right = evaluate(right, context)?;
+            compare(*op, &left, &right).map(Value::Bool)
+        }
+    }
+}
+
+fn compare(op: CompareOp, left: &Value, right: &Value) -> Result<bool, ServiceError> {
+    match op {
+        CompareOp::Eq => Ok(values_equal(left, right)),
+        CompareOp::Ne => Ok(!values_equal(left, right)),
+        CompareOp::In => match right {
+            Value::Array(items) => Ok(items.iter().any(|item| values_equal(left, item))),
+            Value::String(network) => {
+                let (network, prefix) = parse_cidr(network)
+                    .ok_or_else(|| evaluation_error(format!("'{}' is not a network", network)))?;
+                let address = left
+                    .as_str()
+                    .and_then(|address| address.parse::<IpAddr>().ok())
+                    .ok_or_else(|| evaluation_error(format!("{} is not an IP address", left)))?;
+                Ok(cidr_contains(network, prefix, address))
+            }
+            _ => Err(evaluation_error("right side of 'in' must be a list or a network")),
+        },
+        CompareOp::Lt | CompareOp::Le | CompareOp::Gt | CompareOp::Ge => {
+            // 数字按数值比较，字符串按字典序比较（适用于 "HH:MM" 形式的时间）
+            let ordering = match (left, right) {
+                (Value::Number(_), Value::Number(_)) => left
+                    .as_f64()
+                    .zip(right.as_f64())
+                    .and_then(|(left, right)| left.partial_cmp(&right)),
+                (Value::String(left), Value::String(right)) => Some(left.cmp(right)),
+                _ => None,
+            }
+            .ok_or_else(|| evaluation_error(format!("cannot order {} and {}", left, right)))?;
+
+            Ok(match op {
+                CompareOp::Lt => ordering.is_lt(),
+                CompareOp::Le => ordering.is_le(),
+                CompareOp::Gt => ordering.is_gt(),
+                _ => ordering.is_ge(),
+            })
+        }
+    }
+}
+
+/// 已解析的授权条件表达式
+///
+/// 语法只包含字面量（字符串、数字、`true`/`false`/`null`、IP 网段）、以 `user.`、`resource.`、
+/// `ctx.` 开头的属性、列表 `[a, b]`、比较运算 `== != < <= > >= in` 以及 `! && ||` 和括号，
+/// 没有函数调用和循环，求值时间与表达式长度成正比。`x in [..]` 判断列表成员，
+/// `ctx.ip in 10.0.0.0/8` 判断 IP 是否属于网段。引用不存在的属性或类型不匹配时求值失败。
+#[derive(Debug, Clone)]
+pub struct Condition {
+    expr: Expr,
+}
+
+impl Condition {
+    /// 解析条件表达式，语法错误时返回 `BadRequest`
+    pub fn parse(source: &str) -> Result<Self, ServiceError> {
+        if source.len() > MAX_EXPRESSION_LENGTH {
+            return Err(syntax_error(format!("longer than {} characters", MAX_EXPRESSION_LENGTH)));
+        }
+
+        let mut parser = Parser {
+            tokens: tokenize(source)?,
+            position: 0,
+        };
+        let expr = parser.parse_or(0)?;
+        if parser.position != parser.tokens.len() {
+            return Err(syntax_error("unexpected trailing tokens"));
+        }
+
+        Ok(Condition { expr })
+    }
+
+    /// 在上下文中求值，结果必须是布尔值
+    pub fn evaluate(&self, context: &AccessContext) -> Result<bool, ServiceError> {
+        as_bool(evaluate(&self.expr, context)?)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use serde_json::json;
+
+    fn context() -> AccessContext {
+        let mut context = AccessContext::default();
+        context.user.insert("id".to_string(), json!("u-1"));
+        context.user.insert("roles".to_string(), json!(["editor", "viewer"]));
+        context.user.insert("profile".to_string(), json!({ "department": "finance" }));
+        context.resource.insert("owner_id".to_string(), json!("u-1"));
+        context.resource.insert("size".to_string(), json!(42));
+        context.ctx.insert("ip".to_string(), json!("10.1.2.3"));
+        context.ctx.insert("time".to_string(), json!("09:30"));
+        context.ctx.insert("hour".to_string(), json!(9));
+        context.ctx.insert("mfa_level".to_string(), json!(2));
+        context
+    }
+
+    fn eval(source: &str) -> Result<bool, ServiceError> {
+        Condition::parse(source)?.evaluate(&context())
+    }
+
+    #[test]
+    fn compares_attributes_and_literals() {
+        assert!(eval("resource.owner_id == user.id").unwrap());
+        assert!(!eval("resource.owner_id != user.id").unwrap());
+        assert!(eval("user.id == 'u-1'").unwrap());
+        assert!(eval("user.profile.department == \"finance\"").unwrap());
+        assert!(eval("resource.size == 42").unwrap());
+        assert!(eval("resource.size == 42.0").unwrap());
+    }
+
+    #[test]
+    fn orders_numbers_and_strings() {
+        assert!(eval("ctx.mfa_level >= 2").unwrap());
+        assert!(!eval("ctx.mfa_level > 2").unwrap());
+        assert!(eval("ctx.hour < 18 && ctx.hour >= 8").unwrap());
+        assert!(eval("ctx.time >= '09:00' && ctx.time <= '17:30'").unwrap());
+        assert!(eval("resource.size <= -1 || resource.size > 10").unwrap());
+    }
+
+    #[test]
+    fn membership_in_lists_and_networks() {
+        assert!(eval("'editor' in user.roles").unwrap());
+        assert!(!eval("'admin' in user.roles").unwrap());
+        assert!(eval("ctx.hour in [8, 9, 10]").unwrap());
+        assert!(eval("ctx.ip in 10.0.0.0/8").unwrap());
+        assert!(!eval("ctx.ip in 192.168.0.0/16").unwrap());
+        assert!(eval("ctx.ip in '10.1.2.3'").unwrap());
+        assert!(eval("ctx.ip in 0.0.0.0/0").unwrap());
+    }
+
+    #[test]
+    fn boolean_operators_and_precedence() {
+        assert!(eval("!(ctx.hour > 12)").unwrap());
+        assert!(eval("true || false && false").unwrap());
+        assert!(!eval("(true || false) && false").unwrap());
+        assert!(eval("!!true").unwrap());
+    }
+
+    #[test]
+    fn missing_attributes_fail_evaluation() {
+        assert!(eval("resource.missing == null").is_err());
+        assert!(eval("user.profile.team == 'x'").is_err());
+        // 短路求值时未访问的属性不影响结果
+        assert!(eval("true || resource.missing == 1").unwrap());
+    }
+
+    #[test]
+    fn type_mismatches_fail_evaluation() {
+        assert!(eval("ctx.hour").is_err());
+        assert!(eval("ctx.hour < '10'").is_err());
+        assert!(eval("user.id in 10.0.0.0/8").is_err());
+        assert!(eval("ctx.hour in 5").is_err());
+        assert!(eval("!ctx.hour").is_err());
+    }
+
+    #[test]
+    fn rejects_invalid_syntax() {
+        assert!(Condition::parse("").is_err());
+        assert!(Condition::parse("user.id ==").is_err());
+        assert!(Condition::parse("user.id = 'a'").is_err());
+        assert!(Condition::parse("(true").is_err());
+        assert!(Condition::parse("true)").is_err());
+        assert!(Condition::parse("'unterminated").is_err());
+        assert!(Condition::parse("[1, 2").is_err());
+        assert!(Condition::parse("id == 1").is_err());
+        assert!(Condition::parse("env.home == 1").is_err());
+        assert!(Condition::parse("user..id == 1").is_err());
+        assert!(Condition::parse("ctx.ip in 10.0.0.0/33").is_err());
+        assert!(Condition::parse("len(user.roles) > 1").is_err());
+    }
+
+    #[test]
+    fn rejects_overly_long_expressions() {
+        let source = format!("user.id == '{}'", "a".repeat(MAX_EXPRESSION_LENGTH));
+        assert!(Condition::parse(&source).is_err());
+    }
+
+    #[test]
+    fn limits_nesting_depth() {
+        let nested = |open: &str, close: &str, depth: usize| {
+            format!("{}true{}", open.repeat(depth), close.repeat(depth))
+        };
+
+        assert!(Condition::parse(&nested("(", ")", MAX_DEPTH)).is_ok());
+        assert!(Condition::parse(&nested("(", ")", MAX_DEPTH + 1)).is_err());
+        assert!(Condition::parse(&nested("!", "", MAX_DEPTH)).is_ok());
+        assert!(Condition::parse(&nested("!", "", MAX_DEPTH + 1)).is_err());
+
+        let list = |depth: usize| format!("1 in {}1{}", "[".repeat(depth), "]".repeat(depth));
+        assert!(Condition::parse(&list(MAX_DEPTH)).is_ok());
+        assert!(Condition::parse(&list(MAX_DEPTH + 1)).is_err());
+        assert!(Condition::parse(&list(500)).is_err());
+    }
+}
@@ -0,0 +1,119 @@
+use actix_web::cookie::{time::Duration as CookieDuration, Cookie, SameSite};
+use actix_web::{http::Method, HttpRequest};
+
+use crate::config::CONFIG;
+use crate::utils::crypto::{constant_time_eq, generate_random_token};
+
+/// CSRF令牌请求头，值必须与CSRF Cookie一致（双重提交）
+pub const CSRF_HEADER: &str = "X-CSRF-Token";
+
+fn same_site() -> SameSite {
+    match CONFIG.cookie_same_site.to_ascii_lowercase().as_str() {
+        "strict" => SameSite::Strict,
+        "none" => SameSite::None,
+        _ => SameSite::Lax,
+    }
+}
+
+fn build_cookie(name: &str, value: String, http_only: bool, max_age_secs: i64) -> Cookie<'static> {
+    let mut builder = Cookie::build(name.to_string(), value)
+        .path(CONFIG.cookie_path.clone())
+        .secure(CONFIG.cookie_secure)
+        .http_only(http_only)
+        .same_site(same_site())
+        .max_age(CookieDuration::seconds(max_age_secs));
+
+    if let Some(domain) = &CONFIG.cookie_domain {
+        builder = builder.domain(domain.clone());
+    }
+
+    builder.finish()
+}
+
+/// 生成浏览器会话所需的Cookie，返回Cookie列表及CSRF令牌
+///
+/// 访问令牌和刷新令牌放在 HttpOnly Cookie 中；CSRF令牌放在脚本可读的Cookie中，
+/// 前端需在不安全请求中通过 X-CSRF-Token 头回传。
+pub fn session_cookies(access_token: &str, refresh_token: &str) -> (Vec<Cookie<'static>>, String) {
+    let csrf_token = generate_random_token(32);
+
+    let cookies = vec![
+        build_cookie(&CONFIG.session_cookie_name, access_token.to_string(), true, CONFIG.access_token_ttl_secs),
+        build_cookie(&CONFIG.refresh_cookie_name, refresh_token.to_string(), true, CONFIG.refresh_token_ttl_secs),
+        build_cookie(&CONFIG.csrf_cookie_name, csrf_token.clone(), false, CONFIG.refresh_token_ttl_secs),
+    ];
+
+    (cookies, csrf_token)
+}
+
+/// 生成用于清除浏览器会话的过期Cookie
+pub fn cleared_session_cookies() -> Vec<Cookie<'static>> {
+    [&CONFIG.session_cookie_name, &CONFIG.refresh_cookie_name, &CONFIG.csrf_cookie_name]
+        .iter()
+        .map(|name| build_cookie(name, String::new(), true, 0))
+        .collect()
+}
+
+/// 是否为需要CSRF保护的不安全方法
+pub fn is_unsafe_method(method: &Method) -> bool {
+    !matches!(*method, Method::GET | Method::HEAD | Method::OPTIONS | Method::TRACE)
+}
+
+/// 校验双重提交的CSRF令牌
+pub fn verify_csrf(req: &HttpRequest) -> bool {
+    let cookie = req.cookie(&CONFIG.csrf_cookie_name);
+    let header = req.headers().get(CSRF_HEADER).and_then(|h| h.to_str().ok());
+
+    csrf_tokens_match(cookie.as_ref().map(|c| c.value()), header)
+}
+
+// Cookie 和请求头都存在、非空且完全一致时才通过
+fn csrf_tokens_match(cookie: Option<&str>, header: Option<&str>) -> bool {
+    match (cookie, header) {
+        (Some(cookie), Some(header)) => !cookie.is_empty() && constant_time_eq(cookie, header),
+        _ => false,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn matching_cookie_and_header_pass() {
+        let token = generate_random_token(32);
+        assert!(csrf_tokens_match(Some(&token), Some(&token)));
+    }
+
+    #[test]
+    fn missing_cookie_or_header_fails() {
+        assert!(!csrf_tokens_match(None, Some("token")));
+        assert!(!csrf_tokens_match(Some("token"), None));
+        assert!(!csrf_tokens_match(None, None));
+    }
+
+    #[test]
+    fn mismatched_tokens_fail() {
+        assert!(!csrf_tokens_match(Some("token"), Some("other")));
+        assert!(!csrf_tokens_match(Some("token"), Some("token2")));
+        assert!(!csrf_tokens_match(Some("token"), Some("TOKEN")));
+        assert!(!csrf_tokens_match(Some("token"), Some("")));
+    }
+
+    #[test]
+    fn empty_tokens_never_match() {
+        assert!(!csrf_tokens_match(Some(""), Some("")));
+    }
+
+    #[test]
+    fn only_unsafe_methods_need_csrf() {
+        assert!(is_unsafe_method(&Method::POST));
+        assert!(is_unsafe_method(&Method::PUT));
+        assert!(is_unsafe_method(&Method::PATCH));
+        assert!(is_unsafe_method(&Method::DELETE));
+        assert!(!is_unsafe_method(&Method::GET));
+        assert!(!is_unsafe_method(&Method::HEAD));
+        assert!(!is_unsafe_method(&Method::OPTIONS));
+        assert!(!is_unsafe_method(&Method::TRACE));
+    }
+}
@@ -2,52 +2,206 @@ use chrono::{Duration, Utc};
 use jsonwebtoken::{decode, encode, DecodingKey, EncodingKey, Header, Validation};
 use serde::{Deserialize, Serialize};
 use uuid::Uuid;
-use std::env;
 
+use crate::auth::models::PrincipalKind;
+use crate::config::CONFIG;
 use crate::errors::ServiceError;
+use crate::utils::token_format::{TokenFormat, TokenFormatKind, TOKEN_FORMATS};
 
-#[derive(Debug, Serialize, Deserialize)]
+/// 修改密码受限令牌的用途标识
+pub const PASSWORD_CHANGE_PURPOSE: &str = "password_change";
+
+/// 修改密码受限令牌的有效期（秒）
+pub const PASSWORD_CHANGE_TOKEN_TTL_SECS: i64 = 600;
+
+// 委托链中的实际操作方（RFC 8693 第4.1节 act 声明）
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct Actor {
+    pub sub: String,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub client_id: Option<String>,
+    // 更早的委托方，形成嵌套链
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub act: Option<Box<Actor>>,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct Claims {
+    pub iss: String,
     pub sub: String,
     pub exp: i64,
     pub iat: i64,
+    // 令牌唯一标识，用于单独吊销
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub jti: Option<String>,
+    // 所属会话ID，会话吊销后令牌随之失效
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub sid: Option<String>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub scope: Option<String>,
+    // 令牌签发给的OAuth客户端
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub client_id: Option<String>,
+    // sub 的主体类型，缺省为用户
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub sub_kind: Option<PrincipalKind>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub aud: Option<String>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub act: Option<Actor>,
+    // 受限令牌的用途，如仅可用于修改密码
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub purpose: Option<String>,
+    // 认证方式（RFC 8176），其数量作为授权条件中的多因素认证级别
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub amr: Option<Vec<String>>,
 }
 
-/// 生成JWT令牌
+impl Claims {
+    /// 以当前时间为签发时间创建声明，受众默认为本服务
+    pub fn new(sub: String) -> Self {
+        let now = Utc::now();
+        let expires_at = now + Duration::seconds(CONFIG.access_token_ttl_secs);
+
+        Claims {
+            iss: CONFIG.jwt_issuer.clone(),
+            sub,
+            exp: expires_at.timestamp(),
+            iat: now.timestamp(),
+            jti: Some(Uuid::new_v4().to_string()),
+            sid: None,
+            scope: None,
+            client_id: None,
+            sub_kind: None,
+            aud: CONFIG.jwt_audiences.first().cloned(),
+            act: None,
+            purpose: None,
+            amr: None,
+        }
+    }
+}
+
+/// 生成访问令牌，使用配置的令牌格式签发
 pub fn generate_token(user_id: Uuid) -> Result<String, ServiceError> {
-    let jwt_secret = env::var("JWT_SECRET")
-        .map_err(|e| ServiceError::JwtError(format!("JWT_SECRET not set: {}", e)))?;
-    
-    let now = Utc::now();
-    let expires_at = now + Duration::hours(24);
-    
-    let claims = Claims {
-        sub: user_id.to_string(),
-        exp: expires_at.timestamp(),
-        iat: now.timestamp(),
-    };
-    
-    encode(
-        &Header::default(),
-        &claims,
-        &EncodingKey::from_secret(jwt_secret.as_bytes()),
-    )
-    .map_err(|e| ServiceError::JwtError(e.to_string()))
+    encode_claims(&Claims::new(user_id.to_string()))
 }
 
-/// 验证JWT令牌
+/// 生成绑定到会话的访问令牌
+pub fn generate_session_token(user_id: Uuid, session_id: Uuid) -> Result<String, ServiceError> {
+    let mut claims = Claims::new(user_id.to_string());
+    claims.sid = Some(session_id.to_string());
+    // 第一方会话目前只通过密码登录建立
+    claims.amr = Some(vec!["pwd".to_string()]);
+
+    encode_claims(&claims)
+}
+
+/// 生成仅可用于修改密码的受限令牌，不绑定会话，也没有刷新令牌
+pub fn generate_password_change_token(user_id: Uuid) -> Result<String, ServiceError> {
+    let mut claims = Claims::new(user_id.to_string());
+    claims.exp = claims.iat + PASSWORD_CHANGE_TOKEN_TTL_SECS;
+    claims.purpose = Some(PASSWORD_CHANGE_PURPOSE.to_string());
+
+    encode_claims(&claims)
+}
+
+/// 使用配置的令牌格式对声明进行签名
+pub fn encode_claims(claims: &Claims) -> Result<String, ServiceError> {
+    TOKEN_FORMATS.encode(claims)
+}
+
+/// HS256 签名的JWT格式
+pub struct JwtFormat;
+
+impl TokenFormat for JwtFormat {
+    fn kind(&self) -> TokenFormatKind {
+        TokenFormatKind::Jwt
+    }
+
+    fn matches(&self, token: &str) -> bool {
+        !token.starts_with("v4.") && token.split('.').count() == 3
+    }
+
+    fn encode(&self, claims: &Claims) -> Result<String, ServiceError> {
+        encode(
+            &Header::default(),
+            claims,
+            &EncodingKey::from_secret(CONFIG.jwt_secret.as_bytes()),
+        )
+        .map_err(|e| ServiceError::JwtError(e.to_string()))
+    }
+
+    fn decode(&self, token: &str) -> Result<Claims, ServiceError> {
+        // 只校验签名，声明由 validate_claims 统一校验
+        let mut validation = Validation::default();
+        validation.validate_exp = false;
+        validation.required_spec_claims.clear();
+
+        let token_data = decode::<Claims>(
+            token,
+            &DecodingKey::from_secret(CONFIG.jwt_secret.as_bytes()),
+            &validation,
+        )
+        .map_err(|e| ServiceError::JwtError(e.to_string()))?;
+
+        Ok(token_data.claims)
+    }
+}
+
+// 校验与令牌格式无关的声明：有效期（含时钟偏差）、签发时间、签发方以及可选的受众
+fn validate_claims(claims: &Claims, check_audience: bool) -> Result<(), ServiceError> {
+    let now = Utc::now().timestamp();
+    let leeway = CONFIG.jwt_leeway_secs as i64;
+    if claims.exp + leeway <= now {
+        return Err(ServiceError::JwtError("ExpiredSignature".to_string()));
+    }
+
+    // 签发时间晚于当前时间的令牌会越过任何令牌有效起点，一律拒绝
+    if claims.iat > now + leeway {
+        return Err(ServiceError::JwtError("ImmatureSignature".to_string()));
+    }
+
+    if claims.iss != CONFIG.jwt_issuer {
+        return Err(ServiceError::JwtError("InvalidIssuer".to_string()));
+    }
+
+    if check_audience {
+        let accepted = claims
+            .aud
+            .as_ref()
+            .is_some_and(|aud| CONFIG.jwt_audiences.contains(aud));
+        if !accepted {
+            return Err(ServiceError::JwtError("InvalidAudience".to_string()));
+        }
+    }
+
+    Ok(())
+}
+
+/// 解码并验证令牌，返回完整声明
+///
+/// 接受所有已启用的令牌格式。除签名和有效期外，还要求 iss 为本服务、
+/// aud 属于本服务接受的受众，防止共享密钥的其他服务签发或持有的令牌在此重放。
+pub fn decode_token(token: &str) -> Result<Claims, ServiceError> {
+    let claims = TOKEN_FORMATS.decode(token)?;
+    validate_claims(&claims, true)?;
+
+    Ok(claims)
+}
+
+/// 解码本服务签发的任意受众的令牌，仅供内省、吊销和令牌交换使用
+pub fn decode_token_any_audience(token: &str) -> Result<Claims, ServiceError> {
+    let claims = TOKEN_FORMATS.decode(token)?;
+    validate_claims(&claims, false)?;
+
+    Ok(claims)
+}
+
+/// 验证任意已启用格式的访问令牌，返回用户ID
 pub fn verify_token(token: &str) -> Result<Uuid, ServiceError> {
-    let jwt_secret = env::var("JWT_SECRET")
-        .map_err(|e| ServiceError::JwtError(format!("JWT_SECRET not set: {}", e)))?;
-    
-    let token_data = decode::<Claims>(
-        token,
-        &DecodingKey::from_secret(jwt_secret.as_bytes()),
-        &Validation::default(),
-    )
-    .map_err(|e| ServiceError::JwtError(e.to_string()))?;
+    let claims = decode_token(token)?;
     
-    let user_id = Uuid::parse_str(&token_data.claims.sub)
+    let user_id = Uuid::parse_str(&claims.sub)
         .map_err(|e| ServiceError::JwtError(e.to_string()))?;
     
     Ok(user_id)
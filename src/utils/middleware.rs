@@ -3,17 +3,83 @@ use actix_web::{
     Error, HttpMessage, HttpRequest, web,
     FromRequest, dev::Payload,
 };
+use chrono::{Timelike, Utc};
 use futures::future::{ready, LocalBoxFuture, Ready};
+use lazy_static::lazy_static;
+use serde_json::{Map, Value};
+use std::net::IpAddr;
 use std::rc::Rc;
 use uuid::Uuid;
 
-use crate::utils::jwt::verify_token;
+use crate::api_keys::services::authenticate_api_key;
+use crate::auth::models::{parse_scope, Principal, PrincipalKind};
+use crate::config::CONFIG;
+use crate::db::{run_blocking, DbPool};
+use crate::errors::ServiceError;
+use crate::utils::condition::{cidr_contains, parse_cidr, AccessContext};
+use crate::utils::cookies::{is_unsafe_method, verify_csrf};
+use crate::utils::jwt::{decode_token, PASSWORD_CHANGE_PURPOSE};
+use crate::utils::revocation::REVOCATIONS;
 
-pub struct AuthMiddleware;
+enum Credential {
+    Bearer(String),
+    ApiKey(String),
+    // 浏览器会话Cookie中的访问令牌，不安全方法需通过CSRF校验
+    Cookie(String),
+}
+
+// 从请求头或会话Cookie中解析凭证，请求头优先
+fn extract_credential(req: &ServiceRequest) -> Result<Credential, Error> {
+    if let Some(header) = req.headers().get("Authorization") {
+        let header_str = header.to_str().map_err(|_| {
+            actix_web::error::ErrorUnauthorized("Invalid authorization header")
+        })?;
+        
+        if let Some(token) = header_str.strip_prefix("Bearer ") {
+            return Ok(Credential::Bearer(token.to_string()));
+        }
+        
+        if let Some(key) = header_str.strip_prefix("ApiKey ") {
+            return Ok(Credential::ApiKey(key.to_string()));
+        }
+        
+        return Err(actix_web::error::ErrorUnauthorized("Invalid token format"));
+    }
+    
+    if let Some(header) = req.headers().get("X-API-Key") {
+        let key = header.to_str().map_err(|_| {
+            actix_web::error::ErrorUnauthorized("Invalid API key header")
+        })?;
+        
+        return Ok(Credential::ApiKey(key.to_string()));
+    }
+    
+    if let Some(cookie) = req.cookie(&CONFIG.session_cookie_name) {
+        return Ok(Credential::Cookie(cookie.value().to_string()));
+    }
+    
+    Err(actix_web::error::ErrorUnauthorized("Missing authorization token"))
+}
+
+fn db_pool(req: &ServiceRequest) -> Result<web::Data<DbPool>, Error> {
+    req.app_data::<web::Data<DbPool>>()
+        .cloned()
+        .ok_or_else(|| actix_web::error::ErrorInternalServerError("Database pool not found"))
+}
+
+pub struct AuthMiddleware {
+    // 是否接受仅可用于修改密码的受限令牌
+    allow_password_change: bool,
+}
 
 impl AuthMiddleware {
     pub fn new() -> Self {
-        AuthMiddleware
+        AuthMiddleware { allow_password_change: false }
+    }
+
+    /// 用于修改密码端点，额外接受登录时因密码过期或被强制修改而签发的受限令牌
+    pub fn allowing_password_change() -> Self {
+        AuthMiddleware { allow_password_change: true }
     }
 }
 
@@ -31,12 +97,14 @@ where
     fn new_transform(&self, service: S) -> Self::Future {
         ready(Ok(AuthMiddlewareService {
             service: Rc::new(service),
+            allow_password_change: self.allow_password_change,
         }))
     }
 }
 
 pub struct AuthMiddlewareService<S> {
     service: Rc<S>,
+    allow_password_change: bool,
 }
 
 impl<S, B> Service<ServiceRequest> for AuthMiddlewareService<S>
@@ -52,35 +120,83 @@ where
 
     fn call(&self, req: ServiceRequest) -> Self::Future {
         let service = Rc::clone(&self.service);
+        let allow_password_change = self.allow_password_change;
 
         Box::pin(async move {
-            // 从请求头中获取令牌
-            let auth_header = req.headers().get("Authorization");
+            // 从请求中获取凭证：Bearer JWT、Authorization: ApiKey、X-API-Key 或会话Cookie
+            let credential = extract_credential(&req)?;
             
-            let token = match auth_header {
-                Some(header) => {
-                    let header_str = header.to_str().map_err(|_| {
-                        actix_web::error::ErrorUnauthorized("Invalid authorization header")
+            if matches!(credential, Credential::Cookie(_))
+                && is_unsafe_method(req.method())
+                && !verify_csrf(req.request())
+            {
+                return Err(actix_web::error::ErrorForbidden("CSRF token mismatch"));
+            }
+            
+            let principal = match credential {
+                Credential::Bearer(token) | Credential::Cookie(token) => {
+                    // 验证令牌
+                    let claims = decode_token(&token).map_err(|_| {
+                        actix_web::error::ErrorUnauthorized("Invalid token")
+                    })?;
+                    
+                    // 受限令牌只能用于允许的端点
+                    match claims.purpose.as_deref() {
+                        None => {}
+                        Some(PASSWORD_CHANGE_PURPOSE) if allow_password_change => {}
+                        Some(PASSWORD_CHANGE_PURPOSE) => {
+                            return Err(actix_web::error::ErrorForbidden("Password change required"));
+                        }
+                        Some(_) => return Err(actix_web::error::ErrorUnauthorized("Invalid token")),
+                    }
+                    
+                    let subject_id = Uuid::parse_str(&claims.sub).map_err(|_| {
+                        actix_web::error::ErrorUnauthorized("Invalid token")
                     })?;
                     
-                    if !header_str.starts_with("Bearer ") {
-                        return Err(actix_web::error::ErrorUnauthorized("Invalid token format"));
+                    // 检查令牌或其所属会话是否已被吊销，优先使用内存中的吊销集合，
+                    // 集合未同步（如监听连接中断）时回退到数据库查询
+                    let revoked = match REVOCATIONS.is_revoked(&claims) {
+                        Some(revoked) => revoked,
+                        None => {
+                            let pool = db_pool(&req)?;
+                            let claims = claims.clone();
+                            let active = run_blocking(&pool, move |conn| {
+                                crate::oauth::services::is_token_active(conn, &claims)
+                            })
+                            .await?;
+                            !active
+                        }
+                    };
+                    if revoked {
+                        return Err(actix_web::error::ErrorUnauthorized("Token has been revoked"));
                     }
                     
-                    header_str[7..].to_string()
+                    // 记录认证方式，供授权条件计算多因素认证级别
+                    req.extensions_mut().insert(AuthMethods(claims.amr.clone().unwrap_or_default()));
+                    
+                    let scopes = parse_scope(claims.scope.as_deref());
+                    let principal = match claims.sub_kind {
+                        Some(PrincipalKind::Client) => Principal::client(subject_id, scopes),
+                        _ => Principal::user(subject_id, scopes),
+                    };
+                    principal.issued_to(claims.client_id)
                 }
-                None => {
-                    return Err(actix_web::error::ErrorUnauthorized("Missing authorization token"));
+                Credential::ApiKey(key) => {
+                    let pool = db_pool(&req)?;
+                    // 数据库故障不应表现为密钥无效
+                    run_blocking(&pool, move |conn| authenticate_api_key(conn, &key)).await.map_err(|e| match e {
+                        ServiceError::DatabaseError(_) | ServiceError::InternalServerError => Error::from(e),
+                        _ => actix_web::error::ErrorUnauthorized("Invalid API key"),
+                    })?
                 }
             };
             
-            // 验证令牌
-            let user_id = verify_token(&token).map_err(|_| {
-                actix_web::error::ErrorUnauthorized("Invalid token")
-            })?;
-            
-            // 将用户ID添加到请求扩展中
-            req.extensions_mut().insert(user_id);
+            // 将主体添加到请求扩展中，用户主体同时保留用户ID
+            if principal.kind == PrincipalKind::User {
+                req.extensions_mut().insert(principal.id);
+            }
+            req.extensions_mut().insert(principal);
             
             // 继续处理请求
             let res = service.call(req).await?;
@@ -89,6 +205,104 @@ where
     }
 }
 
+// 令牌中记录的认证方式，API密钥认证时不存在
+pub struct AuthMethods(pub Vec<String>);
+
+lazy_static! {
+    /// 受信任的反向代理网段，配置无效时在启动时报错
+    pub static ref TRUSTED_PROXIES: Vec<(IpAddr, u8)> = CONFIG
+        .trusted_proxies
+        .iter()
+        .map(|proxy| parse_cidr(proxy).unwrap_or_else(|| panic!("TRUSTED_PROXIES 中的地址无效: {}", proxy)))
+        .collect();
+}
+
+fn is_trusted_proxy(address: IpAddr) -> bool {
+    TRUSTED_PROXIES
+        .iter()
+        .any(|(network, prefix)| cidr_contains(*network, *prefix, address))
+}
+
+// 客户端地址：直连对端不是受信任代理时就是对端地址；否则从右向左跳过 X-Forwarded-For 中
+// 受信任的代理，取第一个不受信任的地址。该头最左侧的部分可由客户端任意伪造，不能直接采用。
+// 经过的地址无法解析时返回 None，依赖 ctx.ip 的条件随之求值失败。
+fn client_ip(req: &HttpRequest) -> Option<IpAddr> {
+    let mut address = req.peer_addr()?.ip();
+    if !is_trusted_proxy(address) {
+        return Some(address);
+    }
+
+    let forwarded: Vec<&str> = req
+        .headers()
+        .get_all("X-Forwarded-For")
+        .filter_map(|value| value.to_str().ok())
+        .flat_map(|value| value.split(','))
+        .collect();
+
+    for hop in forwarded.into_iter().rev() {
+        address = hop.trim().parse().ok()?;
+        if !is_trusted_proxy(address) {
+            break;
+        }
+    }
+
+    Some(address)
+}
+
+/// 授权条件求值上下文的构建器
+pub struct AccessContextBuilder {
+    context: AccessContext,
+}
+
+impl AccessContextBuilder {
+    /// 从请求中收集上下文：
+    ///
+    /// - `ctx.ip`：客户端地址，只有直连对端属于 `TRUSTED_PROXIES` 时才采用 `X-Forwarded-For`；
+    /// - `ctx.time`（UTC "HH:MM"）、`ctx.hour`、`ctx.weekday`（"mon".."sun"）；
+    /// - `ctx.method`、`ctx.mfa_level`（令牌 `amr` 中不同认证方式的数量，API密钥为 0）；
+    /// - `ctx.params.*`：全部路径参数。路径参数由调用方控制，不放入 `resource.*`，
+    ///   资源属性只来自服务端加载的数据，见 [`PermissionCheckMiddleware::with_resource_attributes`]。
+    pub fn from_request(req: &HttpRequest) -> Self {
+        let now = Utc::now();
+        let mut context = AccessContext::default();
+
+        if let Some(ip) = client_ip(req) {
+            context.ctx.insert("ip".to_string(), ip.to_string().into());
+        }
+        context.ctx.insert("time".to_string(), now.format("%H:%M").to_string().into());
+        context.ctx.insert("hour".to_string(), now.hour().into());
+        context.ctx.insert("weekday".to_string(), now.format("%a").to_string().to_lowercase().into());
+        context.ctx.insert("method".to_string(), req.method().as_str().into());
+
+        let mfa_level = req.extensions().get::<AuthMethods>().map_or(0, |methods| {
+            let mut methods = methods.0.clone();
+            methods.sort();
+            methods.dedup();
+            methods.len()
+        });
+        context.ctx.insert("mfa_level".to_string(), mfa_level.into());
+
+        let params: Map<String, Value> = req
+            .match_info()
+            .iter()
+            .map(|(name, value)| (name.to_string(), value.into()))
+            .collect();
+        context.ctx.insert("params".to_string(), Value::Object(params));
+
+        AccessContextBuilder { context }
+    }
+
+    /// 添加调用方已知的资源属性，如从数据库加载的 `owner_id`
+    pub fn resource_attributes(mut self, attributes: Map<String, Value>) -> Self {
+        self.context.resource.extend(attributes);
+        self
+    }
+
+    pub fn build(self) -> AccessContext {
+        self.context
+    }
+}
+
 // 用于从请求中提取用户ID的提取器
 pub struct AuthenticatedUser {
     pub id: Uuid,
@@ -108,10 +322,31 @@ impl FromRequest for AuthenticatedUser {
     }
 }
 
+// 用于从请求中提取已认证主体（用户或服务客户端）的提取器
+impl FromRequest for Principal {
+    type Error = Error;
+    type Future = Ready<Result<Self, Self::Error>>;
+
+    fn from_request(req: &HttpRequest, _: &mut Payload) -> Self::Future {
+        match req.extensions().get::<Principal>().cloned() {
+            Some(principal) => ready(Ok(principal)),
+            None => ready(Err(actix_web::error::ErrorUnauthorized("Unauthorized"))),
+        }
+    }
+}
+
+/// 按资源实例 id 加载授权条件可引用的 `resource.*` 属性，如 `owner_id`
+pub type ResourceAttributeLoader =
+    fn(&mut diesel::PgConnection, &str) -> Result<Map<String, Value>, ServiceError>;
+
 // 权限检查中间件
 pub struct PermissionCheckMiddleware {
     resource: String,
     action: String,
+    // 从该路径参数读取资源实例 id，同时检查实例级授权
+    resource_id_param: Option<String>,
+    // 为授权条件加载资源属性
+    attribute_loader: Option<ResourceAttributeLoader>,
 }
 
 impl PermissionCheckMiddleware {
@@ -119,8 +354,34 @@ impl PermissionCheckMiddleware {
         PermissionCheckMiddleware {
             resource: resource.to_string(),
             action: action.to_string(),
+            resource_id_param: None,
+            attribute_loader: None,
+        }
+    }
+
+    /// 检查对路径参数 `param` 所指资源实例的权限，类型级权限或该实例上的授权均可放行
+    ///
+    /// 路径参数只有在路由匹配后才可用，因此需要用在 `web::resource` 上，
+    /// 或者参数位于所包裹的 scope 路径中。
+    pub fn for_resource_param(resource: &str, action: &str, param: &str) -> Self {
+        PermissionCheckMiddleware {
+            resource: resource.to_string(),
+            action: action.to_string(),
+            resource_id_param: Some(param.to_string()),
+            attribute_loader: None,
         }
     }
+
+    /// 检查前用 `loader` 按资源实例 id 加载资源属性，使 `resource.owner_id == user.id`
+    /// 之类的条件可以成立。只能与 `for_resource_param` 一起使用。
+    pub fn with_resource_attributes(mut self, loader: ResourceAttributeLoader) -> Self {
+        assert!(
+            self.resource_id_param.is_some(),
+            "with_resource_attributes 需要与 for_resource_param 一起使用"
+        );
+        self.attribute_loader = Some(loader);
+        self
+    }
 }
 
 impl<S, B> Transform<S, ServiceRequest> for PermissionCheckMiddleware
@@ -139,6 +400,8 @@ where
             service: Rc::new(service),
             resource: self.resource.clone(),
             action: self.action.clone(),
+            resource_id_param: self.resource_id_param.clone(),
+            attribute_loader: self.attribute_loader,
         }))
     }
 }
@@ -147,6 +410,8 @@ pub struct PermissionCheckMiddlewareService<S> {
     service: Rc<S>,
     resource: String,
     action: String,
+    resource_id_param: Option<String>,
+    attribute_loader: Option<ResourceAttributeLoader>,
 }
 
 impl<S, B> Service<ServiceRequest> for PermissionCheckMiddlewareService<S>
@@ -164,28 +429,49 @@ where
         let service = Rc::clone(&self.service);
         let resource = self.resource.clone();
         let action = self.action.clone();
+        let resource_id_param = self.resource_id_param.clone();
+        let attribute_loader = self.attribute_loader;
 
         Box::pin(async move {
-            // 从请求中获取用户ID
-            let user_id = match req.extensions().get::<Uuid>() {
-                Some(id) => *id,
+            // 从请求中获取已认证主体
+            let principal = match req.extensions().get::<Principal>() {
+                Some(principal) => principal.clone(),
                 None => return Err(actix_web::error::ErrorForbidden("Access denied")),
             };
             
-            // 获取数据库连接
-            let pool = req.app_data::<web::Data<crate::db::DbPool>>()
-                .ok_or_else(|| actix_web::error::ErrorInternalServerError("Database pool not found"))?;
+            // 读取资源实例 id，路由未提供该参数时拒绝访问
+            let resource_id = match &resource_id_param {
+                Some(param) => match req.match_info().get(param) {
+                    Some(resource_id) => Some(resource_id.to_string()),
+                    None => {
+                        log::warn!("路径参数 {} 不存在，无法检查实例级权限", param);
+                        return Err(actix_web::error::ErrorForbidden("Access denied"));
+                    }
+                },
+                None => None,
+            };
             
-            let mut conn = pool.get()
-                .map_err(|_| actix_web::error::ErrorInternalServerError("Database connection error"))?;
+            let pool = db_pool(&req)?;
+            let mut context = AccessContextBuilder::from_request(req.request());
             
-            // 检查用户是否有权限
-            let has_permission = crate::permissions::services::check_user_permission(
-                &mut conn, 
-                user_id, 
-                &resource, 
-                &action
-            )?;
+            let has_permission = run_blocking(&pool, move |conn| {
+                // 授权条件的求值上下文，按需加载资源属性
+                if let (Some(loader), Some(resource_id)) = (attribute_loader, resource_id.as_deref()) {
+                    context = context.resource_attributes(loader(conn, resource_id)?);
+                }
+                let context = context.build();
+                
+                // 检查主体是否有权限（同时受令牌或API密钥的作用域限制）
+                crate::permissions::services::check_principal_permission(
+                    conn,
+                    &principal,
+                    &resource,
+                    &action,
+                    resource_id.as_deref(),
+                    &context,
+                )
+            })
+            .await?;
             
             if !has_permission {
                 return Err(actix_web::error::ErrorForbidden("Insufficient permissions"));
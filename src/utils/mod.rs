@@ -2,3 +2,11 @@ pub mod password;
 pub mod jwt;
 pub mod middleware;
 pub mod supabase;
+pub mod crypto;
+pub mod cookies;
+pub mod token_format;
+pub mod paseto;
+pub mod revocation;
+pub mod password_policy;
+pub mod breach_filter;
+pub mod condition;
@@ -0,0 +1,495 @@
+use base64::{engine::general_purpose::URL_SAFE_NO_PAD, Engine};
+use blake2::digest::consts::{U32, U56};
+use blake2::digest::Mac;
+use blake2::Blake2bMac;
+use chacha20::cipher::{KeyIvInit, StreamCipher};
+use chacha20::XChaCha20;
+use chrono::{DateTime, TimeZone, Utc};
+use ed25519_dalek::{Signature, Signer, SigningKey, Verifier, VerifyingKey};
+use rand_core::{OsRng, RngCore};
+use serde_json::Value;
+
+use crate::errors::ServiceError;
+use crate::utils::crypto::constant_time_eq;
+use crate::utils::jwt::Claims;
+use crate::utils::token_format::{TokenFormat, TokenFormatKind};
+
+const LOCAL_HEADER: &str = "v4.local.";
+const PUBLIC_HEADER: &str = "v4.public.";
+
+// 由随机数派生的 (加密密钥, XChaCha20 随机数, 认证密钥)
+type DerivedKeys = ([u8; 32], [u8; 24], [u8; 32]);
+
+fn paseto_error(msg: &str) -> ServiceError {
+    ServiceError::JwtError(format!("PASETO: {}", msg))
+}
+
+// 预认证编码 PAE（PASETO 规范第2.2节）
+fn pae(pieces: &[&[u8]]) -> Vec<u8> {
+    fn le64(n: usize) -> [u8; 8] {
+        ((n as u64) & (u64::MAX >> 1)).to_le_bytes()
+    }
+
+    let mut out = Vec::new();
+    out.extend_from_slice(&le64(pieces.len()));
+    for piece in pieces {
+        out.extend_from_slice(&le64(piece.len()));
+        out.extend_from_slice(piece);
+    }
+    out
+}
+
+// PASETO 的注册声明 exp/iat 使用 ISO 8601 字符串，这里与 Claims 的时间戳互转
+fn claims_to_payload(claims: &Claims) -> Result<Vec<u8>, ServiceError> {
+    let mut value = serde_json::to_value(claims).map_err(|e| paseto_error(&e.to_string()))?;
+
+    for key in ["exp", "iat"] {
+        if let Some(timestamp) = value.get(key).and_then(Value::as_i64) {
+            let time = Utc
+                .timestamp_opt(timestamp, 0)
+                .single()
+                .ok_or_else(|| paseto_error("invalid timestamp"))?;
+            value[key] = Value::String(time.to_rfc3339());
+        }
+    }
+
+    serde_json::to_vec(&value).map_err(|e| paseto_error(&e.to_string()))
+}
+
+fn payload_to_claims(payload: &[u8]) -> Result<Claims, ServiceError> {
+    let mut value: Value = serde_json::from_slice(payload).map_err(|e| paseto_error(&e.to_string()))?;
+
+    for key in ["exp", "iat"] {
+        if let Some(time) = value.get(key).and_then(Value::as_str) {
+            let timestamp = DateTime::parse_from_rfc3339(time)
+                .map_err(|e| paseto_error(&e.to_string()))?
+                .timestamp();
+            value[key] = Value::from(timestamp);
+        }
+    }
+
+    serde_json::from_value(value).map_err(|e| paseto_error(&e.to_string()))
+}
+
+// 拆分令牌主体，并以常量时间校验页脚与预期一致（PASETO 规范第4节 Decrypt/Verify 第2步）
+fn split_body(token: &str, header: &str, footer: &[u8]) -> Result<Vec<u8>, ServiceError> {
+    let rest = token.strip_prefix(header).ok_or_else(|| paseto_error("wrong header"))?;
+    let (body, token_footer) = match rest.split_once('.') {
+        Some((body, token_footer)) => (body, token_footer),
+        None => (rest, ""),
+    };
+
+    let token_footer = URL_SAFE_NO_PAD
+        .decode(token_footer)
+        .map_err(|_| paseto_error("invalid footer"))?;
+    if !constant_time_eq(&token_footer, footer) {
+        return Err(paseto_error("footer mismatch"));
+    }
+
+    URL_SAFE_NO_PAD
+        .decode(body)
+        .map_err(|_| paseto_error("invalid encoding"))
+}
+
+// 拼接令牌，页脚为空时省略
+fn join_token(header: &str, body: &[u8], footer: &[u8]) -> String {
+    if footer.is_empty() {
+        format!("{}{}", header, URL_SAFE_NO_PAD.encode(body))
+    } else {
+        format!("{}{}.{}", header, URL_SAFE_NO_PAD.encode(body), URL_SAFE_NO_PAD.encode(footer))
+    }
+}
+
+/// v4.local：XChaCha20 加密 + BLAKE2b-MAC 的对称令牌，载荷对持有者不可见
+pub struct PasetoV4Local {
+    key: [u8; 32],
+}
+
+impl PasetoV4Local {
+    pub fn new(key: [u8; 32]) -> Self {
+        PasetoV4Local { key }
+    }
+
+    fn derive_keys(&self, nonce: &[u8]) -> Result<DerivedKeys, ServiceError> {
+        let mut enc_mac = <Blake2bMac<U56>>::new_with_salt_and_personal(&self.key, &[], &[])
+            .map_err(|_| paseto_error("invalid key"))?;
+        enc_mac.update(b"paseto-encryption-key");
+        enc_mac.update(nonce);
+        let tmp = enc_mac.finalize().into_bytes();
+
+        let mut auth_mac = <Blake2bMac<U32>>::new_with_salt_and_personal(&self.key, &[], &[])
+            .map_err(|_| paseto_error("invalid key"))?;
+        auth_mac.update(b"paseto-auth-key-for-aead");
+        auth_mac.update(nonce);
+        let auth_key = auth_mac.finalize().into_bytes();
+
+        let mut enc_key = [0u8; 32];
+        let mut nonce2 = [0u8; 24];
+        let mut ak = [0u8; 32];
+        enc_key.copy_from_slice(&tmp[..32]);
+        nonce2.copy_from_slice(&tmp[32..]);
+        ak.copy_from_slice(&auth_key);
+
+        Ok((enc_key, nonce2, ak))
+    }
+
+    fn tag(auth_key: &[u8; 32], pre_auth: &[u8]) -> Result<Vec<u8>, ServiceError> {
+        let mut mac = <Blake2bMac<U32>>::new_with_salt_and_personal(auth_key, &[], &[])
+            .map_err(|_| paseto_error("invalid key"))?;
+        mac.update(pre_auth);
+        Ok(mac.finalize().into_bytes().to_vec())
+    }
+
+    // 使用给定的 32 字节随机数加密，footer 和 implicit 参与认证（implicit 不出现在令牌中）
+    fn encrypt(&self, message: &[u8], nonce: &[u8; 32], footer: &[u8], implicit: &[u8]) -> Result<String, ServiceError> {
+        let (enc_key, nonce2, auth_key) = self.derive_keys(nonce)?;
+        let mut ciphertext = message.to_vec();
+        XChaCha20::new(&enc_key.into(), &nonce2.into()).apply_keystream(&mut ciphertext);
+
+        let pre_auth = pae(&[LOCAL_HEADER.as_bytes(), nonce, &ciphertext, footer, implicit]);
+        let tag = Self::tag(&auth_key, &pre_auth)?;
+
+        let mut body = Vec::with_capacity(nonce.len() + ciphertext.len() + tag.len());
+        body.extend_from_slice(nonce);
+        body.extend_from_slice(&ciphertext);
+        body.extend_from_slice(&tag);
+
+        Ok(join_token(LOCAL_HEADER, &body, footer))
+    }
+
+    // 校验认证标签后解密，返回明文载荷
+    fn decrypt(&self, token: &str, footer: &[u8], implicit: &[u8]) -> Result<Vec<u8>, ServiceError> {
+        let body = split_body(token, LOCAL_HEADER, footer)?;
+        if body.len() < 64 {
+            return Err(paseto_error("token too short"));
+        }
+
+        let (nonce, rest) = body.split_at(32);
+        let (ciphertext, tag) = rest.split_at(rest.len() - 32);
+
+        let (enc_key, nonce2, auth_key) = self.derive_keys(nonce)?;
+        let pre_auth = pae(&[LOCAL_HEADER.as_bytes(), nonce, ciphertext, footer, implicit]);
+        let expected = Self::tag(&auth_key, &pre_auth)?;
+
+        if !constant_time_eq(&expected, tag) {
+            return Err(paseto_error("invalid tag"));
+        }
+
+        let mut message = ciphertext.to_vec();
+        XChaCha20::new(&enc_key.into(), &nonce2.into()).apply_keystream(&mut message);
+
+        Ok(message)
+    }
+}
+
+impl TokenFormat for PasetoV4Local {
+    fn kind(&self) -> TokenFormatKind {
+        TokenFormatKind::PasetoV4Local
+    }
+
+    fn matches(&self, token: &str) -> bool {
+        token.starts_with(LOCAL_HEADER)
+    }
+
+    fn encode(&self, claims: &Claims) -> Result<String, ServiceError> {
+        let mut nonce = [0u8; 32];
+        OsRng.fill_bytes(&mut nonce);
+
+        self.encrypt(&claims_to_payload(claims)?, &nonce, b"", b"")
+    }
+
+    fn decode(&self, token: &str) -> Result<Claims, ServiceError> {
+        payload_to_claims(&self.decrypt(token, b"", b"")?)
+    }
+}
+
+/// v4.public：Ed25519 签名的令牌，载荷明文可读，资源服务器只需公钥即可验证
+pub struct PasetoV4Public {
+    signing_key: SigningKey,
+    verifying_key: VerifyingKey,
+}
+
+impl PasetoV4Public {
+    pub fn new(seed: [u8; 32]) -> Self {
+        let signing_key = SigningKey::from_bytes(&seed);
+        let verifying_key = signing_key.verifying_key();
+
+        PasetoV4Public { signing_key, verifying_key }
+    }
+
+    // 签名，footer 和 implicit 参与签名（implicit 不出现在令牌中）
+    fn sign(&self, message: &[u8], footer: &[u8], implicit: &[u8]) -> String {
+        let m2 = pae(&[PUBLIC_HEADER.as_bytes(), message, footer, implicit]);
+        let signature = self.signing_key.sign(&m2);
+
+        let mut body = message.to_vec();
+        body.extend_from_slice(&signature.to_bytes());
+
+        join_token(PUBLIC_HEADER, &body, footer)
+    }
+
+    // 验证签名，返回明文载荷
+    fn verify(&self, token: &str, footer: &[u8], implicit: &[u8]) -> Result<Vec<u8>, ServiceError> {
+        let body = split_body(token, PUBLIC_HEADER, footer)?;
+        if body.len() < 64 {
+            return Err(paseto_error("token too short"));
+        }
+
+        let (message, signature) = body.split_at(body.len() - 64);
+        let signature = Signature::from_slice(signature).map_err(|_| paseto_error("invalid signature"))?;
+
+        let m2 = pae(&[PUBLIC_HEADER.as_bytes(), message, footer, implicit]);
+        self.verifying_key
+            .verify(&m2, &signature)
+            .map_err(|_| paseto_error("invalid signature"))?;
+
+        Ok(message.to_vec())
+    }
+}
+
+impl TokenFormat for PasetoV4Public {
+    fn kind(&self) -> TokenFormatKind {
+        TokenFormatKind::PasetoV4Public
+    }
+
+    fn matches(&self, token: &str) -> bool {
+        token.starts_with(PUBLIC_HEADER)
+    }
+
+    fn encode(&self, claims: &Claims) -> Result<String, ServiceError> {
+        Ok(self.sign(&claims_to_payload(claims)?, b"", b""))
+    }
+
+    fn decode(&self, token: &str) -> Result<Claims, ServiceError> {
+        payload_to_claims(&self.verify(token, b"", b"")?)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    // PASETO 官方测试向量 v4.json（paseto-standard/test-vectors）
+    const LOCAL_KEY: &str = "707172737475767778797a7b7c7d7e7f808182838485868788898a8b8c8d8e8f";
+    const LOCAL_NONCE: &str = "df654812bac492663825520ba2f6e67cf5ca5bdc13d4e7507a98cc4c2fcc3ad8";
+    const PUBLIC_SEED: &str = "b4cbfb43df4ce210727d953e4a713307fa19bb7d9f85041438d9e11b942a3774";
+    const PUBLIC_KEY: &str = "1eb9dbbbbc047c03fd70604e0071f0987e16b28b757225c11f00415d0e20b1a2";
+
+    const SECRET_MESSAGE: &[u8] = br#"{"data":"this is a secret message","exp":"2022-01-01T00:00:00+00:00"}"#;
+    const HIDDEN_MESSAGE: &[u8] = br#"{"data":"this is a hidden message","exp":"2022-01-01T00:00:00+00:00"}"#;
+    const SIGNED_MESSAGE: &[u8] = br#"{"data":"this is a signed message","exp":"2022-01-01T00:00:00+00:00"}"#;
+    const FOOTER: &[u8] = br#"{"kid":"zVhMiPBP9fRf2snEcT7gFTioeA9COcNy9DfgL1W60haN"}"#;
+
+    // (名称, 随机数, 载荷, 页脚, 令牌)
+    type LocalVector = (&'static str, &'static str, &'static [u8], &'static [u8], &'static str);
+
+    const LOCAL_VECTORS: &[LocalVector] = &[
+        (
+            "4-E-1",
+            "0000000000000000000000000000000000000000000000000000000000000000",
+            SECRET_MESSAGE,
+            b"",
+            "v4.local.AAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAQAr68PS4AXe7If_ZgesdkUMvSwscFlAl1pk5HC0e8kApeaqMfGo_7OpBnwJOAbY9V7WU6abu74MmcUE8YWAiaArVI8XJ5hOb_4v9RmDkneN0S92dx0OW4pgy7omxgf3S8c3LlQg",
+        ),
+        (
+            "4-E-2",
+            "0000000000000000000000000000000000000000000000000000000000000000",
+            HIDDEN_MESSAGE,
+            b"",
+            "v4.local.AAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAQAr68PS4AXe7If_ZgesdkUMvS2csCgglvpk5HC0e8kApeaqMfGo_7OpBnwJOAbY9V7WU6abu74MmcUE8YWAiaArVI8XIemu9chy3WVKvRBfg6t8wwYHK0ArLxxfZP73W_vfwt5A",
+        ),
+        (
+            "4-E-4",
+            LOCAL_NONCE,
+            HIDDEN_MESSAGE,
+            b"",
+            "v4.local.32VIErrEkmY4JVILovbmfPXKW9wT1OdQepjMTC_MOtjA4kiqw7_tcaOM5GNEcnTxl60WiA8rd3wgFSNb_UdJPXjpzm0KW9ojM5f4O2mRvE2IcweP-PRdoHjd5-RHCiExR1IK6t4gt6TiLm55vIH8c_lGxxZpE3AWlH4WTR0v45nsWoU3gQ",
+        ),
+        (
+            "4-E-5",
+            LOCAL_NONCE,
+            SECRET_MESSAGE,
+            FOOTER,
+            "v4.local.32VIErrEkmY4JVILovbmfPXKW9wT1OdQepjMTC_MOtjA4kiqw7_tcaOM5GNEcnTxl60WkwMsYXw6FSNb_UdJPXjpzm0KW9ojM5f4O2mRvE2IcweP-PRdoHjd5-RHCiExR1IK6t4x-RMNXtQNbz7FvFZ_G-lFpk5RG3EOrwDL6CgDqcerSQ.eyJraWQiOiJ6VmhNaVBCUDlmUmYyc25FY1Q3Z0ZUaW9lQTlDT2NOeTlEZmdMMVc2MGhhTiJ9",
+        ),
+    ];
+
+    // (名称, 页脚, 隐式断言, 令牌)
+    const PUBLIC_VECTORS: &[(&str, &[u8], &[u8], &str)] = &[
+        (
+            "4-S-1",
+            b"",
+            b"",
+            "v4.public.eyJkYXRhIjoidGhpcyBpcyBhIHNpZ25lZCBtZXNzYWdlIiwiZXhwIjoiMjAyMi0wMS0wMVQwMDowMDowMCswMDowMCJ9bg_XBBzds8lTZShVlwwKSgeKpLT3yukTw6JUz3W4h_ExsQV-P0V54zemZDcAxFaSeef1QlXEFtkqxT1ciiQEDA",
+        ),
+        (
+            "4-S-2",
+            FOOTER,
+            b"",
+            "v4.public.eyJkYXRhIjoidGhpcyBpcyBhIHNpZ25lZCBtZXNzYWdlIiwiZXhwIjoiMjAyMi0wMS0wMVQwMDowMDowMCswMDowMCJ9v3Jt8mx_TdM2ceTGoqwrh4yDFn0XsHvvV_D0DtwQxVrJEBMl0F2caAdgnpKlt4p7xBnx1HcO-SPo8FPp214HDw.eyJraWQiOiJ6VmhNaVBCUDlmUmYyc25FY1Q3Z0ZUaW9lQTlDT2NOeTlEZmdMMVc2MGhhTiJ9",
+        ),
+        (
+            "4-S-3",
+            FOOTER,
+            br#"{"test-vector":"4-S-3"}"#,
+            "v4.public.eyJkYXRhIjoidGhpcyBpcyBhIHNpZ25lZCBtZXNzYWdlIiwiZXhwIjoiMjAyMi0wMS0wMVQwMDowMDowMCswMDowMCJ9NPWciuD3d0o5eXJXG5pJy-DiVEoyPYWs1YSTwWHNJq6DZD3je5gf-0M4JR9ipdUSJbIovzmBECeaWmaqcaP0DQ.eyJraWQiOiJ6VmhNaVBCUDlmUmYyc25FY1Q3Z0ZUaW9lQTlDT2NOeTlEZmdMMVc2MGhhTiJ9",
+        ),
+    ];
+
+    fn bytes32(value: &str) -> [u8; 32] {
+        hex::decode(value).unwrap().try_into().unwrap()
+    }
+
+    fn local() -> PasetoV4Local {
+        PasetoV4Local::new(bytes32(LOCAL_KEY))
+    }
+
+    fn public() -> PasetoV4Public {
+        PasetoV4Public::new(bytes32(PUBLIC_SEED))
+    }
+
+    // 翻转主体最后一个字节，即认证标签或签名的最后一个字节
+    fn tamper(token: &str, header: &str) -> String {
+        let rest = token.strip_prefix(header).unwrap();
+        let (body, footer) = rest.split_once('.').unwrap_or((rest, ""));
+        let mut body = URL_SAFE_NO_PAD.decode(body).unwrap();
+        *body.last_mut().unwrap() ^= 1;
+
+        let body = URL_SAFE_NO_PAD.encode(body);
+        if footer.is_empty() {
+            format!("{}{}", header, body)
+        } else {
+            format!("{}{}.{}", header, body, footer)
+        }
+    }
+
+    fn sample_claims() -> Claims {
+        Claims {
+            iss: "issuer".to_string(),
+            sub: "user-1".to_string(),
+            exp: 1_700_000_600,
+            iat: 1_700_000_000,
+            jti: Some("jti-1".to_string()),
+            sid: None,
+            scope: Some("documents:read".to_string()),
+            client_id: None,
+            sub_kind: None,
+            aud: Some("authenticated".to_string()),
+            act: None,
+            purpose: None,
+            amr: None,
+        }
+    }
+
+    #[test]
+    fn local_encrypts_official_vectors() {
+        for (name, nonce, message, footer, token) in LOCAL_VECTORS {
+            let encrypted = local().encrypt(message, &bytes32(nonce), footer, b"").unwrap();
+            assert_eq!(encrypted, *token, "{}", name);
+        }
+    }
+
+    #[test]
+    fn local_decrypts_official_vectors() {
+        for (name, _, message, footer, token) in LOCAL_VECTORS {
+            assert_eq!(local().decrypt(token, footer, b"").unwrap(), *message, "{}", name);
+        }
+    }
+
+    #[test]
+    fn public_key_matches_official_vector() {
+        assert_eq!(hex::encode(public().verifying_key.to_bytes()), PUBLIC_KEY);
+    }
+
+    #[test]
+    fn public_signs_official_vectors() {
+        for (name, footer, implicit, token) in PUBLIC_VECTORS {
+            assert_eq!(public().sign(SIGNED_MESSAGE, footer, implicit), *token, "{}", name);
+        }
+    }
+
+    #[test]
+    fn public_verifies_official_vectors() {
+        for (name, footer, implicit, token) in PUBLIC_VECTORS {
+            assert_eq!(public().verify(token, footer, implicit).unwrap(), SIGNED_MESSAGE, "{}", name);
+        }
+    }
+
+    #[test]
+    fn local_rejects_tampered_tag() {
+        let (_, _, _, footer, token) = LOCAL_VECTORS[3];
+        assert!(local().decrypt(&tamper(token, LOCAL_HEADER), footer, b"").is_err());
+    }
+
+    #[test]
+    fn public_rejects_tampered_signature() {
+        let (_, footer, implicit, token) = PUBLIC_VECTORS[2];
+        assert!(public().verify(&tamper(token, PUBLIC_HEADER), footer, implicit).is_err());
+    }
+
+    #[test]
+    fn rejects_wrong_footer() {
+        let (_, _, _, footer, token) = LOCAL_VECTORS[3];
+        assert!(local().decrypt(token, b"", b"").is_err());
+        assert!(local().decrypt(token, br#"{"kid":"other"}"#, b"").is_err());
+        assert!(local().decrypt(LOCAL_VECTORS[0].4, footer, b"").is_err());
+
+        let (_, footer, implicit, token) = PUBLIC_VECTORS[1];
+        assert!(public().verify(token, b"", implicit).is_err());
+        assert!(public().verify(PUBLIC_VECTORS[0].3, footer, implicit).is_err());
+
+        // 令牌中的页脚被替换时，签名同样无法通过
+        let replaced = format!(
+            "{}.{}",
+            token.rsplit_once('.').unwrap().0,
+            URL_SAFE_NO_PAD.encode(br#"{"kid":"other"}"#)
+        );
+        assert!(public().verify(&replaced, br#"{"kid":"other"}"#, implicit).is_err());
+    }
+
+    #[test]
+    fn rejects_wrong_implicit_assertion() {
+        let (_, footer, _, token) = PUBLIC_VECTORS[2];
+        assert!(public().verify(token, footer, b"").is_err());
+        assert!(public().verify(token, footer, br#"{"test-vector":"4-S-2"}"#).is_err());
+
+        let nonce = bytes32(LOCAL_NONCE);
+        let token = local().encrypt(SECRET_MESSAGE, &nonce, FOOTER, b"implicit").unwrap();
+        assert_eq!(local().decrypt(&token, FOOTER, b"implicit").unwrap(), SECRET_MESSAGE);
+        assert!(local().decrypt(&token, FOOTER, b"").is_err());
+        assert!(local().decrypt(&token, FOOTER, b"other").is_err());
+    }
+
+    #[test]
+    fn rejects_wrong_header() {
+        let (_, _, _, _, token) = LOCAL_VECTORS[0];
+        assert!(local().decrypt(&token.replacen("v4.local.", "v3.local.", 1), b"", b"").is_err());
+        assert!(local().decrypt(&token.replacen("v4.local.", "v4.public.", 1), b"", b"").is_err());
+
+        let (_, _, _, token) = PUBLIC_VECTORS[0];
+        assert!(public().verify(&token.replacen("v4.public.", "v3.public.", 1), b"", b"").is_err());
+        assert!(public().verify(&token.replacen("v4.public.", "v4.local.", 1), b"", b"").is_err());
+    }
+
+    #[test]
+    fn token_formats_round_trip_claims() {
+        let claims = sample_claims();
+
+        for format in [&local() as &dyn TokenFormat, &public()] {
+            let token = format.encode(&claims).unwrap();
+            assert!(format.matches(&token));
+
+            let decoded = format.decode(&token).unwrap();
+            assert_eq!(decoded.sub, claims.sub);
+            assert_eq!(decoded.exp, claims.exp);
+            assert_eq!(decoded.iat, claims.iat);
+            assert_eq!(decoded.scope, claims.scope);
+        }
+    }
+
+    #[test]
+    fn local_tokens_use_fresh_nonces() {
+        let claims = sample_claims();
+        assert_ne!(local().encode(&claims).unwrap(), local().encode(&claims).unwrap());
+    }
+}
@@ -1,17 +1,145 @@
 use argon2::{
     password_hash::{rand_core::OsRng, PasswordHash, PasswordHasher, PasswordVerifier, SaltString},
-    Argon2,
+    Algorithm, Argon2, KeyId, Params, ParamsBuilder, Version,
 };
+use base64::{engine::general_purpose::STANDARD, Engine};
+use hmac::{Hmac, Mac};
+use lazy_static::lazy_static;
+use std::time::Duration;
+use tokio::sync::{Semaphore, SemaphorePermit};
+use pbkdf2::{pbkdf2_hmac, Pbkdf2};
+use scrypt::Scrypt;
+use sha2::Sha256;
+
+use crate::config::CONFIG;
 use crate::errors::ServiceError;
+use crate::utils::crypto::constant_time_eq;
+
+/// 服务端 pepper，哈希中以 Argon2 的 keyid 参数记录所用密钥
+struct Pepper {
+    key_id: String,
+    secret: Vec<u8>,
+}
+
+/// 当前的密码哈希策略
+struct HashPolicy {
+    algorithm: Algorithm,
+    // 含当前 pepper 的 keyid
+    params: Params,
+    // 第一个为当前 pepper
+    peppers: Vec<Pepper>,
+}
+
+fn parse_pepper(value: &str) -> Pepper {
+    let (key_id, secret) = value
+        .split_once(':')
+        .unwrap_or_else(|| panic!("PASSWORD_PEPPERS 格式应为 <key id>:<base64密钥>"));
+
+    assert!(
+        !key_id.is_empty() && key_id.len() <= KeyId::MAX_LEN,
+        "pepper key id 长度必须为1到{}字节",
+        KeyId::MAX_LEN
+    );
+
+    let secret = STANDARD
+        .decode(secret)
+        .unwrap_or_else(|_| panic!("pepper {} 必须是有效的base64", key_id));
+    assert!(secret.len() >= 16, "pepper {} 至少需要16字节", key_id);
+
+    Pepper {
+        key_id: key_id.to_string(),
+        secret,
+    }
+}
+
+lazy_static! {
+    static ref POLICY: HashPolicy = {
+        let algorithm = match CONFIG.argon2_algorithm.to_ascii_lowercase().as_str() {
+            "argon2id" => Algorithm::Argon2id,
+            "argon2i" => Algorithm::Argon2i,
+            "argon2d" => Algorithm::Argon2d,
+            other => panic!("未知的Argon2算法: {}", other),
+        };
+
+        let peppers: Vec<Pepper> = CONFIG.password_peppers.iter().map(|value| parse_pepper(value)).collect();
+
+        let mut builder = ParamsBuilder::new();
+        builder
+            .m_cost(CONFIG.argon2_memory_kib)
+            .t_cost(CONFIG.argon2_iterations)
+            .p_cost(CONFIG.argon2_parallelism);
+        if let Some(current) = peppers.first() {
+            let key_id = KeyId::new(current.key_id.as_bytes())
+                .unwrap_or_else(|e| panic!("pepper key id 无效: {}", e));
+            builder.keyid(key_id);
+        }
+        let params = builder
+            .build()
+            .unwrap_or_else(|e| panic!("Argon2参数无效: {}", e));
+
+        HashPolicy { algorithm, params, peppers }
+    };
+}
+
+lazy_static! {
+    // 密码哈希并发名额，防止登录洪峰占满阻塞线程池、拖垮其他端点
+    static ref HASH_PERMITS: Semaphore = Semaphore::new(CONFIG.password_hash_concurrency.max(1));
+}
+
+/// 提前加载哈希策略和并发名额，Argon2 参数或 pepper 配置无效时启动即失败
+pub fn init() {
+    lazy_static::initialize(&POLICY);
+    lazy_static::initialize(&HASH_PERMITS);
+}
+
+/// 获取一个密码哈希名额，在执行哈希或验证密码前调用并持有到操作结束
+///
+/// 名额耗尽时排队等待，超过 PASSWORD_HASH_QUEUE_TIMEOUT_MS 仍未获得则返回
+/// `ServiceUnavailable`，让过载请求快速失败而不是无限堆积。
+pub async fn acquire_hash_permit() -> Result<SemaphorePermit<'static>, ServiceError> {
+    let timeout = Duration::from_millis(CONFIG.password_hash_queue_timeout_ms);
+
+    match tokio::time::timeout(timeout, HASH_PERMITS.acquire()).await {
+        Ok(Ok(permit)) => Ok(permit),
+        Ok(Err(_)) => Err(ServiceError::InternalServerError),
+        Err(_) => Err(ServiceError::ServiceUnavailable("Too many concurrent password operations".to_string())),
+    }
+}
+
+fn hasher() -> Argon2<'static> {
+    Argon2::new(POLICY.algorithm, Version::V0x13, POLICY.params.clone())
+}
+
+// 以 HMAC-SHA256(pepper, 密码) 作为 Argon2 的输入；未使用 pepper 时为原始密码
+fn peppered_input(password: &str, pepper: Option<&Pepper>) -> Vec<u8> {
+    match pepper {
+        Some(pepper) => {
+            let mut mac = <Hmac<Sha256>>::new_from_slice(&pepper.secret)
+                .expect("HMAC接受任意长度的密钥");
+            mac.update(password.as_bytes());
+            mac.finalize().into_bytes().to_vec()
+        }
+        None => password.as_bytes().to_vec(),
+    }
+}
+
+// 哈希中记录的 pepper key id，没有时为空
+fn hash_key_id(parsed_hash: &PasswordHash) -> Result<String, ServiceError> {
+    let params = Params::try_from(parsed_hash)
+        .map_err(|e| ServiceError::PasswordHashError(e.to_string()))?;
+
+    Ok(String::from_utf8_lossy(params.keyid()).into_owned())
+}
 
-/// 使用Argon2哈希密码
+/// 使用Argon2哈希密码，配置了 pepper 时先与当前 pepper 做 HMAC
 pub fn hash_password(password: &str) -> Result<String, ServiceError> {
-    let argon2 = Argon2::default();
+    let argon2 = hasher();
     let salt = SaltString::generate(&mut OsRng);
+    let input = peppered_input(password, POLICY.peppers.first());
 
     // 哈希密码
     let password_hash = argon2
-        .hash_password(password.as_bytes(), &salt)
+        .hash_password(&input, &salt)
         .map_err(|e| ServiceError::PasswordHashError(e.to_string()))?
         .to_string();
 
@@ -19,11 +147,189 @@ pub fn hash_password(password: &str) -> Result<String, ServiceError> {
 }
 
 /// 验证密码
+///
+/// 按哈希前缀选择算法，算法和参数取自哈希本身，旧参数生成的哈希仍可验证。
+/// 除 Argon2 外，还支持从 Supabase 及旧系统导入的哈希：
+/// - bcrypt（`$2a$` / `$2b$` / `$2y$`）
+/// - scrypt（PHC 格式 `$scrypt$`）
+/// - PBKDF2（PHC 格式 `$pbkdf2-sha256$` 等，以及 Django 格式 `pbkdf2_sha256$`）
+///
+/// 这些哈希在下次登录成功后会被升级为 Argon2，见 [`needs_rehash`]。
 pub fn verify_password(password: &str, password_hash: &str) -> Result<bool, ServiceError> {
+    if password_hash.starts_with("$2a$")
+        || password_hash.starts_with("$2b$")
+        || password_hash.starts_with("$2y$")
+    {
+        return bcrypt::verify(password, password_hash)
+            .map_err(|e| ServiceError::PasswordHashError(e.to_string()));
+    }
+
+    if let Some(encoded) = password_hash.strip_prefix("pbkdf2_sha256$") {
+        return verify_django_pbkdf2(password, encoded);
+    }
+
     let parsed_hash = PasswordHash::new(password_hash)
         .map_err(|e| ServiceError::PasswordHashError(e.to_string()))?;
 
-    let result = Argon2::default().verify_password(password.as_bytes(), &parsed_hash);
+    let result = match parsed_hash.algorithm.as_str() {
+        "argon2id" | "argon2i" | "argon2d" => {
+            let key_id = hash_key_id(&parsed_hash)?;
+            let pepper = if key_id.is_empty() {
+                None
+            } else {
+                // 已下线的 pepper 无法再验证，只能重置密码
+                let pepper = POLICY
+                    .peppers
+                    .iter()
+                    .find(|pepper| pepper.key_id == key_id)
+                    .ok_or_else(|| ServiceError::PasswordHashError(format!("未知的pepper: {}", key_id)))?;
+                Some(pepper)
+            };
+
+            Argon2::default().verify_password(&peppered_input(password, pepper), &parsed_hash)
+        }
+        "scrypt" => Scrypt.verify_password(password.as_bytes(), &parsed_hash),
+        "pbkdf2" | "pbkdf2-sha256" | "pbkdf2-sha512" => {
+            Pbkdf2.verify_password(password.as_bytes(), &parsed_hash)
+        }
+        other => {
+            return Err(ServiceError::PasswordHashError(format!("不支持的哈希算法: {}", other)));
+        }
+    };
     
     Ok(result.is_ok())
 }
+
+// Django 格式: pbkdf2_sha256$<迭代次数>$<盐>$<base64摘要>
+fn verify_django_pbkdf2(password: &str, encoded: &str) -> Result<bool, ServiceError> {
+    let invalid = || ServiceError::PasswordHashError("无效的PBKDF2哈希".to_string());
+
+    let mut parts = encoded.splitn(3, '$');
+    let iterations: u32 = parts
+        .next()
+        .and_then(|v| v.parse().ok())
+        .filter(|iterations| *iterations > 0)
+        .ok_or_else(invalid)?;
+    let salt = parts.next().ok_or_else(invalid)?;
+    let expected = parts.next().ok_or_else(invalid)?;
+
+    let expected_len = STANDARD.decode(expected).map_err(|_| invalid())?.len();
+    if expected_len == 0 {
+        return Err(invalid());
+    }
+
+    let mut derived = vec![0u8; expected_len];
+    pbkdf2_hmac::<Sha256>(password.as_bytes(), salt.as_bytes(), iterations, &mut derived);
+
+    Ok(constant_time_eq(&STANDARD.encode(derived), expected))
+}
+
+/// 判断哈希是否弱于当前策略（算法不同、版本较旧或任一成本参数更低），需要重新哈希
+///
+/// 导入的 bcrypt、scrypt、PBKDF2 哈希总是需要重新哈希；
+/// pepper 轮换后，使用旧 pepper（或未使用 pepper）的哈希同样需要重新哈希。
+pub fn needs_rehash(password_hash: &str) -> bool {
+    let parsed_hash = match PasswordHash::new(password_hash) {
+        Ok(parsed_hash) => parsed_hash,
+        Err(_) => return true,
+    };
+
+    // 非 Argon2 的导入哈希无需比较参数
+    match Algorithm::try_from(parsed_hash.algorithm) {
+        Ok(algorithm) if algorithm == POLICY.algorithm => {}
+        _ => return true,
+    }
+
+    if parsed_hash.version != Some(Version::V0x13.into()) {
+        return true;
+    }
+
+    match Params::try_from(&parsed_hash) {
+        Ok(params) => {
+            params.keyid() != POLICY.params.keyid()
+                || params.m_cost() < POLICY.params.m_cost()
+                || params.t_cost() < POLICY.params.t_cost()
+                || params.p_cost() < POLICY.params.p_cost()
+        }
+        Err(_) => true,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    // pyca/bcrypt 测试向量
+    const BCRYPT_HASH: &str = "$2b$04$cVWp4XaNU8a4v1uMRum2SO026BWLIoQMD/TXg5uZV.0P.uO8m3YEm";
+    const BCRYPT_PASSWORD: &str = "Kk4DQuMMfZL9o";
+
+    // RFC 7914 第12节：scrypt("password", "NaCl", N=1024, r=8, p=16)
+    const SCRYPT_HASH: &str = "$scrypt$ln=10,r=8,p=16$TmFDbA$/bq+HJ00cgB4VucZDQHp/nxq18vII3gw53N2Y0s3MWIurzDZLiKjiG/xCSedmDDaxyevuUqD7m2DYMvfoswGQA";
+
+    // PBKDF2-HMAC-SHA256("password", "salt", 4096)
+    const PBKDF2_HASH: &str = "$pbkdf2-sha256$i=4096,l=32$c2FsdA$xeR41ZKIyEGqUw22hFxMjZYok6ABzk4RpJY4c6qYE0o";
+
+    // Django: PBKDF2-HMAC-SHA256("correct horse", "seasalt42", 1000)
+    const DJANGO_HASH: &str = "pbkdf2_sha256$1000$seasalt42$DLjCn+VwiEMemQXgu8JpFEayHo19oxLeCyxZCF+tuek=";
+
+    #[test]
+    fn bcrypt_known_answer() {
+        assert!(verify_password(BCRYPT_PASSWORD, BCRYPT_HASH).unwrap());
+        assert!(!verify_password("wrong password", BCRYPT_HASH).unwrap());
+        assert!(needs_rehash(BCRYPT_HASH));
+    }
+
+    #[test]
+    fn bcrypt_accepts_2a_and_2y_prefixes() {
+        let hash_2a = BCRYPT_HASH.replacen("$2b$", "$2a$", 1);
+        let hash_2y = BCRYPT_HASH.replacen("$2b$", "$2y$", 1);
+        assert!(verify_password(BCRYPT_PASSWORD, &hash_2a).unwrap());
+        assert!(verify_password(BCRYPT_PASSWORD, &hash_2y).unwrap());
+    }
+
+    #[test]
+    fn scrypt_known_answer() {
+        assert!(verify_password("password", SCRYPT_HASH).unwrap());
+        assert!(!verify_password("passwore", SCRYPT_HASH).unwrap());
+        assert!(needs_rehash(SCRYPT_HASH));
+    }
+
+    #[test]
+    fn pbkdf2_known_answer() {
+        assert!(verify_password("password", PBKDF2_HASH).unwrap());
+        assert!(!verify_password("Password", PBKDF2_HASH).unwrap());
+        assert!(needs_rehash(PBKDF2_HASH));
+    }
+
+    #[test]
+    fn django_pbkdf2_known_answer() {
+        assert!(verify_password("correct horse", DJANGO_HASH).unwrap());
+        assert!(!verify_password("correct horse ", DJANGO_HASH).unwrap());
+        assert!(needs_rehash(DJANGO_HASH));
+    }
+
+    #[test]
+    fn malformed_hashes_are_errors() {
+        assert!(verify_password("password", "$2b$04$truncated").is_err());
+        assert!(verify_password("password", "$scrypt$ln=10,r=8,p=16$TmFDbA$not base64!").is_err());
+        assert!(verify_password("password", "$md5$salt$aGFzaA").is_err());
+        assert!(verify_password("password", "not a hash").is_err());
+        assert!(verify_password("password", "pbkdf2_sha256$abc$salt$DLjCn+Vw").is_err());
+        assert!(verify_password("password", "pbkdf2_sha256$0$salt$DLjCn+Vw").is_err());
+        assert!(verify_password("password", "pbkdf2_sha256$1000$salt").is_err());
+        assert!(verify_password("password", "pbkdf2_sha256$1000$salt$not base64!").is_err());
+        assert!(verify_password("password", "pbkdf2_sha256$1000$salt$").is_err());
+    }
+
+    #[test]
+    fn hash_without_digest_never_verifies() {
+        assert!(!verify_password("password", "$scrypt$ln=10,r=8,p=16$TmFDbA").unwrap_or(false));
+        assert!(!verify_password("password", "$pbkdf2-sha256$i=4096,l=32$c2FsdA").unwrap_or(false));
+    }
+
+    #[test]
+    fn unparseable_hashes_need_rehash() {
+        assert!(needs_rehash("not a hash"));
+        assert!(needs_rehash(""));
+    }
+}
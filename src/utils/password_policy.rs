@@ -0,0 +1,404 @@
+use lazy_static::lazy_static;
+use std::path::Path;
+
+use crate::config::CONFIG;
+use crate::errors::{FieldError, ServiceError};
+use crate::utils::breach_filter::BreachFilter;
+
+/// 校验密码时可用的用户信息，用于禁止密码包含个人信息
+pub struct PasswordContext<'a> {
+    pub email: &'a str,
+    pub full_name: Option<&'a str>,
+}
+
+/// 密码规则，违反时返回错误码和说明
+pub trait PasswordRule: Send + Sync {
+    fn check(&self, password: &str, context: &PasswordContext) -> Option<(&'static str, String)>;
+}
+
+struct LengthRule {
+    min: usize,
+    max: usize,
+}
+
+impl PasswordRule for LengthRule {
+    fn check(&self, password: &str, _: &PasswordContext) -> Option<(&'static str, String)> {
+        let length = password.chars().count();
+        if length < self.min {
+            return Some(("too_short", format!("密码长度不能少于{}个字符", self.min)));
+        }
+        if length > self.max {
+            return Some(("too_long", format!("密码长度不能超过{}个字符", self.max)));
+        }
+        None
+    }
+}
+
+struct CharacterClassRule {
+    code: &'static str,
+    message: &'static str,
+    matches: fn(char) -> bool,
+}
+
+impl CharacterClassRule {
+    fn lowercase() -> Self {
+        CharacterClassRule {
+            code: "missing_lowercase",
+            message: "密码必须包含小写字母",
+            matches: |c| c.is_lowercase(),
+        }
+    }
+
+    fn uppercase() -> Self {
+        CharacterClassRule {
+            code: "missing_uppercase",
+            message: "密码必须包含大写字母",
+            matches: |c| c.is_uppercase(),
+        }
+    }
+
+    fn digit() -> Self {
+        CharacterClassRule {
+            code: "missing_digit",
+            message: "密码必须包含数字",
+            matches: |c| c.is_ascii_digit(),
+        }
+    }
+
+    fn symbol() -> Self {
+        CharacterClassRule {
+            code: "missing_symbol",
+            message: "密码必须包含符号",
+            matches: |c| !c.is_alphanumeric() && !c.is_whitespace(),
+        }
+    }
+}
+
+impl PasswordRule for CharacterClassRule {
+    fn check(&self, password: &str, _: &PasswordContext) -> Option<(&'static str, String)> {
+        if password.chars().any(self.matches) {
+            None
+        } else {
+            Some((self.code, self.message.to_string()))
+        }
+    }
+}
+
+struct PersonalInfoRule;
+
+impl PasswordRule for PersonalInfoRule {
+    fn check(&self, password: &str, context: &PasswordContext) -> Option<(&'static str, String)> {
+        let password = password.to_lowercase();
+        let email = context.email.to_lowercase();
+        let local_part = email.split('@').next().unwrap_or_default();
+
+        let mut tokens = vec![email.as_str(), local_part];
+        let name = context.full_name.map(str::to_lowercase).unwrap_or_default();
+        tokens.extend(name.split_whitespace());
+
+        // 过短的片段（如姓氏缩写）容易误伤，不参与比较
+        let contains_personal_info = tokens
+            .into_iter()
+            .filter(|token| token.chars().count() >= 3)
+            .any(|token| password.contains(token));
+
+        if contains_personal_info {
+            Some(("contains_personal_info", "密码不能包含邮箱或姓名".to_string()))
+        } else {
+            None
+        }
+    }
+}
+
+struct StrengthRule {
+    min_score: u8,
+}
+
+impl PasswordRule for StrengthRule {
+    fn check(&self, password: &str, _: &PasswordContext) -> Option<(&'static str, String)> {
+        if strength_score(password) < self.min_score {
+            Some(("too_weak", "密码强度不足，请使用更长或更不易猜测的密码".to_string()))
+        } else {
+            None
+        }
+    }
+}
+
+struct BreachedPasswordRule {
+    filter: BreachFilter,
+}
+
+impl PasswordRule for BreachedPasswordRule {
+    fn check(&self, password: &str, _: &PasswordContext) -> Option<(&'static str, String)> {
+        if self.filter.contains_password(password) {
+            Some(("breached", "该密码出现在已知的数据泄露中，请更换".to_string()))
+        } else {
+            None
+        }
+    }
+}
+
+// 常见弱密码，命中即视为强度 0
+const COMMON_PASSWORDS: &[&str] = &[
+    "password", "123456", "12345678", "123456789", "1234567890", "qwerty", "qwertyuiop", "abc123",
+    "111111", "123123", "admin", "letmein", "welcome", "monkey", "dragon", "iloveyou", "sunshine",
+    "princess", "football", "baseball", "master", "shadow", "superman", "trustno1", "passw0rd",
+    "password1", "qwerty123", "1q2w3e4r", "zaq12wsx", "asdfghjkl", "000000", "654321", "666666",
+];
+
+const KEYBOARD_ROWS: &[&str] = &["qwertyuiop", "asdfghjkl", "zxcvbnm", "1234567890"];
+
+// 字符是否与前一个字符构成重复、连续序列或键盘相邻
+fn is_predictable(previous: char, current: char) -> bool {
+    if previous == current {
+        return true;
+    }
+
+    let delta = current as i64 - previous as i64;
+    if delta.abs() == 1 && previous.is_ascii_alphanumeric() && current.is_ascii_alphanumeric() {
+        return true;
+    }
+
+    KEYBOARD_ROWS.iter().any(|row| {
+        let mut pair = String::new();
+        pair.push(previous);
+        pair.push(current);
+        row.contains(&pair) || row.contains(&pair.chars().rev().collect::<String>())
+    })
+}
+
+/// 估算密码强度，0（极弱）到 4（很强），分档与 zxcvbn 一致
+///
+/// 以字符集大小估算每个字符的熵，重复、连续序列和键盘相邻的字符只计少量熵，
+/// 常见密码（忽略大小写和末尾数字）直接记为 0。
+pub fn strength_score(password: &str) -> u8 {
+    let lowered = password.to_lowercase();
+    let stripped = lowered.trim_end_matches(|c: char| c.is_ascii_digit() || c == '!');
+    if COMMON_PASSWORDS.contains(&lowered.as_str()) || COMMON_PASSWORDS.contains(&stripped) {
+        return 0;
+    }
+
+    let mut charset = 0u32;
+    if password.chars().any(|c| c.is_ascii_lowercase()) {
+        charset += 26;
+    }
+    if password.chars().any(|c| c.is_ascii_uppercase()) {
+        charset += 26;
+    }
+    if password.chars().any(|c| c.is_ascii_digit()) {
+        charset += 10;
+    }
+    if password.chars().any(|c| c.is_ascii_punctuation() || c == ' ') {
+        charset += 33;
+    }
+    if !password.is_ascii() {
+        charset += 100;
+    }
+    if charset == 0 {
+        return 0;
+    }
+
+    let bits_per_char = (charset as f64).log2();
+    let mut bits = 0.0;
+    let mut previous: Option<char> = None;
+    for current in lowered.chars() {
+        bits += match previous {
+            Some(previous) if is_predictable(previous, current) => 1.0,
+            _ => bits_per_char,
+        };
+        previous = Some(current);
+    }
+
+    // 猜测次数的数量级：10^3、10^6、10^8、10^10
+    let guesses_log10 = bits * std::f64::consts::LOG10_2;
+    match guesses_log10 {
+        g if g < 3.0 => 0,
+        g if g < 6.0 => 1,
+        g if g < 8.0 => 2,
+        g if g < 10.0 => 3,
+        _ => 4,
+    }
+}
+
+/// 密码策略，由一组规则组成
+pub struct PasswordPolicy {
+    rules: Vec<Box<dyn PasswordRule>>,
+}
+
+lazy_static! {
+    pub static ref PASSWORD_POLICY: PasswordPolicy = PasswordPolicy::from_config();
+}
+
+impl PasswordPolicy {
+    fn from_config() -> Self {
+        let mut rules: Vec<Box<dyn PasswordRule>> = vec![Box::new(LengthRule {
+            min: CONFIG.password_min_length,
+            max: CONFIG.password_max_length,
+        })];
+
+        if CONFIG.password_require_lowercase {
+            rules.push(Box::new(CharacterClassRule::lowercase()));
+        }
+        if CONFIG.password_require_uppercase {
+            rules.push(Box::new(CharacterClassRule::uppercase()));
+        }
+        if CONFIG.password_require_digit {
+            rules.push(Box::new(CharacterClassRule::digit()));
+        }
+        if CONFIG.password_require_symbol {
+            rules.push(Box::new(CharacterClassRule::symbol()));
+        }
+        if CONFIG.password_forbid_personal_info {
+            rules.push(Box::new(PersonalInfoRule));
+        }
+        if CONFIG.password_min_strength > 0 {
+            rules.push(Box::new(StrengthRule {
+                min_score: CONFIG.password_min_strength,
+            }));
+        }
+
+        if let Some(path) = &CONFIG.breached_password_filter {
+            let filter = BreachFilter::load(Path::new(path))
+                .unwrap_or_else(|e| panic!("泄露密码过滤器加载失败 {}: {}", path, e));
+            rules.push(Box::new(BreachedPasswordRule { filter }));
+        }
+
+        PasswordPolicy { rules }
+    }
+
+    /// 按全部规则校验密码，违反的规则逐条作为 `field` 字段的错误返回
+    pub fn validate(
+        &self,
+        field: &str,
+        password: &str,
+        context: &PasswordContext,
+    ) -> Result<(), ServiceError> {
+        let errors: Vec<FieldError> = self
+            .rules
+            .iter()
+            .filter_map(|rule| rule.check(password, context))
+            .map(|(code, message)| FieldError {
+                field: field.to_string(),
+                code: code.to_string(),
+                message,
+            })
+            .collect();
+
+        if errors.is_empty() {
+            Ok(())
+        } else {
+            Err(ServiceError::ValidationError(errors))
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const CONTEXT: PasswordContext = PasswordContext {
+        email: "Alice.Smith@example.com",
+        full_name: Some("Alice Wonder Li"),
+    };
+
+    fn code(rule: &dyn PasswordRule, password: &str) -> Option<&'static str> {
+        rule.check(password, &CONTEXT).map(|(code, _)| code)
+    }
+
+    #[test]
+    fn length_rule_counts_characters_not_bytes() {
+        let rule = LengthRule { min: 8, max: 12 };
+        assert_eq!(code(&rule, "short"), Some("too_short"));
+        assert_eq!(code(&rule, "exactly8"), None);
+        assert_eq!(code(&rule, "twelve chars"), None);
+        assert_eq!(code(&rule, "thirteen char"), Some("too_long"));
+        assert_eq!(code(&rule, "密码密码密码密码"), None);
+        assert_eq!(code(&rule, "密码密码"), Some("too_short"));
+    }
+
+    #[test]
+    fn character_class_rules_require_their_class() {
+        assert_eq!(code(&CharacterClassRule::lowercase(), "ABC123!"), Some("missing_lowercase"));
+        assert_eq!(code(&CharacterClassRule::lowercase(), "aBC123!"), None);
+        assert_eq!(code(&CharacterClassRule::uppercase(), "abc123!"), Some("missing_uppercase"));
+        assert_eq!(code(&CharacterClassRule::uppercase(), "abC123!"), None);
+        assert_eq!(code(&CharacterClassRule::digit(), "abcDEF!"), Some("missing_digit"));
+        assert_eq!(code(&CharacterClassRule::digit(), "abcDEF1"), None);
+        assert_eq!(code(&CharacterClassRule::symbol(), "abc DEF 123"), Some("missing_symbol"));
+        assert_eq!(code(&CharacterClassRule::symbol(), "abcDEF-123"), None);
+    }
+
+    #[test]
+    fn personal_info_rule_rejects_email_and_name_parts() {
+        let rule = PersonalInfoRule;
+        assert_eq!(code(&rule, "xx-alice.smith-xx"), Some("contains_personal_info"));
+        assert_eq!(code(&rule, "my ALICE.SMITH@EXAMPLE.COM"), Some("contains_personal_info"));
+        assert_eq!(code(&rule, "wonderland42"), Some("contains_personal_info"));
+        assert_eq!(code(&rule, "purple-elephant-7"), None);
+    }
+
+    #[test]
+    fn personal_info_rule_ignores_short_fragments() {
+        // 姓氏 "Li" 不足3个字符，不参与比较
+        assert_eq!(code(&PersonalInfoRule, "limelight-42"), None);
+
+        let context = PasswordContext {
+            email: "al@example.com",
+            full_name: None,
+        };
+        assert!(PersonalInfoRule.check("always-al", &context).is_none());
+    }
+
+    #[test]
+    fn common_passwords_score_zero() {
+        assert_eq!(strength_score("password"), 0);
+        assert_eq!(strength_score("Password123"), 0);
+        assert_eq!(strength_score("QWERTY!"), 0);
+        assert_eq!(strength_score("letmein2024!"), 0);
+        assert_eq!(strength_score(""), 0);
+    }
+
+    #[test]
+    fn predictable_sequences_score_low() {
+        assert!(strength_score("aaaaaaaaaaaa") <= 1);
+        assert!(strength_score("abcdefghijkl") <= 1);
+        assert!(strength_score("asdfghjkl;") <= 1);
+    }
+
+    #[test]
+    fn long_varied_passwords_score_high() {
+        assert_eq!(strength_score("correct horse battery staple"), 4);
+        assert_eq!(strength_score("Tr0ub4dor&3"), 4);
+        assert!(strength_score("purple-elephant-7") >= 3);
+    }
+
+    #[test]
+    fn strength_rule_compares_against_min_score() {
+        let rule = StrengthRule { min_score: 3 };
+        assert_eq!(code(&rule, "password1"), Some("too_weak"));
+        assert_eq!(code(&rule, "correct horse battery staple"), None);
+    }
+
+    #[test]
+    fn policy_reports_every_violation_for_the_field() {
+        let policy = PasswordPolicy {
+            rules: vec![
+                Box::new(LengthRule { min: 12, max: 64 }),
+                Box::new(CharacterClassRule::uppercase()),
+                Box::new(CharacterClassRule::digit()),
+                Box::new(PersonalInfoRule),
+            ],
+        };
+
+        match policy.validate("new_password", "alice", &CONTEXT) {
+            Err(ServiceError::ValidationError(errors)) => {
+                let codes: Vec<&str> = errors.iter().map(|e| e.code.as_str()).collect();
+                assert_eq!(codes, ["too_short", "missing_uppercase", "missing_digit", "contains_personal_info"]);
+                assert!(errors.iter().all(|e| e.field == "new_password"));
+            }
+            other => panic!("unexpected result: {:?}", other.err()),
+        }
+
+        assert!(policy.validate("new_password", "Purple-Elephant-7", &CONTEXT).is_ok());
+    }
+}
@@ -0,0 +1,494 @@
+use chrono::{Duration as ChronoDuration, Utc};
+use diesel::pg::PgConnection;
+use diesel::prelude::*;
+use lazy_static::lazy_static;
+use std::collections::{HashMap, HashSet};
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::RwLock;
+use std::thread;
+use std::time::{Duration, Instant};
+use uuid::Uuid;
+
+use crate::auth::models::PrincipalKind;
+use crate::config::CONFIG;
+use crate::errors::ServiceError;
+use crate::utils::jwt::Claims;
+
+const CHANNEL: &str = "auth_revocations";
+// 轮询通知的间隔，决定吊销在其他实例生效的延迟
+const POLL_INTERVAL: Duration = Duration::from_millis(250);
+// 空闲时探测连接是否仍然可用，并顺带清理过期条目
+const HEARTBEAT_INTERVAL: Duration = Duration::from_secs(30);
+const MAX_RECONNECT_DELAY: Duration = Duration::from_secs(30);
+
+/// 令牌是否签发于有效起点之前（含同一秒，宁可多拒也不漏放）
+pub fn issued_before(claims: &Claims, valid_after: i64) -> bool {
+    claims.iat <= valid_after
+}
+
+#[derive(Default)]
+struct RevocationState {
+    // 已吊销的访问令牌 jti -> 令牌过期时间
+    tokens: HashMap<Uuid, i64>,
+    // 已吊销的会话 -> 会话过期时间
+    sessions: HashMap<Uuid, i64>,
+    // 已停用的服务客户端
+    clients: HashSet<Uuid>,
+    // 全局令牌有效起点
+    global_valid_after: Option<i64>,
+    // 用户级令牌有效起点
+    users_valid_after: HashMap<Uuid, i64>,
+}
+
+impl RevocationState {
+    fn prune(&mut self) {
+        let now = Utc::now().timestamp();
+        let leeway = CONFIG.jwt_leeway_secs as i64;
+        // 会话过期后，其最后签发的访问令牌仍可能存活一个访问令牌有效期
+        let session_grace = CONFIG.access_token_ttl_secs + leeway;
+
+        self.tokens.retain(|_, expires_at| *expires_at + leeway > now);
+        self.sessions.retain(|_, expires_at| *expires_at + session_grace > now);
+        // 起点之前签发的令牌都已自然过期后，起点不再有意义
+        self.users_valid_after
+            .retain(|_, valid_after| *valid_after + CONFIG.access_token_ttl_secs + leeway > now);
+    }
+
+    // 已删除的会话和客户端不在各自表中，按墓碑记录视同已吊销
+    fn add_tombstone(&mut self, kind: &str, id: Uuid, valid_until: i64) {
+        match kind {
+            "session" => {
+                self.sessions.insert(id, valid_until);
+            }
+            "client" => {
+                self.clients.insert(id);
+            }
+            _ => log::warn!("未知的墓碑类型: {}", kind),
+        }
+    }
+}
+
+/// 本实例内存中的吊销集合
+///
+/// 由后台线程通过 Postgres `LISTEN/NOTIFY` 保持与数据库同步，
+/// 连接中断期间标记为未同步，此时调用方应回退到数据库查询。
+pub struct RevocationCache {
+    state: RwLock<RevocationState>,
+    synced: AtomicBool,
+}
+
+lazy_static! {
+    pub static ref REVOCATIONS: RevocationCache = RevocationCache {
+        state: RwLock::new(RevocationState::default()),
+        synced: AtomicBool::new(false),
+    };
+}
+
+impl RevocationCache {
+    /// 判断令牌是否已被吊销；缓存未同步时返回 None
+    pub fn is_revoked(&self, claims: &Claims) -> Option<bool> {
+        if !self.synced.load(Ordering::Acquire) {
+            return None;
+        }
+
+        let state = self.state.read().ok()?;
+
+        if let Some(jti) = claims.jti.as_deref() {
+            match Uuid::parse_str(jti) {
+                Ok(jti) if state.tokens.contains_key(&jti) => return Some(true),
+                Ok(_) => {}
+                Err(_) => return Some(true),
+            }
+        }
+
+        if let Some(sid) = claims.sid.as_deref() {
+            match Uuid::parse_str(sid) {
+                Ok(sid) if state.sessions.contains_key(&sid) => return Some(true),
+                Ok(_) => {}
+                Err(_) => return Some(true),
+            }
+        }
+
+        if state.global_valid_after.is_some_and(|valid_after| issued_before(claims, valid_after)) {
+            return Some(true);
+        }
+
+        if claims.sub_kind == Some(PrincipalKind::Client) {
+            match Uuid::parse_str(&claims.sub) {
+                Ok(client_id) if state.clients.contains(&client_id) => return Some(true),
+                Ok(_) => {}
+                Err(_) => return Some(true),
+            }
+        } else if let Ok(user_id) = Uuid::parse_str(&claims.sub) {
+            let valid_after = state.users_valid_after.get(&user_id);
+            if valid_after.is_some_and(|valid_after| issued_before(claims, *valid_after)) {
+                return Some(true);
+            }
+        }
+
+        Some(false)
+    }
+
+    fn replace(&self, mut snapshot: RevocationState) {
+        snapshot.prune();
+        if let Ok(mut state) = self.state.write() {
+            *state = snapshot;
+            self.synced.store(true, Ordering::Release);
+        }
+    }
+
+    fn mark_stale(&self) {
+        self.synced.store(false, Ordering::Release);
+    }
+
+    fn prune(&self) {
+        if let Ok(mut state) = self.state.write() {
+            state.prune();
+        }
+    }
+
+    // 应用一条通知，负载格式: <kind>:<id>[:<expires_at_epoch>] 或 global:<valid_after_epoch>
+    fn apply(&self, payload: &str) {
+        let mut parts = payload.split(':');
+        let kind = parts.next().unwrap_or_default();
+
+        if kind == "global" {
+            match parts.next().and_then(|v| v.parse::<i64>().ok()) {
+                Some(valid_after) => {
+                    if let Ok(mut state) = self.state.write() {
+                        state.global_valid_after = Some(valid_after);
+                    }
+                }
+                None => log::warn!("无法解析吊销通知: {}", payload),
+            }
+            return;
+        }
+
+        let id = match parts.next().and_then(|v| Uuid::parse_str(v).ok()) {
+            Some(id) => id,
+            None => {
+                log::warn!("无法解析吊销通知: {}", payload);
+                return;
+            }
+        };
+        // 客户端通知不带过期时间，只有需要时才取默认值
+        let expires_at = parts.next().and_then(|v| v.parse::<i64>().ok());
+        let expires_at = || expires_at.unwrap_or_else(|| Utc::now().timestamp() + CONFIG.refresh_token_ttl_secs);
+
+        let mut state = match self.state.write() {
+            Ok(state) => state,
+            Err(_) => return,
+        };
+
+        match kind {
+            "jti" => {
+                state.tokens.insert(id, expires_at());
+            }
+            "session" => {
+                state.sessions.insert(id, expires_at());
+            }
+            "client" => {
+                state.clients.insert(id);
+            }
+            "client_restored" => {
+                state.clients.remove(&id);
+            }
+            "user" => {
+                state.users_valid_after.insert(id, expires_at());
+            }
+            _ => log::warn!("未知的吊销通知类型: {}", payload),
+        }
+    }
+}
+
+// 从数据库加载完整的吊销快照
+fn load_snapshot(conn: &mut PgConnection) -> Result<RevocationState, ServiceError> {
+    use crate::db::schema::{clients, revocation_tombstones, revoked_tokens, sessions, token_epochs, users};
+
+    let now = Utc::now();
+    let leeway = ChronoDuration::seconds(CONFIG.jwt_leeway_secs as i64);
+    let session_grace = ChronoDuration::seconds(CONFIG.access_token_ttl_secs) + leeway;
+
+    let tokens = revoked_tokens::table
+        .filter(revoked_tokens::expires_at.gt(now - leeway))
+        .select((revoked_tokens::jti, revoked_tokens::expires_at))
+        .load::<(Uuid, chrono::DateTime<Utc>)>(conn)?
+        .into_iter()
+        .map(|(jti, expires_at)| (jti, expires_at.timestamp()))
+        .collect();
+
+    let sessions = sessions::table
+        .filter(sessions::revoked_at.is_not_null())
+        .filter(sessions::expires_at.gt(now - session_grace))
+        .select((sessions::id, sessions::expires_at))
+        .load::<(Uuid, chrono::DateTime<Utc>)>(conn)?
+        .into_iter()
+        .map(|(id, expires_at)| (id, expires_at.timestamp()))
+        .collect();
+
+    let clients = clients::table
+        .filter(clients::is_active.eq(false))
+        .select(clients::id)
+        .load::<Uuid>(conn)?
+        .into_iter()
+        .collect();
+
+    let tombstones = revocation_tombstones::table
+        .filter(revocation_tombstones::valid_until.gt(now - session_grace))
+        .select((revocation_tombstones::kind, revocation_tombstones::id, revocation_tombstones::valid_until))
+        .load::<(String, Uuid, chrono::DateTime<Utc>)>(conn)?;
+
+    let global_valid_after = token_epochs::table
+        .select(token_epochs::tokens_valid_after)
+        .first::<chrono::DateTime<Utc>>(conn)
+        .optional()?
+        .map(|valid_after| valid_after.timestamp());
+
+    let users_valid_after = users::table
+        .filter(users::tokens_valid_after.gt(now - leeway - ChronoDuration::seconds(CONFIG.access_token_ttl_secs)))
+        .select((users::id, users::tokens_valid_after.assume_not_null()))
+        .load::<(Uuid, chrono::DateTime<Utc>)>(conn)?
+        .into_iter()
+        .map(|(id, valid_after)| (id, valid_after.timestamp()))
+        .collect();
+
+    let mut state = RevocationState {
+        tokens,
+        sessions,
+        clients,
+        global_valid_after,
+        users_valid_after,
+    };
+    for (kind, id, valid_until) in tombstones {
+        state.add_tombstone(&kind, id, valid_until.timestamp());
+    }
+
+    Ok(state)
+}
+
+// 清理相关令牌都已自然过期的墓碑记录
+fn prune_tombstones(conn: &mut PgConnection) -> Result<(), ServiceError> {
+    use crate::db::schema::revocation_tombstones;
+
+    let grace = ChronoDuration::seconds(CONFIG.access_token_ttl_secs + CONFIG.jwt_leeway_secs as i64);
+    diesel::delete(revocation_tombstones::table.filter(revocation_tombstones::valid_until.lt(Utc::now() - grace)))
+        .execute(conn)?;
+
+    Ok(())
+}
+
+// 建立专用连接并持续监听，直到连接出错
+fn listen() -> Result<(), ServiceError> {
+    let mut conn = PgConnection::establish(&CONFIG.database_url)
+        .map_err(|e| ServiceError::DatabaseError(e.to_string()))?;
+
+    // 先 LISTEN 再加载快照，两者之间的通知会排队，重放是幂等的，不会遗漏
+    diesel::sql_query(format!("LISTEN {}", CHANNEL)).execute(&mut conn)?;
+    REVOCATIONS.replace(load_snapshot(&mut conn)?);
+    log::info!("吊销缓存已同步");
+
+    let mut last_heartbeat = Instant::now();
+    loop {
+        let notifications = conn
+            .notifications_iter()
+            .collect::<Result<Vec<_>, _>>()?;
+        for notification in notifications {
+            REVOCATIONS.apply(&notification.payload);
+        }
+
+        if last_heartbeat.elapsed() >= HEARTBEAT_INTERVAL {
+            diesel::sql_query("SELECT 1").execute(&mut conn)?;
+            prune_tombstones(&mut conn)?;
+            REVOCATIONS.prune();
+            last_heartbeat = Instant::now();
+        }
+
+        thread::sleep(POLL_INTERVAL);
+    }
+}
+
+/// 启动后台监听线程，断线后按指数退避重连，并在重连后全量重新同步
+pub fn start_listener() {
+    thread::Builder::new()
+        .name("revocation-listener".to_string())
+        .spawn(|| {
+            let mut delay = Duration::from_secs(1);
+            loop {
+                let started = Instant::now();
+                if let Err(e) = listen() {
+                    log::warn!("吊销通知连接中断: {}", e);
+                }
+                REVOCATIONS.mark_stale();
+
+                // 稳定运行过一段时间则重置退避
+                if started.elapsed() > MAX_RECONNECT_DELAY {
+                    delay = Duration::from_secs(1);
+                }
+                thread::sleep(delay);
+                delay = (delay * 2).min(MAX_RECONNECT_DELAY);
+            }
+        })
+        .expect("吊销监听线程启动失败");
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const NOW: i64 = 1_700_000_000;
+
+    fn cache(state: RevocationState) -> RevocationCache {
+        RevocationCache {
+            state: RwLock::new(state),
+            synced: AtomicBool::new(true),
+        }
+    }
+
+    fn user_claims(user_id: Uuid, iat: i64) -> Claims {
+        Claims {
+            iss: "issuer".to_string(),
+            sub: user_id.to_string(),
+            exp: iat + 600,
+            iat,
+            jti: Some(Uuid::new_v4().to_string()),
+            sid: Some(Uuid::new_v4().to_string()),
+            scope: None,
+            client_id: None,
+            sub_kind: None,
+            aud: None,
+            act: None,
+            purpose: None,
+            amr: None,
+        }
+    }
+
+    fn client_claims(client_id: Uuid) -> Claims {
+        Claims {
+            sid: None,
+            sub_kind: Some(PrincipalKind::Client),
+            ..user_claims(client_id, NOW)
+        }
+    }
+
+    #[test]
+    fn unsynced_cache_defers_to_database() {
+        let cache = cache(RevocationState::default());
+        cache.mark_stale();
+        assert_eq!(cache.is_revoked(&user_claims(Uuid::new_v4(), NOW)), None);
+    }
+
+    #[test]
+    fn unrelated_token_is_not_revoked() {
+        let cache = cache(RevocationState::default());
+        assert_eq!(cache.is_revoked(&user_claims(Uuid::new_v4(), NOW)), Some(false));
+    }
+
+    #[test]
+    fn revoked_jti_is_rejected() {
+        let cache = cache(RevocationState::default());
+        let claims = user_claims(Uuid::new_v4(), NOW);
+
+        cache.apply(&format!("jti:{}:{}", claims.jti.as_deref().unwrap(), NOW + 600));
+        assert_eq!(cache.is_revoked(&claims), Some(true));
+        assert_eq!(cache.is_revoked(&user_claims(Uuid::new_v4(), NOW)), Some(false));
+    }
+
+    #[test]
+    fn revoked_session_is_rejected() {
+        let cache = cache(RevocationState::default());
+        let claims = user_claims(Uuid::new_v4(), NOW);
+
+        cache.apply(&format!("session:{}:{}", claims.sid.as_deref().unwrap(), NOW + 3600));
+        assert_eq!(cache.is_revoked(&claims), Some(true));
+    }
+
+    #[test]
+    fn malformed_identifiers_are_rejected() {
+        let cache = cache(RevocationState::default());
+
+        let mut claims = user_claims(Uuid::new_v4(), NOW);
+        claims.jti = Some("not-a-uuid".to_string());
+        assert_eq!(cache.is_revoked(&claims), Some(true));
+
+        let mut claims = user_claims(Uuid::new_v4(), NOW);
+        claims.sid = Some("not-a-uuid".to_string());
+        assert_eq!(cache.is_revoked(&claims), Some(true));
+
+        let mut claims = client_claims(Uuid::new_v4());
+        claims.sub = "not-a-uuid".to_string();
+        assert_eq!(cache.is_revoked(&claims), Some(true));
+    }
+
+    #[test]
+    fn deactivated_client_is_rejected_until_restored() {
+        let cache = cache(RevocationState::default());
+        let client_id = Uuid::new_v4();
+        let claims = client_claims(client_id);
+
+        cache.apply(&format!("client:{}", client_id));
+        assert_eq!(cache.is_revoked(&claims), Some(true));
+
+        cache.apply(&format!("client_restored:{}", client_id));
+        assert_eq!(cache.is_revoked(&claims), Some(false));
+    }
+
+    #[test]
+    fn tombstones_revoke_deleted_sessions_and_clients() {
+        let claims = user_claims(Uuid::new_v4(), NOW);
+        let sid = Uuid::parse_str(claims.sid.as_deref().unwrap()).unwrap();
+        let client_id = Uuid::new_v4();
+
+        let mut state = RevocationState::default();
+        state.add_tombstone("session", sid, NOW + 3600);
+        state.add_tombstone("client", client_id, NOW);
+        state.add_tombstone("unknown", Uuid::new_v4(), NOW);
+        let cache = cache(state);
+
+        assert_eq!(cache.is_revoked(&claims), Some(true));
+        assert_eq!(cache.is_revoked(&client_claims(client_id)), Some(true));
+        assert_eq!(cache.is_revoked(&client_claims(Uuid::new_v4())), Some(false));
+    }
+
+    #[test]
+    fn user_valid_after_rejects_tokens_issued_at_or_before() {
+        let cache = cache(RevocationState::default());
+        let user_id = Uuid::new_v4();
+
+        cache.apply(&format!("user:{}:{}", user_id, NOW));
+        assert_eq!(cache.is_revoked(&user_claims(user_id, NOW - 1)), Some(true));
+        assert_eq!(cache.is_revoked(&user_claims(user_id, NOW)), Some(true));
+        assert_eq!(cache.is_revoked(&user_claims(user_id, NOW + 1)), Some(false));
+        assert_eq!(cache.is_revoked(&user_claims(Uuid::new_v4(), NOW - 1)), Some(false));
+    }
+
+    #[test]
+    fn user_valid_after_does_not_apply_to_clients() {
+        let cache = cache(RevocationState::default());
+        let client_id = Uuid::new_v4();
+
+        cache.apply(&format!("user:{}:{}", client_id, NOW + 1));
+        assert_eq!(cache.is_revoked(&client_claims(client_id)), Some(false));
+    }
+
+    #[test]
+    fn global_valid_after_rejects_older_tokens() {
+        let cache = cache(RevocationState::default());
+
+        cache.apply(&format!("global:{}", NOW));
+        assert_eq!(cache.is_revoked(&user_claims(Uuid::new_v4(), NOW)), Some(true));
+        assert_eq!(cache.is_revoked(&client_claims(Uuid::new_v4())), Some(true));
+        assert_eq!(cache.is_revoked(&user_claims(Uuid::new_v4(), NOW + 1)), Some(false));
+    }
+
+    #[test]
+    fn malformed_notifications_are_ignored() {
+        let cache = cache(RevocationState::default());
+        let claims = user_claims(Uuid::new_v4(), NOW);
+
+        cache.apply("global:not-a-number");
+        cache.apply("session:not-a-uuid:1");
+        cache.apply(&format!("unknown:{}:{}", Uuid::new_v4(), NOW));
+        cache.apply("");
+        assert_eq!(cache.is_revoked(&claims), Some(false));
+    }
+}
@@ -0,0 +1,122 @@
+use base64::{engine::general_purpose::STANDARD, Engine};
+use lazy_static::lazy_static;
+
+use crate::config::CONFIG;
+use crate::errors::ServiceError;
+use crate::utils::jwt::{Claims, JwtFormat};
+use crate::utils::paseto::{PasetoV4Local, PasetoV4Public};
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum TokenFormatKind {
+    Jwt,
+    PasetoV4Local,
+    PasetoV4Public,
+}
+
+impl TokenFormatKind {
+    pub fn parse(value: &str) -> Option<Self> {
+        match value.trim().to_ascii_lowercase().as_str() {
+            "jwt" => Some(TokenFormatKind::Jwt),
+            "paseto-v4-local" => Some(TokenFormatKind::PasetoV4Local),
+            "paseto-v4-public" => Some(TokenFormatKind::PasetoV4Public),
+            _ => None,
+        }
+    }
+}
+
+/// 访问令牌的编码格式
+///
+/// 实现只负责签名/加密与解析，exp、iss、aud 等声明的校验由 jwt 模块统一完成，
+/// 保证各格式的校验语义一致。
+pub trait TokenFormat: Send + Sync {
+    fn kind(&self) -> TokenFormatKind;
+
+    /// 根据令牌前缀判断是否属于该格式
+    fn matches(&self, token: &str) -> bool;
+
+    fn encode(&self, claims: &Claims) -> Result<String, ServiceError>;
+
+    fn decode(&self, token: &str) -> Result<Claims, ServiceError>;
+}
+
+/// 已启用的令牌格式及签发使用的格式
+pub struct TokenFormats {
+    issuer: TokenFormatKind,
+    enabled: Vec<Box<dyn TokenFormat>>,
+}
+
+lazy_static! {
+    pub static ref TOKEN_FORMATS: TokenFormats = TokenFormats::from_config();
+}
+
+// 读取 base64 编码的 32 字节密钥
+fn read_key(name: &str, value: Option<&String>) -> [u8; 32] {
+    let value = value.unwrap_or_else(|| panic!("启用PASETO时{}必须设置", name));
+    let bytes = STANDARD
+        .decode(value.trim())
+        .unwrap_or_else(|_| panic!("{}必须是有效的base64", name));
+
+    bytes
+        .try_into()
+        .unwrap_or_else(|_| panic!("{}必须是32字节", name))
+}
+
+fn build_format(kind: TokenFormatKind) -> Box<dyn TokenFormat> {
+    match kind {
+        TokenFormatKind::Jwt => Box::new(JwtFormat),
+        TokenFormatKind::PasetoV4Local => Box::new(PasetoV4Local::new(read_key(
+            "PASETO_LOCAL_KEY",
+            CONFIG.paseto_local_key.as_ref(),
+        ))),
+        TokenFormatKind::PasetoV4Public => Box::new(PasetoV4Public::new(read_key(
+            "PASETO_SECRET_KEY",
+            CONFIG.paseto_secret_key.as_ref(),
+        ))),
+    }
+}
+
+impl TokenFormats {
+    fn from_config() -> Self {
+        let parse = |value: &str| {
+            TokenFormatKind::parse(value).unwrap_or_else(|| panic!("未知的令牌格式: {}", value))
+        };
+
+        let issuer = parse(&CONFIG.token_format);
+        let mut kinds: Vec<TokenFormatKind> = CONFIG
+            .enabled_token_formats
+            .iter()
+            .map(|value| parse(value))
+            .collect();
+
+        // 签发格式必须能被自身接受
+        if !kinds.contains(&issuer) {
+            kinds.push(issuer);
+        }
+        kinds.dedup();
+
+        TokenFormats {
+            issuer,
+            enabled: kinds.into_iter().map(build_format).collect(),
+        }
+    }
+
+    /// 使用配置的签发格式编码声明
+    pub fn encode(&self, claims: &Claims) -> Result<String, ServiceError> {
+        self.enabled
+            .iter()
+            .find(|format| format.kind() == self.issuer)
+            .expect("签发格式必须已启用")
+            .encode(claims)
+    }
+
+    /// 按前缀选择已启用的格式解码，未启用的格式直接拒绝
+    pub fn decode(&self, token: &str) -> Result<Claims, ServiceError> {
+        let format = self
+            .enabled
+            .iter()
+            .find(|format| format.matches(token))
+            .ok_or_else(|| ServiceError::JwtError("不支持的令牌格式".to_string()))?;
+
+        format.decode(token)
+    }
+}